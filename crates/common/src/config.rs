@@ -1,12 +1,25 @@
+use crate::error::Error;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+pub use stego::CoverPattern;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClusterConfig {
     pub cluster_name: String,
     pub nodes: Vec<NodeConfig>,
     pub stego: StegoConfig,
+    #[serde(default)]
+    pub dataset: DatasetConfig,
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+    #[serde(default)]
+    pub jobs: JobsConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
     pub gui: GuiConfig,
     pub loadgen: LoadgenConfig,
 }
@@ -23,7 +36,290 @@ pub struct NodeConfig {
 pub struct StegoConfig {
     pub lsb_per_channel: u8,
     pub compress: bool,
+    /// Largest client-supplied image (cover, mask or archive cover) in pixels; 0 = unlimited
+    #[serde(default = "default_max_pixels")]
     pub max_pixels: u64,
+    #[serde(default)]
+    pub cover_pattern: CoverPattern,
+    #[serde(default)]
+    pub cover_seed: u64,
+    /// Directory of candidate covers for automatic cover selection
+    #[serde(default)]
+    pub cover_library_dir: Option<String>,
+    /// Shared key for HMAC-SHA256 payload authentication (plain CRC frames when unset)
+    #[serde(default)]
+    pub mac_key: Option<String>,
+    /// Bounds on the settings a request may override
+    #[serde(default)]
+    pub limits: StegoLimits,
+}
+
+/// Default `stego.max_pixels`: 40 MP, about 120 MB of decoded RGB
+pub const DEFAULT_MAX_PIXELS: u64 = 40_000_000;
+
+fn default_max_pixels() -> u64 {
+    DEFAULT_MAX_PIXELS
+}
+
+impl Default for StegoConfig {
+    fn default() -> Self {
+        Self {
+            lsb_per_channel: 1,
+            compress: true,
+            max_pixels: DEFAULT_MAX_PIXELS,
+            cover_pattern: CoverPattern::default(),
+            cover_seed: 0,
+            cover_library_dir: None,
+            mac_key: None,
+            limits: StegoLimits::default(),
+        }
+    }
+}
+
+/// Server-side limits on per-request `/api/embed` and `/api/extract` settings
+/// and on `/api/embed/batch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StegoLimits {
+    /// Highest `lsb_per_channel` a request may ask for
+    pub max_lsb_per_channel: u8,
+    /// Shortest traversal passphrase accepted
+    pub min_passphrase_len: usize,
+    /// Most secrets accepted in one batch
+    pub max_batch_items: usize,
+    /// Batch items embedded in parallel
+    pub batch_concurrency: usize,
+    /// Largest file a batch archive entry may inflate to
+    pub max_entry_bytes: u64,
+    /// Largest total a batch (every archive entry and file) may inflate to
+    pub max_archive_bytes: u64,
+    /// Largest total of the stego images a batch returns; items beyond it fail
+    pub max_output_bytes: u64,
+    /// Largest `/api/embed` and `/api/extract` request body, client covers included
+    pub max_request_bytes: u64,
+}
+
+impl Default for StegoLimits {
+    fn default() -> Self {
+        Self {
+            max_lsb_per_channel: 2,
+            min_passphrase_len: 8,
+            max_batch_items: 500,
+            batch_concurrency: 4,
+            max_entry_bytes: 64 * 1024 * 1024,
+            max_archive_bytes: 512 * 1024 * 1024,
+            max_output_bytes: 1024 * 1024 * 1024,
+            max_request_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+/// Room in an `/api/embed/batch` body for multipart framing, ZIP headers and
+/// settings fields on top of `max_archive_bytes`
+const BATCH_FORM_OVERHEAD_BYTES: u64 = 1024 * 1024;
+
+impl StegoLimits {
+    /// Largest `/api/embed/batch` request body accepted
+    pub fn max_batch_request_bytes(&self) -> usize {
+        self.max_archive_bytes.saturating_add(BATCH_FORM_OVERHEAD_BYTES) as usize
+    }
+
+    /// Reject byte limits of 0: a batch must always be bounded
+    pub fn validate(&self) -> crate::Result<()> {
+        for (name, value) in [
+            ("max_entry_bytes", self.max_entry_bytes),
+            ("max_archive_bytes", self.max_archive_bytes),
+            ("max_output_bytes", self.max_output_bytes),
+            ("max_request_bytes", self.max_request_bytes),
+        ] {
+            if value == 0 {
+                return Err(Error::Config(format!("stego.limits.{name} must be above 0")));
+            }
+        }
+        if self.max_entry_bytes > self.max_archive_bytes {
+            return Err(Error::Config(format!(
+                "stego.limits.max_entry_bytes ({}) exceeds max_archive_bytes ({})",
+                self.max_entry_bytes, self.max_archive_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Synthetic dataset served at `/api/dataset` for stress testing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatasetConfig {
+    /// Number of images (indices `0..count`)
+    pub count: usize,
+    pub min_width: u32,
+    pub max_width: u32,
+    pub min_height: u32,
+    pub max_height: u32,
+    /// Pattern family (defaults to `stego.cover_pattern`)
+    pub pattern: Option<CoverPattern>,
+    /// Base seed; the same seed yields the same dataset on every node
+    pub seed: u64,
+}
+
+impl Default for DatasetConfig {
+    fn default() -> Self {
+        Self {
+            count: 50,
+            min_width: 800,
+            max_width: 999,
+            min_height: 600,
+            max_height: 799,
+            pattern: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Largest dataset image side, in pixels
+pub const MAX_DATASET_SIDE: u32 = 8192;
+/// Most images a dataset may list
+pub const MAX_DATASET_COUNT: usize = 100_000;
+
+impl DatasetConfig {
+    /// Reject empty or inverted size ranges and images too large to generate on request
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.count > MAX_DATASET_COUNT {
+            return Err(Error::Config(format!(
+                "dataset.count {} exceeds {}",
+                self.count, MAX_DATASET_COUNT
+            )));
+        }
+        for (axis, min, max) in [
+            ("width", self.min_width, self.max_width),
+            ("height", self.min_height, self.max_height),
+        ] {
+            if min == 0 || min > max {
+                return Err(Error::Config(format!(
+                    "dataset.min_{axis} ({min}) must be between 1 and dataset.max_{axis} ({max})"
+                )));
+            }
+            if max > MAX_DATASET_SIDE {
+                return Err(Error::Config(format!(
+                    "dataset.max_{axis} {max} exceeds {MAX_DATASET_SIDE}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&DatasetConfig> for stego::DatasetParams {
+    fn from(config: &DatasetConfig) -> Self {
+        Self {
+            count: config.count,
+            min_width: config.min_width,
+            max_width: config.max_width,
+            min_height: config.min_height,
+            max_height: config.max_height,
+            seed: config.seed,
+        }
+    }
+}
+
+/// Responses cached for `Idempotency-Key` retries of `/api/embed` and `/api/extract`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    /// How long a key's response is replayed (0 disables caching)
+    pub ttl_secs: u64,
+    /// Largest response body replicated through the Raft log; larger ones are
+    /// replayed only by the node that produced them (0 replicates none)
+    pub max_replicated_bytes: u64,
+    /// Largest response body stored at all; larger ones are never replayed
+    pub max_stored_bytes: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 3600,
+            max_replicated_bytes: 1024 * 1024,
+            max_stored_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Retention of asynchronous `/api/jobs` jobs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// How long a finished job and its files are kept (0 = forever)
+    pub ttl_secs: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 86_400 }
+    }
+}
+
+/// API keys and allowed browser origins; authentication is off while no keys
+/// and no `peer_key` are configured
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub keys: Vec<ApiKeyConfig>,
+    /// Shared secret nodes send to each other (Raft RPCs, file copies); holds
+    /// the peer and user roles
+    pub peer_key: Option<String>,
+    /// Browser origins allowed by CORS besides the nodes' own
+    pub cors_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Label used in logs
+    pub name: String,
+    /// Hex SHA-256 of the key (`printf %s KEY | sha256sum`)
+    pub sha256: String,
+    pub roles: Vec<Role>,
+}
+
+/// Access level of an API key: `user` for `/api/*`, `operator` for `/admin/*`,
+/// `peer` for `/raft/*`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Operator,
+    Peer,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Operator => "operator",
+            Role::Peer => "peer",
+        }
+    }
+}
+
+/// Per-client limits on each node, keyed by API key (or source IP without
+/// authentication); off while no routes and no quota are configured
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub routes: Vec<RouteLimit>,
+    /// Request body bytes each client may upload per UTC day (0 = unlimited)
+    pub daily_quota_bytes: u64,
+}
+
+/// Token bucket for requests whose path starts with `prefix` (the longest
+/// matching prefix applies)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLimit {
+    pub prefix: String,
+    /// Sustained rate
+    pub requests_per_sec: f64,
+    /// Requests allowed at once after an idle period
+    pub burst: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +337,8 @@ impl ClusterConfig {
     pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         let content = fs::read_to_string(path)?;
         let config: ClusterConfig = serde_yaml::from_str(&content)?;
+        config.dataset.validate()?;
+        config.stego.limits.validate()?;
         Ok(config)
     }
 
@@ -65,3 +363,52 @@ impl ClusterConfig {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stego_limits_validation() {
+        assert!(StegoLimits::default().validate().is_ok());
+        for limits in [
+            StegoLimits { max_entry_bytes: 0, ..StegoLimits::default() },
+            StegoLimits { max_archive_bytes: 0, ..StegoLimits::default() },
+            StegoLimits { max_output_bytes: 0, ..StegoLimits::default() },
+            StegoLimits { max_request_bytes: 0, ..StegoLimits::default() },
+            StegoLimits { max_entry_bytes: 2, max_archive_bytes: 1, ..StegoLimits::default() },
+        ] {
+            assert!(matches!(limits.validate(), Err(Error::Config(_))));
+        }
+    }
+
+    #[test]
+    fn test_dataset_validation() {
+        assert!(DatasetConfig::default().validate().is_ok());
+
+        let inverted = DatasetConfig {
+            min_width: 900,
+            max_width: 800,
+            ..DatasetConfig::default()
+        };
+        assert!(matches!(inverted.validate(), Err(Error::Config(_))));
+
+        let empty = DatasetConfig {
+            min_height: 0,
+            ..DatasetConfig::default()
+        };
+        assert!(empty.validate().is_err());
+
+        let huge = DatasetConfig {
+            max_height: MAX_DATASET_SIDE + 1,
+            ..DatasetConfig::default()
+        };
+        assert!(huge.validate().is_err());
+
+        let too_many = DatasetConfig {
+            count: MAX_DATASET_COUNT + 1,
+            ..DatasetConfig::default()
+        };
+        assert!(too_many.validate().is_err());
+    }
+}
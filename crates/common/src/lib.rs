@@ -1,5 +1,5 @@
 pub mod config;
 pub mod error;
 
-pub use config::{ClusterConfig, NodeConfig, StegoConfig, GuiConfig, LoadgenConfig};
+pub use config::{ApiKeyConfig, AuthConfig, ClusterConfig, CoverPattern, DatasetConfig, IdempotencyConfig, JobsConfig, NodeConfig, RateLimitConfig, Role, RouteLimit, StegoConfig, StegoLimits, GuiConfig, LoadgenConfig};
 pub use error::{Error, Result};
@@ -2,14 +2,16 @@ pub mod metrics;
 pub mod network;
 pub mod raft;
 pub mod redirect;
+pub mod replication;
 pub mod storage;
 pub mod tasks;
 pub mod types;
 
-pub use metrics::{MetricsCollector, NodeMetrics};
+pub use metrics::{describe_raft_metrics, MetricsCollector, NodeMetrics};
 pub use network::{RaftNetworkClient, AppendEntriesRequest, AppendEntriesResponse, RequestVoteRequest, RequestVoteResponse, health_check, check_peer_health};
 pub use raft::{RaftNode, RaftNodeConfig};
 pub use redirect::{NotLeaderResponse, RetryableError};
+pub use replication::{catch_up, committed_since, propose, Uncommitted};
 pub use storage::{RaftStorage, RaftLogEntry, RaftState};
 pub use tasks::start_raft_tasks;
 pub use types::{NodeId, NodeRole, ClusterStatus, NodeStatus};
@@ -1,3 +1,4 @@
+use crate::types::NodeRole;
 use dashmap::DashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -87,3 +88,26 @@ impl Default for MetricsCollector {
         Self::new()
     }
 }
+
+/// Describe the Raft series below for the installed recorder
+pub fn describe_raft_metrics() {
+    ::metrics::describe_gauge!("raft_term", "Current Raft term of this node");
+    ::metrics::describe_gauge!("raft_role", "1 for this node's current Raft role, 0 for the others");
+    ::metrics::describe_counter!("raft_elections_started_total", "Elections this node started");
+    ::metrics::describe_counter!("raft_elections_won_total", "Elections this node won");
+    ::metrics::describe_counter!(
+        "raft_heartbeat_failures_total",
+        "Heartbeat AppendEntries RPCs to a peer that failed"
+    );
+}
+
+pub(crate) fn record_term(term: u64) {
+    ::metrics::gauge!("raft_term").set(term as f64);
+}
+
+pub(crate) fn record_role(role: &NodeRole) {
+    for candidate in [NodeRole::Leader, NodeRole::Follower, NodeRole::Candidate, NodeRole::Learner] {
+        let value = if candidate == *role { 1.0 } else { 0.0 };
+        ::metrics::gauge!("raft_role", "role" => candidate.as_str()).set(value);
+    }
+}
@@ -65,6 +65,20 @@ impl RaftNetworkClient {
         }
     }
 
+    /// Authenticate every RPC with `token` as a bearer token
+    pub fn with_auth_token(mut self, token: &str) -> anyhow::Result<Self> {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))?;
+        value.set_sensitive(true);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+
+        self.client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .default_headers(headers)
+            .build()?;
+        Ok(self)
+    }
+
     /// Send AppendEntries RPC to a peer (heartbeat or log replication)
     pub async fn send_append_entries(
         &self,
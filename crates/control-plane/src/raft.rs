@@ -2,9 +2,11 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+use crate::metrics::{record_role, record_term};
+use crate::network::{AppendEntriesRequest, AppendEntriesResponse, ConflictOpt};
 use crate::types::NodeRole;
-use crate::storage::RaftStorage;
+use crate::storage::{RaftLogEntry, RaftStorage};
 
 pub type NodeId = u64;
 pub type Node = openraft::BasicNode;
@@ -39,6 +41,7 @@ pub struct RaftNode {
     voted_for: Arc<RwLock<Option<NodeId>>>, // Track who we voted for in current term
     votes_received: Arc<RwLock<HashSet<NodeId>>>, // Track votes received as candidate
     storage: Arc<RaftStorage>, // Persistent log and state storage
+    proposal_lock: Mutex<()>, // Serializes leader appends so indices are unique
 }
 
 impl RaftNode {
@@ -49,6 +52,11 @@ impl RaftNode {
         tokio::fs::create_dir_all(&data_dir).await?;
         let storage = Arc::new(RaftStorage::new(&data_dir)?);
 
+        Ok(Self::with_storage(config, storage))
+    }
+
+    /// Build a node on top of already opened `storage`
+    pub fn with_storage(config: RaftNodeConfig, storage: Arc<RaftStorage>) -> Self {
         // Determine initial role: start all nodes as followers and allow
         // the cluster to elect a leader dynamically. Relying on a
         // hardcoded leader (node 1) makes startup order-sensitive and
@@ -59,8 +67,10 @@ impl RaftNode {
 
         let node_id = config.node_id;
         let election_timeout_ms = 50 + ((node_id * 30) % 50); // 50-100ms randomized (REDUCED for faster failover)
+        record_term(0);
+        record_role(&initial_role);
 
-        Ok(Self {
+        Self {
             config,
             current_term: Arc::new(RwLock::new(0)),
             current_role: Arc::new(RwLock::new(initial_role)),
@@ -71,7 +81,8 @@ impl RaftNode {
             voted_for: Arc::new(RwLock::new(None)),
             votes_received: Arc::new(RwLock::new(HashSet::new())),
             storage,
-        })
+            proposal_lock: Mutex::new(()),
+        }
     }
 
     /// Get current term from Raft state
@@ -84,9 +95,27 @@ impl RaftNode {
         let mut term = self.current_term.write().await;
         if new_term > *term {
             *term = new_term;
+            record_term(new_term);
         }
     }
 
+    /// Adopt a higher `term` seen in a request or response: clear this term's
+    /// vote and step down to follower. Returns whether `term` was higher.
+    pub async fn observe_term(&self, term: u64) -> bool {
+        let mut current_term = self.current_term.write().await;
+        if term <= *current_term {
+            return false;
+        }
+        *current_term = term;
+        record_term(term);
+        drop(current_term);
+
+        *self.voted_for.write().await = None;
+        *self.current_leader.write().await = None;
+        self.set_follower().await;
+        true
+    }
+
     /// Get current role of this node
     pub async fn get_role(&self) -> NodeRole {
         self.current_role.read().await.clone()
@@ -94,6 +123,7 @@ impl RaftNode {
 
     /// Update role
     pub async fn set_role(&self, role: NodeRole) {
+        record_role(&role);
         *self.current_role.write().await = role;
     }
 
@@ -106,11 +136,14 @@ impl RaftNode {
     pub async fn set_leader(&self) {
         *self.current_role.write().await = NodeRole::Leader;
         *self.current_leader.write().await = Some(self.config.node_id);
+        record_role(&NodeRole::Leader);
+        ::metrics::counter!("raft_elections_won_total").increment(1);
     }
 
     /// Demote to follower
     pub async fn set_follower(&self) {
         *self.current_role.write().await = NodeRole::Follower;
+        record_role(&NodeRole::Follower);
     }
 
     /// Get current leader
@@ -225,6 +258,9 @@ impl RaftNode {
 
         // Transition to Candidate
         *self.current_role.write().await = NodeRole::Candidate;
+        record_term(new_term);
+        record_role(&NodeRole::Candidate);
+        ::metrics::counter!("raft_elections_started_total").increment(1);
 
         // Vote for self
         *self.voted_for.write().await = Some(self.config.node_id);
@@ -276,8 +312,14 @@ impl RaftNode {
         has_majority
     }
 
-    /// Grant vote for a candidate (if we haven't voted this term)
-    pub async fn grant_vote(&self, candidate_id: NodeId, candidate_term: u64) -> bool {
+    /// Grant vote for a candidate (if we haven't voted this term and its log,
+    /// given as last index and term, is at least as up to date as ours)
+    pub async fn grant_vote(
+        &self,
+        candidate_id: NodeId,
+        candidate_term: u64,
+        candidate_last_log: (u64, u64),
+    ) -> bool {
         let current_term = self.get_term().await;
         
         // Update term if candidate has higher term
@@ -290,8 +332,10 @@ impl RaftNode {
         // Check if we can vote
         let mut voted_for = self.voted_for.write().await;
         let can_vote = voted_for.is_none() || *voted_for == Some(candidate_id);
+        let (last_index, last_term) = self.storage.last_log_info().unwrap_or((0, 0));
+        let log_up_to_date = (candidate_last_log.1, candidate_last_log.0) >= (last_term, last_index);
 
-        if can_vote && candidate_term >= current_term {
+        if can_vote && log_up_to_date && candidate_term >= current_term {
             *voted_for = Some(candidate_id);
             
             // CRITICAL: Reset heartbeat when granting vote
@@ -307,11 +351,12 @@ impl RaftNode {
             true
         } else {
             tracing::debug!(
-                "Node {} denied vote to {} for term {} (already voted for {:?})",
+                "Node {} denied vote to {} for term {} (already voted for {:?}, log up to date: {})",
                 self.config.node_id,
                 candidate_id,
                 candidate_term,
-                *voted_for
+                *voted_for,
+                log_up_to_date
             );
             false
         }
@@ -350,11 +395,120 @@ impl RaftNode {
         self.storage.get_entries(start, end)
     }
 
+    /// Hold while assigning and appending a new leader entry
+    pub async fn lock_proposals(&self) -> MutexGuard<'_, ()> {
+        self.proposal_lock.lock().await
+    }
+
+    /// Index of the highest entry known to be replicated on a majority
+    pub fn commit_index(&self) -> anyhow::Result<u64> {
+        self.storage.get_commit_index()
+    }
+
+    /// Handle an AppendEntries RPC: refuse a stale term, otherwise follow
+    /// `req.leader_id` and store its entries.
+    ///
+    /// A stale leader gets `success: false` with our term so it steps down.
+    pub async fn handle_append_entries(
+        &self,
+        req: &AppendEntriesRequest,
+    ) -> anyhow::Result<AppendEntriesResponse> {
+        let current_term = self.get_term().await;
+        if req.term < current_term {
+            tracing::debug!(
+                "Node {} rejected AppendEntries from {}: term {} is behind {}",
+                self.config.node_id,
+                req.leader_id,
+                req.term,
+                current_term
+            );
+            return Ok(AppendEntriesResponse {
+                term: current_term,
+                success: false,
+                conflict_opt: None,
+            });
+        }
+
+        let was_leader = self.is_leader().await;
+        self.observe_term(req.term).await;
+        self.set_current_leader(Some(req.leader_id)).await;
+
+        // If we're currently leader but received AppendEntries from another leader
+        // with same or higher term, we must step down (only one leader per term)
+        if was_leader && req.leader_id != self.config.node_id {
+            tracing::warn!(
+                "Node {} stepping down: received AppendEntries from {} in term {}",
+                self.config.node_id,
+                req.leader_id,
+                req.term
+            );
+            self.set_follower().await;
+
+            // When stepping down, give the new leader a grace period to establish
+            // itself: a full election timeout, so it can send heartbeats first
+            self.record_heartbeat_with_grace(self.election_timeout_ms + 50).await;
+        } else if !self.is_leader().await {
+            self.set_follower().await;
+            self.record_heartbeat().await;
+        } else {
+            // We're still leader, just record the heartbeat
+            self.record_heartbeat().await;
+        }
+
+        // Store replicated entries; a gap or term mismatch is reported as a conflict
+        let conflict_opt = self.append_from_leader(req)?;
+        Ok(AppendEntriesResponse {
+            term: self.get_term().await,
+            success: conflict_opt.is_none(),
+            conflict_opt,
+        })
+    }
+
+    /// Apply an AppendEntries request from the leader to the local log.
+    ///
+    /// Returns a conflict when the entry before `entries` is missing or from
+    /// another term; it carries our commit index, up to which our log is known
+    /// to match the leader's, so the leader can resend everything after it.
+    pub fn append_from_leader(&self, req: &AppendEntriesRequest) -> anyhow::Result<Option<ConflictOpt>> {
+        if req.prev_log_index > 0 {
+            let prev = self.storage.get_entry(req.prev_log_index)?;
+            if prev.is_none_or(|entry| entry.term != req.prev_log_term) {
+                let commit_index = self.storage.get_commit_index()?;
+                let commit_term = self.storage.get_entry(commit_index)?.map_or(0, |entry| entry.term);
+                return Ok(Some(ConflictOpt {
+                    last_log_index: commit_index,
+                    last_log_term: commit_term,
+                }));
+            }
+        }
+
+        for entry in &req.entries {
+            match self.storage.get_entry(entry.index)? {
+                Some(existing) if existing.term == entry.term => continue,
+                Some(_) => self.storage.delete_from(entry.index)?,
+                None => {}
+            }
+            self.storage.append_entry(&RaftLogEntry {
+                term: entry.term,
+                index: entry.index,
+                data: entry.data.clone(),
+            })?;
+        }
+
+        // Entries past the ones just checked may still be from a stale leader
+        let last_verified = req.prev_log_index + req.entries.len() as u64;
+        let commit_index = req.leader_commit.min(last_verified);
+        if commit_index > self.storage.get_commit_index()? {
+            self.storage.set_commit_index(commit_index)?;
+        }
+        Ok(None)
+    }
+
     /// Persist term and voted_for to storage
     pub async fn persist_state(&self) -> anyhow::Result<()> {
         let state = crate::storage::RaftState {
-            current_term: self.current_term.read().await.clone(),
-            voted_for: self.voted_for.read().await.clone(),
+            current_term: *self.current_term.read().await,
+            voted_for: *self.voted_for.read().await,
             commit_index: 0, // Will be updated with append entries
             last_applied: 0,
         };
@@ -366,6 +520,7 @@ impl RaftNode {
     pub async fn restore_state(&self) -> anyhow::Result<()> {
         if let Some(state) = self.storage.load_state()? {
             *self.current_term.write().await = state.current_term;
+            record_term(state.current_term);
             *self.voted_for.write().await = state.voted_for;
             tracing::info!("Node {} restored state from storage: term={}", self.config.node_id, state.current_term);
         }
@@ -392,3 +547,141 @@ impl RaftNode {
 // - Log replication and consistency
 // - Snapshot management
 // - Member reconfiguration
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::LogEntry;
+    use tempfile::TempDir;
+
+    fn node(node_id: NodeId) -> (RaftNode, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let storage = Arc::new(RaftStorage::new(dir.path()).unwrap());
+        let config = RaftNodeConfig {
+            node_id,
+            raft_addr: String::new(),
+            peers: Vec::new(),
+        };
+        (RaftNode::with_storage(config, storage), dir)
+    }
+
+    fn entry(index: u64, term: u64) -> LogEntry {
+        LogEntry {
+            term,
+            index,
+            data: vec![index as u8],
+        }
+    }
+
+    fn request(term: u64, prev: (u64, u64), entries: Vec<LogEntry>, leader_commit: u64) -> AppendEntriesRequest {
+        AppendEntriesRequest {
+            term,
+            leader_id: 2,
+            prev_log_index: prev.0,
+            prev_log_term: prev.1,
+            entries,
+            leader_commit,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_term_is_rejected_with_current_term() {
+        let (node, _dir) = node(1);
+        node.advance_term(3).await;
+
+        let resp = node
+            .handle_append_entries(&request(2, (0, 0), vec![entry(1, 2)], 1))
+            .await
+            .unwrap();
+        assert!(!resp.success);
+        assert_eq!(resp.term, 3);
+        assert!(resp.conflict_opt.is_none());
+        assert!(node.get_log_entry(1).await.unwrap().is_none());
+        assert_eq!(node.get_current_leader().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_newer_term_makes_leader_step_down() {
+        let (node, _dir) = node(1);
+        node.start_election().await;
+        node.set_leader().await;
+
+        let resp = node.handle_append_entries(&request(4, (0, 0), Vec::new(), 0)).await.unwrap();
+        assert!(resp.success);
+        assert_eq!(resp.term, 4);
+        assert_eq!(node.get_role().await, NodeRole::Follower);
+        assert_eq!(node.get_current_leader().await, Some(2));
+        assert_eq!(node.get_voted_for().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_observe_term_only_moves_forward() {
+        let (node, _dir) = node(1);
+        node.set_leader().await;
+        assert!(!node.observe_term(0).await);
+        assert!(node.is_leader().await);
+
+        assert!(node.observe_term(2).await);
+        assert!(!node.is_leader().await);
+        assert_eq!(node.get_term().await, 2);
+        assert!(!node.observe_term(2).await);
+    }
+
+    #[tokio::test]
+    async fn test_append_from_leader_stores_entries_and_bounds_commit() {
+        let (node, _dir) = node(1);
+
+        let req = request(1, (0, 0), vec![entry(1, 1), entry(2, 1)], 5);
+        assert!(node.append_from_leader(&req).unwrap().is_none());
+        assert_eq!(node.storage().last_log_info().unwrap(), (2, 1));
+        // Only entries checked against the leader's log may be committed
+        assert_eq!(node.commit_index().unwrap(), 2);
+
+        // A repeated request is a no-op
+        assert!(node.append_from_leader(&req).unwrap().is_none());
+        assert_eq!(node.storage().last_log_info().unwrap(), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_append_from_leader_reports_gaps_and_replaces_conflicts() {
+        let (node, _dir) = node(1);
+        let req = request(1, (0, 0), vec![entry(1, 1), entry(2, 1), entry(3, 1)], 1);
+        node.append_from_leader(&req).unwrap();
+        assert_eq!(node.commit_index().unwrap(), 1);
+
+        // Missing previous entry: report the commit index to resend from
+        let conflict = node
+            .append_from_leader(&request(2, (5, 2), vec![entry(6, 2)], 1))
+            .unwrap()
+            .expect("gap is a conflict");
+        assert_eq!((conflict.last_log_index, conflict.last_log_term), (1, 1));
+
+        // Previous entry from another term is a conflict as well
+        assert!(node
+            .append_from_leader(&request(2, (3, 2), Vec::new(), 1))
+            .unwrap()
+            .is_some());
+
+        // Entries from a newer term replace the stale suffix
+        let req = request(2, (1, 1), vec![entry(2, 2)], 2);
+        assert!(node.append_from_leader(&req).unwrap().is_none());
+        assert_eq!(node.storage().last_log_info().unwrap(), (2, 2));
+        assert!(node.get_log_entry(3).await.unwrap().is_none());
+        assert_eq!(node.commit_index().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_handle_append_entries_reports_conflict() {
+        let (node, _dir) = node(1);
+
+        let resp = node
+            .handle_append_entries(&request(1, (3, 1), vec![entry(4, 1)], 3))
+            .await
+            .unwrap();
+        assert!(!resp.success);
+        assert_eq!(resp.term, 1);
+        let conflict = resp.conflict_opt.expect("missing entries are a conflict");
+        assert_eq!(conflict.last_log_index, 0);
+        assert_eq!(node.get_current_leader().await, Some(2));
+    }
+}
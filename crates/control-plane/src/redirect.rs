@@ -12,6 +12,21 @@ pub struct NotLeaderResponse {
 pub struct RetryableError {
     pub error: String,
     pub retry_at: Option<String>,
+    /// Seconds to wait before retrying (also sent as `Retry-After`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl RetryableError {
+    /// An error worth retrying after `retry_after`, rounded up to whole seconds
+    pub fn after(error: impl Into<String>, retry_after: std::time::Duration) -> Self {
+        let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        Self {
+            error: error.into(),
+            retry_at: None,
+            retry_after_secs: Some(secs.max(1)),
+        }
+    }
 }
 
 impl NotLeaderResponse {
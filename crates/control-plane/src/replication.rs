@@ -0,0 +1,215 @@
+/// Log replication on top of the heartbeat-based leader election in `tasks`
+/// Leaders append entries and push them to followers; an entry is committed
+/// once a majority of the cluster (leader included) has stored it
+use crate::network::{AppendEntriesRequest, ConflictOpt, LogEntry, RaftNetworkClient};
+use crate::raft::RaftNode;
+use crate::storage::RaftLogEntry;
+
+impl From<RaftLogEntry> for LogEntry {
+    fn from(entry: RaftLogEntry) -> Self {
+        Self {
+            term: entry.term,
+            index: entry.index,
+            data: entry.data,
+        }
+    }
+}
+
+/// A proposal that was appended to the leader's log but not acknowledged by
+/// a majority.
+///
+/// Its outcome is unknown rather than failed: the entry stays in the log and
+/// may still be committed (by this leader's next proposal or by a later leader
+/// that stored it) or be overwritten. Callers must not report it as discarded;
+/// commands should be safe to apply even if the client retries.
+#[derive(Debug, thiserror::Error)]
+#[error("Entry {index} reached {acks}/{cluster_size} nodes, no majority yet; it may still be committed")]
+pub struct Uncommitted {
+    pub index: u64,
+    pub acks: usize,
+    pub cluster_size: usize,
+}
+
+/// Append `data` to the leader's log and replicate it.
+///
+/// Returns the entry's index once it is committed. Fails if this node is not
+/// the leader (nothing was appended), or with `Uncommitted` if fewer than a
+/// majority of nodes acknowledged the entry or a peer reported a newer term,
+/// in which case this node steps down.
+pub async fn propose(
+    raft_node: &RaftNode,
+    network: &RaftNetworkClient,
+    data: Vec<u8>,
+) -> anyhow::Result<u64> {
+    let _guard = raft_node.lock_proposals().await;
+    if !raft_node.is_leader().await {
+        anyhow::bail!("Node {} is not the leader", raft_node.node_id());
+    }
+
+    let storage = raft_node.storage();
+    let term = raft_node.get_term().await;
+    let (last_index, last_term) = storage.last_log_info()?;
+    let entry = RaftLogEntry {
+        term,
+        index: last_index + 1,
+        data,
+    };
+    storage.append_entry(&entry)?;
+
+    let req = AppendEntriesRequest {
+        term,
+        leader_id: raft_node.node_id(),
+        prev_log_index: last_index,
+        prev_log_term: last_term,
+        entries: vec![entry.clone().into()],
+        leader_commit: raft_node.commit_index()?,
+    };
+
+    let cluster_size = raft_node.peers().len() + 1;
+    let mut acks = 1; // Self
+    for (peer_id, result) in network.broadcast_append_entries(req).await {
+        match result {
+            Ok(resp) if resp.term > term => {
+                step_down(raft_node, peer_id, resp.term).await;
+                return Err(Uncommitted {
+                    index: entry.index,
+                    acks,
+                    cluster_size,
+                }
+                .into());
+            }
+            Ok(resp) if resp.success => acks += 1,
+            Ok(resp) => {
+                if let Some(conflict) = resp.conflict_opt {
+                    if catch_up(raft_node, network, peer_id, &conflict).await? {
+                        acks += 1;
+                    } else if !raft_node.is_leader().await {
+                        // The follower answered catch-up with a newer term
+                        return Err(Uncommitted {
+                            index: entry.index,
+                            acks,
+                            cluster_size,
+                        }
+                        .into());
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::debug!(
+                    "Node {} failed to replicate entry {} to {}: {}",
+                    raft_node.node_id(),
+                    entry.index,
+                    peer_id,
+                    e
+                );
+            }
+        }
+    }
+
+    if acks * 2 <= cluster_size {
+        return Err(Uncommitted {
+            index: entry.index,
+            acks,
+            cluster_size,
+        }
+        .into());
+    }
+
+    if entry.index > storage.get_commit_index()? {
+        storage.set_commit_index(entry.index)?;
+    }
+    tracing::debug!(
+        "Node {} committed entry {} ({}/{} nodes)",
+        raft_node.node_id(),
+        entry.index,
+        acks,
+        cluster_size
+    );
+    Ok(entry.index)
+}
+
+/// Resend every entry after the follower's reported commit index.
+///
+/// Returns whether the follower accepted them. A follower with a newer term
+/// refuses them and makes this node step down.
+pub async fn catch_up(
+    raft_node: &RaftNode,
+    network: &RaftNetworkClient,
+    peer_id: u64,
+    conflict: &ConflictOpt,
+) -> anyhow::Result<bool> {
+    let storage = raft_node.storage();
+    let term = raft_node.get_term().await;
+    let (last_index, _) = storage.last_log_info()?;
+    let from = conflict.last_log_index;
+    if from > last_index {
+        // The follower committed entries this leader never saw
+        return Ok(false);
+    }
+
+    let prev_log_term = storage.get_entry(from)?.map_or(0, |entry| entry.term);
+    let entries = storage
+        .get_entries(from + 1, last_index + 1)?
+        .into_iter()
+        .map(LogEntry::from)
+        .collect::<Vec<_>>();
+    let count = entries.len();
+
+    let req = AppendEntriesRequest {
+        term,
+        leader_id: raft_node.node_id(),
+        prev_log_index: from,
+        prev_log_term,
+        entries,
+        leader_commit: raft_node.commit_index()?,
+    };
+
+    let accepted = match network.send_append_entries(peer_id, req).await {
+        Ok(resp) if resp.term > term => {
+            step_down(raft_node, peer_id, resp.term).await;
+            false
+        }
+        Ok(resp) => resp.success,
+        Err(e) => {
+            tracing::debug!("Node {} failed to catch up {}: {}", raft_node.node_id(), peer_id, e);
+            false
+        }
+    };
+    if accepted {
+        tracing::info!(
+            "Node {} caught up peer {} with {} entries after index {}",
+            raft_node.node_id(),
+            peer_id,
+            count,
+            from
+        );
+    }
+    Ok(accepted)
+}
+
+/// Adopt the newer `term` reported by `peer_id`, leaving the leader role
+async fn step_down(raft_node: &RaftNode, peer_id: u64, term: u64) {
+    if raft_node.observe_term(term).await {
+        tracing::warn!(
+            "Node {} stepping down: peer {} has higher term {}",
+            raft_node.node_id(),
+            peer_id,
+            term
+        );
+    }
+}
+
+/// Up to `max_entries` committed entries after `last_applied`, in log order;
+/// callers page through a long log instead of loading all of it
+pub fn committed_since(
+    raft_node: &RaftNode,
+    last_applied: u64,
+    max_entries: usize,
+) -> anyhow::Result<Vec<RaftLogEntry>> {
+    let commit_index = raft_node.commit_index()?;
+    if commit_index <= last_applied {
+        return Ok(Vec::new());
+    }
+    let end = commit_index.min(last_applied.saturating_add(max_entries as u64));
+    raft_node.storage().get_entries(last_applied + 1, end + 1)
+}
@@ -42,9 +42,26 @@ impl RaftStorage {
         let key = format!("log:{}", entry.index);
         let value = serde_json::to_vec(entry)?;
         self.db.put(key.as_bytes(), &value)?;
+        if entry.index > self.last_index()? {
+            self.db.put(b"last_index", entry.index.to_le_bytes().as_ref())?;
+        }
         Ok(())
     }
 
+    /// Index of the last stored log entry (0 if empty)
+    fn last_index(&self) -> anyhow::Result<u64> {
+        match self.db.get(b"last_index")? {
+            Some(data) => {
+                let bytes: [u8; 8] = data
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("Invalid last_index data"))?;
+                Ok(u64::from_le_bytes(bytes))
+            }
+            None => Ok(0),
+        }
+    }
+
     /// Get a log entry by index
     pub fn get_entry(&self, index: u64) -> anyhow::Result<Option<RaftLogEntry>> {
         let key = format!("log:{}", index);
@@ -72,13 +89,11 @@ impl RaftStorage {
 
     /// Get last log index and term
     pub fn last_log_info(&self) -> anyhow::Result<(u64, u64)> {
-        // Scan from a high number backwards to find last entry
-        for index in (0..=1_000_000u64).rev() {
-            if let Some(entry) = self.get_entry(index)? {
-                return Ok((index, entry.term));
-            }
+        let index = self.last_index()?;
+        match self.get_entry(index)? {
+            Some(entry) => Ok((index, entry.term)),
+            None => Ok((0, 0)),
         }
-        Ok((0, 0))
     }
 
     /// Delete entries from index onwards (used for log truncation)
@@ -88,7 +103,11 @@ impl RaftStorage {
             let key = format!("log:{}", i);
             let _ = self.db.delete(key.as_bytes());
         }
-        
+
+        if index <= self.last_index()? {
+            let last = index.saturating_sub(1);
+            self.db.put(b"last_index", last.to_le_bytes().as_ref())?;
+        }
         Ok(())
     }
 
@@ -209,6 +228,23 @@ mod tests {
         assert_eq!(retrieved.unwrap(), entry);
     }
 
+    #[test]
+    fn test_last_log_info_tracks_appends_and_truncation() {
+        let dir = TempDir::new().unwrap();
+        let storage = RaftStorage::new(dir.path()).unwrap();
+        assert_eq!(storage.last_log_info().unwrap(), (0, 0));
+
+        for (index, term) in [(1, 1), (2, 1), (3, 2)] {
+            let entry = RaftLogEntry { term, index, data: Vec::new() };
+            storage.append_entry(&entry).unwrap();
+        }
+        assert_eq!(storage.last_log_info().unwrap(), (3, 2));
+
+        storage.delete_from(2).unwrap();
+        assert_eq!(storage.last_log_info().unwrap(), (1, 1));
+        assert!(storage.get_entry(3).unwrap().is_none());
+    }
+
     #[test]
     fn test_state_persistence() {
         let dir = TempDir::new().unwrap();
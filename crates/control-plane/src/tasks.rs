@@ -2,6 +2,7 @@
 /// Handles election monitoring, heartbeat transmission, and failover
 use crate::network::{AppendEntriesRequest, RaftNetworkClient, RequestVoteRequest};
 use crate::raft::RaftNode;
+use crate::replication::catch_up;
 use crate::types::NodeRole;
 use std::sync::Arc;
 use std::time::Duration;
@@ -109,11 +110,13 @@ pub fn start_election_monitor(raft_node: Arc<RaftNode>, network: Arc<RaftNetwork
                 );
 
                 // Broadcast RequestVote to all peers
+                let (last_log_index, last_log_term) =
+                    raft_node.storage().last_log_info().unwrap_or((0, 0));
                 let req = RequestVoteRequest {
                     term: new_term,
                     candidate_id: raft_node.node_id(),
-                    last_log_index: 0, // Simplified for Phase-2
-                    last_log_term: 0,
+                    last_log_index,
+                    last_log_term,
                 };
 
                 let results = network.broadcast_request_vote(req).await;
@@ -222,14 +225,17 @@ pub fn start_heartbeat_sender(raft_node: Arc<RaftNode>, network: Arc<RaftNetwork
                 continue;
             }
 
+            // Heartbeats point at our last entry so lagging followers report a conflict
             let term = raft_node.get_term().await;
+            let (prev_log_index, prev_log_term) =
+                raft_node.storage().last_log_info().unwrap_or((0, 0));
             let req = AppendEntriesRequest {
                 term,
                 leader_id: raft_node.node_id(),
-                prev_log_index: 0,
-                prev_log_term: 0,
+                prev_log_index,
+                prev_log_term,
                 entries: Vec::new(), // Empty for heartbeat
-                leader_commit: 0,
+                leader_commit: raft_node.commit_index().unwrap_or(0),
             };
 
             let results = network.broadcast_append_entries(req).await;
@@ -242,6 +248,16 @@ pub fn start_heartbeat_sender(raft_node: Arc<RaftNode>, network: Arc<RaftNetwork
                     Ok(resp) => {
                         if resp.success {
                             success_count += 1;
+                        } else if let Some(conflict) = resp.conflict_opt.filter(|_| resp.term <= term) {
+                            // Follower is missing entries: resend them
+                            if let Err(e) = catch_up(&raft_node, &network, peer_id, &conflict).await {
+                                tracing::warn!(
+                                    "Node {} failed to catch up {}: {}",
+                                    raft_node.node_id(),
+                                    peer_id,
+                                    e
+                                );
+                            }
                         } else if resp.term > term {
                             // Another node has higher term, step down
                             tracing::warn!(
@@ -251,10 +267,12 @@ pub fn start_heartbeat_sender(raft_node: Arc<RaftNode>, network: Arc<RaftNetwork
                                 resp.term
                             );
                             step_down = true;
-                            raft_node.advance_term(resp.term).await;
+                            raft_node.observe_term(resp.term).await;
                         }
                     }
                     Err(e) => {
+                        ::metrics::counter!("raft_heartbeat_failures_total", "peer" => peer_id.to_string())
+                            .increment(1);
                         tracing::debug!(
                             "Node {} failed to send heartbeat to {}: {}",
                             raft_node.node_id(),
@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod replication_tests {
+    use control_plane::network::{AppendEntriesRequest, ConflictOpt};
+    use control_plane::{
+        catch_up, committed_since, propose, RaftLogEntry, RaftNetworkClient, RaftNode, RaftNodeConfig, RaftStorage,
+        Uncommitted,
+    };
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn node(node_id: u64, peers: Vec<(u64, String)>) -> (Arc<RaftNode>, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let storage = Arc::new(RaftStorage::new(dir.path()).unwrap());
+        let config = RaftNodeConfig {
+            node_id,
+            raft_addr: String::new(),
+            peers,
+        };
+        (Arc::new(RaftNode::with_storage(config, storage)), dir)
+    }
+
+    async fn leader(peers: Vec<(u64, String)>) -> (Arc<RaftNode>, RaftNetworkClient, TempDir) {
+        let (node, dir) = node(1, peers.clone());
+        node.start_election().await;
+        node.set_leader().await;
+        (node, RaftNetworkClient::new(peers), dir)
+    }
+
+    /// Answer `/raft/append-entries` for `node`, one request per connection
+    async fn serve(node: Arc<RaftNode>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let node = node.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let (body_start, body_len) = loop {
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head = String::from_utf8_lossy(&buf[..end]).to_ascii_lowercase();
+                            let len = head
+                                .lines()
+                                .find_map(|line| line.strip_prefix("content-length:"))
+                                .map_or(0, |len| len.trim().parse().unwrap());
+                            break (end + 4, len);
+                        }
+                    };
+                    while buf.len() < body_start + body_len {
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+
+                    let req: AppendEntriesRequest =
+                        serde_json::from_slice(&buf[body_start..body_start + body_len]).unwrap();
+                    let body = serde_json::to_vec(&node.handle_append_entries(&req).await.unwrap()).unwrap();
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                });
+            }
+        });
+        addr
+    }
+
+    /// An address nothing listens on
+    async fn unreachable() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_propose_commits_on_majority() {
+        let (follower, _follower_dir) = node(2, Vec::new());
+        let peers = vec![(2, serve(follower.clone()).await), (3, unreachable().await)];
+        let (leader, network, _dir) = leader(peers).await;
+
+        assert_eq!(propose(&leader, &network, b"first".to_vec()).await.unwrap(), 1);
+        assert_eq!(propose(&leader, &network, b"second".to_vec()).await.unwrap(), 2);
+
+        assert_eq!(leader.commit_index().unwrap(), 2);
+        assert_eq!(follower.storage().last_log_info().unwrap(), (2, 1));
+        assert_eq!(follower.get_log_entry(2).await.unwrap().unwrap().data, b"second");
+        // The follower learns of the first commit with the second entry
+        assert_eq!(follower.commit_index().unwrap(), 1);
+        assert_eq!(follower.get_current_leader().await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_committed_since_pages_through_the_log() {
+        let (follower, _follower_dir) = node(2, Vec::new());
+        let peers = vec![(2, serve(follower.clone()).await)];
+        let (leader, network, _dir) = leader(peers).await;
+        for data in [b"a", b"b", b"c"] {
+            propose(&leader, &network, data.to_vec()).await.unwrap();
+        }
+
+        let page = |from| -> Vec<u64> {
+            committed_since(&leader, from, 2).unwrap().iter().map(|e| e.index).collect()
+        };
+        assert_eq!(page(0), vec![1, 2]);
+        assert_eq!(page(2), vec![3]);
+        assert!(page(3).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_propose_without_majority_is_uncommitted() {
+        let peers = vec![(2, unreachable().await), (3, unreachable().await)];
+        let (leader, network, _dir) = leader(peers).await;
+
+        let err = propose(&leader, &network, b"lost".to_vec()).await.unwrap_err();
+        let uncommitted = err.downcast_ref::<Uncommitted>().expect("outcome is unknown, not failed");
+        assert_eq!((uncommitted.index, uncommitted.acks, uncommitted.cluster_size), (1, 1, 3));
+
+        // The entry stays in the log, uncommitted
+        assert_eq!(leader.storage().last_log_info().unwrap(), (1, 1));
+        assert_eq!(leader.commit_index().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_propose_requires_leader() {
+        let (node, _dir) = node(1, Vec::new());
+        let network = RaftNetworkClient::new(Vec::new());
+
+        let err = propose(&node, &network, b"x".to_vec()).await.unwrap_err();
+        assert!(err.downcast_ref::<Uncommitted>().is_none());
+        assert_eq!(node.storage().last_log_info().unwrap(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_propose_steps_down_on_newer_term() {
+        let (follower, _follower_dir) = node(2, Vec::new());
+        follower.advance_term(5).await;
+        let peers = vec![(2, serve(follower.clone()).await)];
+        let (leader, network, _dir) = leader(peers).await;
+
+        let err = propose(&leader, &network, b"stale".to_vec()).await.unwrap_err();
+        assert!(err.downcast_ref::<Uncommitted>().is_some());
+        assert!(!leader.is_leader().await);
+        assert_eq!(leader.get_term().await, 5);
+        assert!(follower.get_log_entry(1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_resends_entries_after_commit_index() {
+        let (follower, _follower_dir) = node(2, Vec::new());
+        let peers = vec![(2, serve(follower.clone()).await)];
+        let (leader, network, _dir) = leader(peers).await;
+        for index in 1..=3 {
+            let entry = RaftLogEntry {
+                term: 1,
+                index,
+                data: vec![index as u8],
+            };
+            leader.append_log_entry(entry).await.unwrap();
+        }
+
+        let conflict = ConflictOpt {
+            last_log_index: 0,
+            last_log_term: 0,
+        };
+        assert!(catch_up(&leader, &network, 2, &conflict).await.unwrap());
+        assert_eq!(follower.storage().last_log_info().unwrap(), (3, 1));
+
+        // A follower claiming more than the leader has cannot be caught up
+        let ahead = ConflictOpt {
+            last_log_index: 7,
+            last_log_term: 1,
+        };
+        assert!(!catch_up(&leader, &network, 2, &ahead).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_steps_down_on_newer_term() {
+        let (follower, _follower_dir) = node(2, Vec::new());
+        follower.advance_term(3).await;
+        let peers = vec![(2, serve(follower.clone()).await)];
+        let (leader, network, _dir) = leader(peers).await;
+
+        let conflict = ConflictOpt {
+            last_log_index: 0,
+            last_log_term: 0,
+        };
+        assert!(!catch_up(&leader, &network, 2, &conflict).await.unwrap());
+        assert!(!leader.is_leader().await);
+        assert_eq!(leader.get_term().await, 3);
+    }
+}
@@ -23,6 +23,10 @@ struct Args {
 
     #[arg(long)]
     duration_secs: Option<u64>,
+
+    /// API key with the user role, for clusters with authentication enabled
+    #[arg(long)]
+    api_key: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -92,10 +96,23 @@ async fn main() -> Result<()> {
     println!("  Servers: {:?}", servers);
     println!();
 
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(key) = &args.api_key {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", key))?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(30))
+        .default_headers(headers)
         .build()?;
 
+    // Dataset is generated deterministically from the cluster config, so every
+    // node serves the same images and runs are reproducible
+    let dataset_count = fetch_dataset_count(&client, &servers[0]).await?;
+    println!("Dataset: {} images", dataset_count);
+
     let global_stats = Arc::new(Mutex::new(Stats::new()));
     let start_time = Instant::now();
 
@@ -117,8 +134,8 @@ async fn main() -> Result<()> {
 
                 let req_start = Instant::now();
                 let success = match mode_clone.as_str() {
-                    "embed" => perform_embed(&client_clone, server, req_id).await.is_ok(),
-                    "extract" => perform_extract(&client_clone, server, req_id).await.is_ok(),
+                    "embed" => perform_embed(&client_clone, server, req_id % dataset_count).await.is_ok(),
+                    "extract" => perform_extract(&client_clone, server, req_id % dataset_count).await.is_ok(),
                     _ => false,
                 };
                 let latency_ms = req_start.elapsed().as_secs_f64() * 1000.0;
@@ -155,16 +172,33 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn perform_embed(client: &reqwest::Client, server: &str, index: usize) -> Result<()> {
+async fn fetch_dataset_count(client: &reqwest::Client, server: &str) -> Result<usize> {
+    let listing: serde_json::Value = client
+        .get(format!("{}/api/dataset", server))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    match listing.get("count").and_then(|v| v.as_u64()) {
+        Some(count) if count > 0 => Ok(count as usize),
+        _ => anyhow::bail!("Server reports an empty dataset"),
+    }
+}
+
+async fn perform_embed(client: &reqwest::Client, server: &str, dataset_idx: usize) -> Result<()> {
     // Get a dataset image
-    let dataset_idx = index % 50;
     let dataset_url = format!("{}/api/dataset/{}", server, dataset_idx);
     let img_bytes = client.get(&dataset_url).send().await?.bytes().await?;
 
-    // Upload for embedding with retry logic for NotLeader
+    // Upload for embedding with retry logic for NotLeader; retries reuse the
+    // idempotency key so the cluster can tell them from new work
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
     let mut current_server = server.to_string();
     let mut retries = 0;
     const MAX_RETRIES: usize = 3;
+    const MAX_RETRY_AFTER_SECS: u64 = 10;
 
     loop {
         let form = multipart::Form::new().part(
@@ -175,13 +209,33 @@ async fn perform_embed(client: &reqwest::Client, server: &str, index: usize) ->
         );
 
         let embed_url = format!("{}/api/embed", current_server);
-        let resp = client.post(&embed_url).multipart(form).send().await?;
+        let resp = client
+            .post(&embed_url)
+            .header("Idempotency-Key", &idempotency_key)
+            .multipart(form)
+            .send()
+            .await?;
 
         let status = resp.status();
         if status.is_success() {
             return Ok(());
         }
 
+        // Rate limited: wait as told and resend (quota resets hours away are not worth it)
+        if status.as_u16() == 429 && retries < MAX_RETRIES {
+            let wait = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(1);
+            if wait <= MAX_RETRY_AFTER_SECS {
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                retries += 1;
+                continue;
+            }
+        }
+
         // Check if NotLeader (307 Temporary Redirect)
         if status.as_u16() == 307 && retries < MAX_RETRIES {
             // Try to parse redirect response
@@ -199,11 +253,10 @@ async fn perform_embed(client: &reqwest::Client, server: &str, index: usize) ->
     }
 }
 
-async fn perform_extract(client: &reqwest::Client, server: &str, index: usize) -> Result<()> {
+async fn perform_extract(client: &reqwest::Client, server: &str, dataset_idx: usize) -> Result<()> {
     // For extract, we need a stego image
     // In a real test, we'd first embed then extract
     // For simplicity, we'll just use a dataset image (will fail CRC but still exercises the path)
-    let dataset_idx = index % 50;
     let dataset_url = format!("{}/api/dataset/{}", server, dataset_idx);
     let img_bytes = client.get(&dataset_url).send().await?.bytes().await?;
 
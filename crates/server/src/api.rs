@@ -1,19 +1,27 @@
 use axum::{
-    extract::{Multipart, Path, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
+    body::Body,
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{AppendHeaders, Html, IntoResponse, Response},
     Json,
 };
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use image::ImageFormat;
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use uuid::Uuid;
 
+use crate::batch;
+use crate::cover_registry::CoverMeta;
+use crate::jobs::{JobKind, JobRecord, JobSpec, JobState};
+use crate::replication::{self, Command, CoverCommand, JobCommand};
 use crate::state::AppState;
-use control_plane::{ClusterStatus, NodeRole, NodeStatus, NotLeaderResponse};
+use crate::telemetry;
+use common::{StegoConfig, StegoLimits};
+use control_plane::{ClusterStatus, NodeRole, NodeStatus, NotLeaderResponse, RetryableError};
 
 // ============================================================================
 // Helper: Check if this node is leader
@@ -23,7 +31,7 @@ async fn check_is_leader(state: &Arc<AppState>) -> Result<(), AppError> {
     if !state.raft_node.is_leader().await {
         let current_leader = state.raft_node.get_current_leader().await;
         let leader_addr = current_leader.and_then(|lid| {
-            state.config.find_node_by_id(lid as u64).map(|n| format!("{}:{}", n.ip, n.http_port))
+            state.config.find_node_by_id(lid).map(|n| format!("{}:{}", n.ip, n.http_port))
         });
 
         let response = NotLeaderResponse::new(current_leader, leader_addr);
@@ -32,6 +40,347 @@ async fn check_is_leader(state: &Arc<AppState>) -> Result<(), AppError> {
     Ok(())
 }
 
+// ============================================================================
+// Helper: Content negotiation for binary responses
+// ============================================================================
+
+/// Whether the `Accept` header ranks one of `binary_types` above JSON.
+///
+/// Each type takes the q-value of its most specific matching range (exact,
+/// then `type/*`, then `*/*`), so `image/*` selects PNG and `q=0` rules a
+/// type out. JSON stays the default: no `Accept` header or a tie keep the
+/// JSON body.
+fn prefers_binary(headers: &HeaderMap, binary_types: &[&str]) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    let ranges: Vec<(String, f32)> = accept
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let media_range = params.next()?.trim().to_ascii_lowercase();
+            if media_range.is_empty() {
+                return None;
+            }
+            let q = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0)
+                .clamp(0.0, 1.0);
+            Some((media_range, q))
+        })
+        .collect();
+
+    let binary_q = binary_types
+        .iter()
+        .filter_map(|media_type| accept_quality(&ranges, media_type))
+        .fold(0.0f32, f32::max);
+    let json_q = accept_quality(&ranges, "application/json").unwrap_or(0.0);
+
+    binary_q > json_q
+}
+
+/// q-value of the most specific range in `ranges` matching `media_type`
+fn accept_quality(ranges: &[(String, f32)], media_type: &str) -> Option<f32> {
+    let main_type = media_type.split('/').next().unwrap_or("");
+    ranges
+        .iter()
+        .filter_map(|(range, q)| {
+            let specificity = if range == media_type {
+                3
+            } else if range.strip_suffix("/*") == Some(main_type) {
+                2
+            } else if range == "*/*" {
+                1
+            } else {
+                return None;
+            };
+            Some((specificity, *q))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map(|(_, q)| q)
+}
+
+/// Chunk size for streamed binary bodies
+const BINARY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Raw response body with metadata in headers (`X-Request-Id` etc.) and a
+/// download filename, streamed to the client in `BINARY_CHUNK_SIZE` chunks
+fn binary_response<K>(
+    content_type: &str,
+    filename: &str,
+    metadata: Vec<(K, String)>,
+    body: impl Into<bytes::Bytes>,
+) -> Response
+where
+    K: TryInto<HeaderName>,
+    K::Error: std::fmt::Display,
+{
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+    let headers = [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+    ];
+
+    let mut remaining: bytes::Bytes = body.into();
+    let chunks = std::iter::from_fn(move || {
+        if remaining.is_empty() {
+            return None;
+        }
+        let len = remaining.len().min(BINARY_CHUNK_SIZE);
+        Some(Ok::<_, std::convert::Infallible>(remaining.split_to(len)))
+    });
+    let body = Body::from_stream(futures_util::stream::iter(chunks));
+
+    (headers, AppendHeaders(metadata), body).into_response()
+}
+
+// ============================================================================
+// Helper: Per-request stego settings
+// ============================================================================
+
+/// Optional multipart fields overriding `StegoConfig` for one embed/extract request
+#[derive(Default)]
+struct SettingsFields {
+    lsb_per_channel: Option<String>,
+    compress: Option<String>,
+    codec: Option<String>,
+    passphrase: Option<String>,
+    output_format: Option<String>,
+}
+
+impl SettingsFields {
+    const NAMES: [&'static str; 5] = [
+        "lsb_per_channel",
+        "compress",
+        "codec",
+        "passphrase",
+        "output_format",
+    ];
+
+    fn set(&mut self, name: &str, text: String) {
+        let slot = match name {
+            "lsb_per_channel" => &mut self.lsb_per_channel,
+            "compress" => &mut self.compress,
+            "codec" => &mut self.codec,
+            "passphrase" => &mut self.passphrase,
+            "output_format" => &mut self.output_format,
+            _ => return,
+        };
+        *slot = Some(text);
+    }
+
+    /// Apply the fields on top of the server configuration, rejecting values
+    /// outside `stego.limits`
+    fn resolve(self, config: &StegoConfig) -> Result<RequestSettings, AppError> {
+        let limits = &config.limits;
+
+        // A passphrase selects the keyed path, which is 1 LSB per channel, so
+        // only an explicit request for more bits conflicts with it
+        let lsb_per_channel = match self.lsb_per_channel {
+            Some(text) => {
+                let lsb = text.trim().parse::<u8>().ok();
+                match lsb {
+                    Some(lsb) if (1..=limits.max_lsb_per_channel).contains(&lsb) => lsb,
+                    _ => {
+                        return Err(AppError::BadRequest(format!(
+                            "lsb_per_channel must be between 1 and {}, got {}",
+                            limits.max_lsb_per_channel, text
+                        )))
+                    }
+                }
+            }
+            None if self.passphrase.is_some() => 1,
+            None => config.lsb_per_channel,
+        };
+
+        let compress = match self.compress.as_deref().map(str::trim) {
+            Some("true" | "1") => Some(true),
+            Some("false" | "0") => Some(false),
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid compress: {} (expected \"true\" or \"false\")",
+                    other
+                )))
+            }
+            None => None,
+        };
+        let codec = match self.codec.as_deref().map(str::trim) {
+            Some("deflate") => Some(true),
+            Some("none") => Some(false),
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Unknown codec: {} (expected \"deflate\" or \"none\")",
+                    other
+                )))
+            }
+            None => None,
+        };
+        let compression = match (compress, codec) {
+            (Some(a), Some(b)) if a != b => {
+                return Err(AppError::BadRequest(
+                    "compress and codec disagree".to_string(),
+                ))
+            }
+            (compress, codec) => compress.or(codec).unwrap_or(config.compress).into(),
+        };
+
+        if let Some(passphrase) = &self.passphrase {
+            if passphrase.chars().count() < limits.min_passphrase_len {
+                return Err(AppError::BadRequest(format!(
+                    "passphrase must be at least {} characters",
+                    limits.min_passphrase_len
+                )));
+            }
+            if lsb_per_channel != 1 {
+                return Err(AppError::BadRequest(
+                    "passphrase requires lsb_per_channel=1".to_string(),
+                ));
+            }
+        }
+
+        let output_format = match self.output_format.as_deref().map(str::trim) {
+            Some("json") => Some(OutputFormat::Json),
+            Some("binary") => Some(OutputFormat::Binary),
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Unknown output_format: {} (expected \"json\" or \"binary\")",
+                    other
+                )))
+            }
+            None => None,
+        };
+
+        Ok(RequestSettings {
+            lsb_per_channel,
+            compression,
+            passphrase: self.passphrase,
+            mac_key: config.mac_key.clone(),
+            output_format,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    Binary,
+}
+
+impl OutputFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Binary => "binary",
+        }
+    }
+}
+
+/// Effective settings for one request: configuration plus validated overrides
+struct RequestSettings {
+    lsb_per_channel: u8,
+    compression: stego::Compression,
+    passphrase: Option<String>,
+    mac_key: Option<String>,
+    /// Explicit `output_format`; `None` negotiates via `Accept`
+    output_format: Option<OutputFormat>,
+}
+
+/// Embed options for the configured `stego` defaults (bit depth, codec, MAC key)
+pub fn embed_options(config: &StegoConfig) -> stego::EmbedOptions {
+    let options = stego::EmbedOptions::new()
+        .lsb_per_channel(config.lsb_per_channel)
+        .compression(config.compress.into());
+    match &config.mac_key {
+        Some(key) => options.mac_key(key.as_bytes()),
+        None => options,
+    }
+}
+
+/// Extract options matching `embed_options`
+pub fn extract_options(config: &StegoConfig) -> stego::ExtractOptions {
+    let options = stego::ExtractOptions::new()
+        .lsb_per_channel(config.lsb_per_channel)
+        .compression(config.compress.into());
+    match &config.mac_key {
+        Some(key) => options.mac_key(key.as_bytes()),
+        None => options,
+    }
+}
+
+impl RequestSettings {
+    fn embed_options(&self) -> stego::EmbedOptions {
+        let mut options = stego::EmbedOptions::new()
+            .lsb_per_channel(self.lsb_per_channel)
+            .compression(self.compression);
+        if let Some(passphrase) = &self.passphrase {
+            options = options.traversal_key(passphrase.as_str());
+        }
+        if let Some(key) = &self.mac_key {
+            options = options.mac_key(key.as_bytes());
+        }
+        options
+    }
+
+    fn extract_options(&self) -> stego::ExtractOptions {
+        let mut options = stego::ExtractOptions::new()
+            .lsb_per_channel(self.lsb_per_channel)
+            .compression(self.compression);
+        if let Some(passphrase) = &self.passphrase {
+            options = options.traversal_key(passphrase.as_str());
+        }
+        if let Some(key) = &self.mac_key {
+            options = options.mac_key(key.as_bytes());
+        }
+        options
+    }
+
+    /// Explicit `output_format`, else `Accept` negotiation against `binary_types`
+    fn output_format(&self, headers: &HeaderMap, binary_types: &[&str]) -> OutputFormat {
+        self.output_format.unwrap_or(if prefers_binary(headers, binary_types) {
+            OutputFormat::Binary
+        } else {
+            OutputFormat::Json
+        })
+    }
+
+    /// Settings echoed back to the client (the passphrase itself never is)
+    fn echo(&self, output_format: OutputFormat) -> SettingsResponse {
+        SettingsResponse {
+            lsb_per_channel: self.lsb_per_channel,
+            codec: match self.compression {
+                stego::Compression::Deflate => "deflate",
+                stego::Compression::None => "none",
+            },
+            keyed: self.passphrase.is_some(),
+            authenticated: self.mac_key.is_some(),
+            output_format: output_format.as_str(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SettingsResponse {
+    lsb_per_channel: u8,
+    codec: &'static str,
+    keyed: bool,
+    authenticated: bool,
+    output_format: &'static str,
+}
+
+impl SettingsResponse {
+    /// The same settings as `X-*` headers for binary responses
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("x-settings-lsb-per-channel", self.lsb_per_channel.to_string()),
+            ("x-settings-codec", self.codec.to_string()),
+            ("x-settings-keyed", self.keyed.to_string()),
+            ("x-settings-authenticated", self.authenticated.to_string()),
+        ]
+    }
+}
+
 // ============================================================================
 // Embed Handler
 // ============================================================================
@@ -39,7 +388,10 @@ async fn check_is_leader(state: &Arc<AppState>) -> Result<(), AppError> {
 #[derive(Serialize)]
 pub struct EmbedResponse {
     request_id: String,
+    cover_id: String,
+    carrier: String,
     cover_info: CoverInfoResponse,
+    settings: SettingsResponse,
     secret_size_bytes: u64,
     payload_size_bytes: u64,
     stego_image_b64: String,
@@ -55,60 +407,1004 @@ pub struct CoverInfoResponse {
     capacity_bytes: u64,
 }
 
+/// Returns JSON with the PNG base64-encoded, or the raw PNG with metadata in
+/// `X-*` headers when `Accept` prefers `image/png` or `application/octet-stream`
 pub async fn embed_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Response, AppError> {
+    let start = Instant::now();
+
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+
+    // Extract uploaded file, optional cover image, cover mode ("default", "auto" or
+    // "client"), carrier, mask and settings fields
+    let mut secret_bytes = None;
+    let mut cover_bytes = None;
+    let mut cover_mode = None;
+    let mut carrier = None;
+    let mut mask_channels = None;
+    let mut mask_regions = None;
+    let mut mask_image = None;
+    let mut settings_fields = SettingsFields::default();
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::BadRequest(format!("Failed to read multipart: {}", e))
+    })? {
+        match field.name() {
+            Some("file") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+                })?;
+                secret_bytes = Some(data.to_vec());
+            }
+            Some("cover") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read cover bytes: {}", e))
+                })?;
+                cover_bytes = Some(data.to_vec());
+            }
+            Some("cover_mode") => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read cover_mode: {}", e))
+                })?;
+                cover_mode = Some(text);
+            }
+            Some("carrier") => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read carrier: {}", e))
+                })?;
+                carrier = Some(text);
+            }
+            Some("mask_channels") => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read mask_channels: {}", e))
+                })?;
+                mask_channels = Some(text);
+            }
+            Some("mask_regions") => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read mask_regions: {}", e))
+                })?;
+                mask_regions = Some(text);
+            }
+            Some("mask_image") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read mask_image bytes: {}", e))
+                })?;
+                mask_image = Some(data.to_vec());
+            }
+            Some(name) if SettingsFields::NAMES.contains(&name) => {
+                let name = name.to_string();
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read {}: {}", name, e))
+                })?;
+                settings_fields.set(&name, text);
+            }
+            _ => {}
+        }
+    }
+
+    // Server configuration (authenticated when a shared MAC key is set) with the
+    // request's overrides
+    let settings = settings_fields.resolve(&state.config.stego)?;
+    let output_format = settings.output_format(&headers, &["image/png", "application/octet-stream"]);
+    let settings_echo = settings.echo(output_format);
+
+    let secret_bytes = secret_bytes.ok_or_else(|| {
+        AppError::BadRequest("No file field found in multipart data".to_string())
+    })?;
+    let secret_size = secret_bytes.len() as u64;
+
+    let default_mode = if cover_bytes.is_some() { "client" } else { "default" };
+    let cover_mode = cover_mode.unwrap_or_else(|| default_mode.to_string());
+    // A managed default cover (identical on every node) reports its id
+    let default_cover = match cover_mode.as_str() {
+        "default" if cover_bytes.is_none() => Some((
+            state.covers.default_id().await.unwrap_or_else(|| "default".to_string()),
+            state.cover_image.read().await.clone(),
+        )),
+        _ => None,
+    };
+
+    let request = EmbedRequest {
+        settings,
+        secret_bytes,
+        cover_bytes,
+        cover_mode,
+        default_cover,
+        carrier,
+        mask_channels,
+        mask_regions,
+        mask_image,
+    };
+    let task_state = state.clone();
+    let EmbeddedImage {
+        png_bytes,
+        cover_info,
+        cover_id,
+        carrier,
+    } = blocking(move || embed_image(&task_state, request)).await?;
+    let payload_size = png_bytes.len() as u64;
+
+    let request_id = Uuid::new_v4().to_string();
+
+    // Record metrics
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    state.metrics.record_request(&state.node_id, latency_ms, true);
+
+    let settings = settings_echo;
+
+    if output_format == OutputFormat::Binary {
+        let mut metadata = vec![
+            ("x-request-id", request_id.clone()),
+            ("x-cover-id", cover_id),
+            ("x-carrier", carrier.as_str().to_string()),
+            ("x-cover-width", cover_info.width.to_string()),
+            ("x-cover-height", cover_info.height.to_string()),
+            ("x-lsb-per-channel", cover_info.lsb_per_channel.to_string()),
+            ("x-capacity-bytes", cover_info.capacity_bytes.to_string()),
+            ("x-secret-size-bytes", secret_size.to_string()),
+        ];
+        metadata.extend(settings.headers());
+        let filename = format!("stego-{}.png", request_id);
+        return Ok(binary_response("image/png", &filename, metadata, png_bytes));
+    }
+
+    let stego_b64 = blocking(move || Ok(BASE64.encode(&png_bytes))).await?;
+
+    Ok(Json(EmbedResponse {
+        request_id,
+        cover_id,
+        carrier: carrier.as_str().to_string(),
+        cover_info: CoverInfoResponse {
+            width: cover_info.width,
+            height: cover_info.height,
+            channels: cover_info.channels,
+            lsb_per_channel: cover_info.lsb_per_channel,
+            capacity_bytes: cover_info.capacity_bytes,
+        },
+        settings,
+        secret_size_bytes: secret_size,
+        payload_size_bytes: payload_size,
+        stego_image_b64: stego_b64,
+        notes: "steganography (no normal encryption)".to_string(),
+    })
+    .into_response())
+}
+
+/// Parsed `/api/embed` fields, moved to the blocking pool
+struct EmbedRequest {
+    settings: RequestSettings,
+    secret_bytes: Vec<u8>,
+    cover_bytes: Option<Vec<u8>>,
+    cover_mode: String,
+    /// Id and image of the default cover, loaded for `cover_mode=default`
+    default_cover: Option<(String, image::DynamicImage)>,
+    carrier: Option<String>,
+    mask_channels: Option<String>,
+    mask_regions: Option<String>,
+    mask_image: Option<Vec<u8>>,
+}
+
+struct EmbeddedImage {
+    png_bytes: Vec<u8>,
+    cover_info: stego::CoverInfo,
+    cover_id: String,
+    carrier: stego::Carrier,
+}
+
+/// Decode the covers, embed and encode the stego PNG; CPU-bound, so it runs
+/// under `blocking`
+fn embed_image(state: &AppState, request: EmbedRequest) -> Result<EmbeddedImage, AppError> {
+    let EmbedRequest {
+        settings,
+        secret_bytes,
+        cover_bytes,
+        cover_mode,
+        default_cover,
+        carrier,
+        mask_channels,
+        mask_regions,
+        mask_image,
+    } = request;
+
+    // The request's settings plus any mask
+    let mut options = settings.embed_options();
+    if let Some(mask) = build_embed_mask(
+        mask_channels,
+        mask_regions,
+        mask_image,
+        state.config.stego.max_pixels,
+    )? {
+        if settings.lsb_per_channel != 1 {
+            return Err(AppError::BadRequest(
+                "Masks require lsb_per_channel=1".to_string(),
+            ));
+        }
+        options = options.mask(mask);
+    }
+
+    // Pixel LSBs (stealthy, bounded by the cover) or a PNG chunk (bulk, visible to chunk listings)
+    let carrier = match carrier.as_deref().unwrap_or("pixels") {
+        "pixels" => stego::Carrier::Pixels,
+        "png_chunk" if options.mask.is_some() => {
+            return Err(AppError::BadRequest(
+                "Masks only apply to the pixels carrier".to_string(),
+            ))
+        }
+        "png_chunk" if settings.passphrase.is_some() => {
+            return Err(AppError::BadRequest(
+                "passphrase only applies to the pixels carrier".to_string(),
+            ))
+        }
+        "png_chunk" => stego::Carrier::PngChunk,
+        other => {
+            return Err(AppError::BadRequest(format!(
+                "Unknown carrier: {} (expected \"pixels\" or \"png_chunk\")",
+                other
+            )))
+        }
+    };
+
+    let client_cover = match cover_bytes {
+        Some(bytes) => Some(decode_client_cover(&bytes, state.config.stego.max_pixels)?),
+        None => None,
+    };
+
+    // Get cover image: the client's, the server default, or the best fit from the cover library
+    let library_cover;
+    let (cover, cover_id) = match cover_mode.as_str() {
+        "client" => match &client_cover {
+            Some(img) => (img, "client".to_string()),
+            None => {
+                return Err(AppError::BadRequest(
+                    "cover_mode=client requires a cover field".to_string(),
+                ))
+            }
+        },
+        _ if client_cover.is_some() => {
+            return Err(AppError::BadRequest(
+                "A cover field can only be used with cover_mode=client".to_string(),
+            ))
+        }
+        "default" => {
+            let (id, img) = default_cover
+                .as_ref()
+                .expect("the handler loads the default cover for cover_mode=default");
+            (img, id.clone())
+        }
+        "auto" => {
+            if state.cover_library.is_empty() {
+                return Err(AppError::BadRequest(
+                    "cover_mode=auto requires a configured cover library".to_string(),
+                ));
+            }
+
+            let required = options
+                .required_capacity(&secret_bytes)
+                .map_err(|e| AppError::Internal(format!("Embedding failed: {}", e)))?;
+            let entry = state
+                .cover_library
+                .select(required, &options)
+                .ok_or(AppError::PayloadTooLarge {
+                    needed: required,
+                    available: state.cover_library.max_capacity(&options),
+                })?;
+
+            tracing::info!(
+                "Auto-selected cover {} ({}x{}, capacity {} bytes, texture {:.3}) for {} byte payload",
+                entry.id,
+                entry.width,
+                entry.height,
+                entry.capacity_for(&options),
+                entry.texture_score,
+                required
+            );
+
+            library_cover = state
+                .cover_library
+                .load_image(entry)
+                .map_err(|e| AppError::Internal(format!("Failed to load cover {}: {}", entry.id, e)))?;
+            (&library_cover, entry.id.clone())
+        }
+        other => {
+            return Err(AppError::BadRequest(format!(
+                "Unknown cover_mode: {} (expected \"default\", \"auto\" or \"client\")",
+                other
+            )))
+        }
+    };
+
+    let embed_error = |e: stego::StegoError| match e {
+        stego::StegoError::CapacityExceeded { needed, available } => {
+            AppError::PayloadTooLarge { needed, available }
+        }
+        stego::StegoError::InvalidMask(msg) => AppError::BadRequest(format!("Invalid mask: {}", msg)),
+        stego::StegoError::UnsupportedOptions(msg) => AppError::BadRequest(msg),
+        _ => AppError::Internal(format!("Embedding failed: {}", e)),
+    };
+
+    // Perform embedding, then encode the carrier image to PNG bytes
+    let (stego_img, cover_info, mut frame_stats) = match carrier {
+        stego::Carrier::PngChunk => {
+            let info = stego::CoverInfo {
+                width: cover.width(),
+                height: cover.height(),
+                channels: 3,
+                lsb_per_channel: 0,
+                capacity_bytes: stego::CONTAINER_CAPACITY_BYTES,
+                partition_capacity_bytes: vec![stego::CONTAINER_CAPACITY_BYTES],
+            };
+            (None, info, None)
+        }
+        _ => {
+            let (img, info, stats) =
+                stego::embed_with(cover, &secret_bytes, &options).map_err(embed_error)?;
+            (Some(img), info, Some(stats))
+        }
+    };
+
+    // The chunk carrier leaves the pixels alone
+    let mut png_bytes = Vec::new();
+    stego_img
+        .as_ref()
+        .unwrap_or(cover)
+        .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .map_err(|e| AppError::Internal(format!("PNG encoding failed: {}", e)))?;
+
+    if carrier == stego::Carrier::PngChunk {
+        let (png, stats) =
+            stego::embed_png_chunk(&png_bytes, &secret_bytes, &options).map_err(embed_error)?;
+        png_bytes = png;
+        frame_stats = Some(stats);
+    }
+
+    if let Some(stats) = &frame_stats {
+        telemetry::record_embed(secret_bytes.len(), png_bytes.len(), stats, &options);
+    }
+
+    Ok(EmbeddedImage {
+        png_bytes,
+        cover_info,
+        cover_id,
+        carrier,
+    })
+}
+
+/// Run CPU-bound image work (decoding, embedding, encoding) on the blocking
+/// pool so large requests cannot stall heartbeats and other routes
+async fn blocking<T, F>(work: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::Internal(format!("Worker task failed: {}", e)))?
+}
+
+/// Decode a client-supplied cover, rejecting images over `max_pixels` (0 = unlimited)
+/// before their pixels are decoded
+fn decode_client_cover(bytes: &[u8], max_pixels: u64) -> Result<image::DynamicImage, AppError> {
+    decode_client_image(bytes, max_pixels, "cover")
+}
+
+/// Decode a client-supplied image named `what` in errors, checking its
+/// dimensions against `max_pixels` (0 = unlimited) first
+fn decode_client_image(
+    bytes: &[u8],
+    max_pixels: u64,
+    what: &str,
+) -> Result<image::DynamicImage, AppError> {
+    let invalid = |e: image::ImageError| AppError::BadRequest(format!("Invalid {}: {}", what, e));
+    let reader = || {
+        image::ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|e| AppError::BadRequest(format!("Invalid {}: {}", what, e)))
+    };
+
+    let (width, height) = reader()?.into_dimensions().map_err(invalid)?;
+    let pixels = width as u64 * height as u64;
+    if max_pixels > 0 && pixels > max_pixels {
+        return Err(AppError::BadRequest(format!(
+            "Invalid {}: {} pixels ({}x{}), limit is {}",
+            what, pixels, width, height, max_pixels
+        )));
+    }
+
+    reader()?.decode().map_err(invalid)
+}
+
+/// Build an embedding mask from the optional multipart fields, if any were sent.
+///
+/// `mask_channels`: e.g. "b" or "red,green"; `mask_regions`: "x,y,w,h;x,y,w,h";
+/// `mask_image`: image whose bright pixels mark regions to protect.
+fn build_embed_mask(
+    channels: Option<String>,
+    regions: Option<String>,
+    image_bytes: Option<Vec<u8>>,
+    max_pixels: u64,
+) -> Result<Option<stego::EmbedMask>, AppError> {
+    if channels.is_none() && regions.is_none() && image_bytes.is_none() {
+        return Ok(None);
+    }
+
+    let invalid = |e: stego::StegoError| AppError::BadRequest(e.to_string());
+    let mut mask = stego::EmbedMask::new();
+
+    if let Some(channels) = channels {
+        mask = mask.channels(channels.parse().map_err(invalid)?);
+    }
+    if let Some(regions) = regions {
+        for region in regions.split(';').filter(|r| !r.trim().is_empty()) {
+            mask = mask.exclude(region.parse().map_err(invalid)?);
+        }
+    }
+    if let Some(bytes) = image_bytes {
+        let img = decode_client_image(&bytes, max_pixels, "mask_image")?;
+        mask = mask.exclude_mask_image(img.to_luma8());
+    }
+
+    Ok(Some(mask))
+}
+
+// ============================================================================
+// Batch Embed Handler
+// ============================================================================
+
+#[derive(Serialize)]
+pub struct BatchManifest {
+    batch_id: String,
+    settings: SettingsResponse,
+    total: usize,
+    succeeded: usize,
+    failed: usize,
+    items: Vec<BatchItemResult>,
+}
+
+#[derive(Serialize)]
+pub struct BatchItemResult {
+    name: String,
+    /// Stego image in the output archive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    /// Matching cover file, or the default cover's id
+    cover_id: String,
+    secret_size_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capacity_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Body limit of `/api/embed` and `/api/extract`; the idempotency middleware
+/// reads request bodies up to the same size
+pub fn request_body_limit(limits: &StegoLimits) -> DefaultBodyLimit {
+    DefaultBodyLimit::max(limits.max_request_bytes as usize)
+}
+
+/// Body limit of `/api/embed/batch`, replacing axum's 2 MB default so a batch
+/// can reach `stego.limits.max_archive_bytes`
+pub fn batch_body_limit(limits: &StegoLimits) -> DefaultBodyLimit {
+    DefaultBodyLimit::max(limits.max_batch_request_bytes())
+}
+
+/// Embed many secrets in one request.
+///
+/// Accepts an `archive` ZIP (covers under `covers/`) and/or repeated `file` and
+/// `cover` multipart fields; a secret uses the cover with the same file stem,
+/// else the default cover. Returns a ZIP of `<stem>.png` stego images plus
+/// `manifest.json`; failed items are reported there without failing the batch.
+pub async fn embed_batch_handler(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<Response, AppError> {
+    let start = Instant::now();
+
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+
+    let max_pixels = state.config.stego.max_pixels;
+    let limits = &state.config.stego.limits;
+    // No pixel carrier can hold more than its raw RGB bytes
+    let max_entry_bytes = match max_pixels.saturating_mul(3) {
+        0 => limits.max_entry_bytes,
+        carrier_bytes => carrier_bytes.min(limits.max_entry_bytes),
+    };
+
+    let mut input = batch::BatchInput::default();
+    let mut settings_fields = SettingsFields::default();
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::BadRequest(format!("Failed to read multipart: {}", e))
+    })? {
+        match field.name() {
+            Some("archive") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read archive bytes: {}", e))
+                })?;
+                let remaining = limits.max_archive_bytes.saturating_sub(input.total_bytes());
+                let archive = batch::read_archive(&data, max_entry_bytes, remaining)
+                    .map_err(|e| AppError::BadRequest(format!("Invalid archive: {}", e)))?;
+                input.secrets.extend(archive.secrets);
+                input.covers.extend(archive.covers);
+            }
+            Some("file") | Some("cover") => {
+                let is_cover = field.name() == Some("cover");
+                let name = field.file_name().map(str::to_string);
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+                })?;
+                if input.total_bytes() + data.len() as u64 > limits.max_archive_bytes {
+                    return Err(AppError::BadRequest(format!(
+                        "Batch is larger than {} bytes",
+                        limits.max_archive_bytes
+                    )));
+                }
+                let (list, prefix) = if is_cover {
+                    (&mut input.covers, "cover")
+                } else {
+                    (&mut input.secrets, "file")
+                };
+                let name = name.unwrap_or_else(|| format!("{}-{}", prefix, list.len() + 1));
+                list.push((name, data.to_vec()));
+            }
+            Some(name) if SettingsFields::NAMES.contains(&name) => {
+                let name = name.to_string();
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read {}: {}", name, e))
+                })?;
+                settings_fields.set(&name, text);
+            }
+            _ => {}
+        }
+    }
+
+    if settings_fields.output_format.is_some() {
+        return Err(AppError::BadRequest(
+            "output_format is not supported for batches; the result is always a ZIP".to_string(),
+        ));
+    }
+    let settings = settings_fields.resolve(&state.config.stego)?;
+
+    if input.secrets.is_empty() {
+        return Err(AppError::BadRequest("No secrets found in the batch".to_string()));
+    }
+    if input.secrets.len() > limits.max_batch_items {
+        return Err(AppError::BadRequest(format!(
+            "Batch has {} secrets, limit is {}",
+            input.secrets.len(),
+            limits.max_batch_items
+        )));
+    }
+
+    let default_id = state
+        .covers
+        .default_id()
+        .await
+        .unwrap_or_else(|| "default".to_string());
+    let default_cover = Arc::new(state.cover_image.read().await.clone());
+    let options = settings.embed_options();
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(limits.batch_concurrency.max(1)));
+    // Bytes of stego images still allowed, reserved as each image is encoded
+    let max_output_bytes = limits.max_output_bytes;
+    let output_budget = Arc::new(AtomicU64::new(max_output_bytes));
+
+    // Spawn every item, at most `batch_concurrency` embedding at once
+    let mut outputs = std::collections::HashSet::new();
+    let mut tasks = Vec::with_capacity(input.secrets.len());
+    for (name, secret) in &input.secrets {
+        let output = format!("{}.png", batch::stem(name));
+        let cover = input.cover_for(name).cloned();
+        let mut item = BatchItemResult {
+            name: name.clone(),
+            output: None,
+            cover_id: cover.as_ref().map_or(default_id.clone(), |(name, _)| name.clone()),
+            secret_size_bytes: secret.len() as u64,
+            capacity_bytes: None,
+            error: None,
+        };
+
+        if !outputs.insert(output.clone()) {
+            item.error = Some(format!("Duplicate output name {}", output));
+            tasks.push(tokio::spawn(async move { (item, None) }));
+            continue;
+        }
+
+        let secret = secret.clone();
+        let default_cover = default_cover.clone();
+        let options = options.clone();
+        let semaphore = semaphore.clone();
+        let output_budget = output_budget.clone();
+        tasks.push(tokio::spawn(async move {
+            let Ok(_permit) = semaphore.acquire_owned().await else {
+                item.error = Some("Batch cancelled".to_string());
+                return (item, None);
+            };
+
+            let result = tokio::task::spawn_blocking(move || -> Result<_, String> {
+                let client_cover = match cover {
+                    Some((_, bytes)) => Some(decode_client_cover(&bytes, max_pixels).map_err(
+                        |e| match e {
+                            AppError::BadRequest(msg) => msg,
+                            other => format!("{:?}", other),
+                        },
+                    )?),
+                    None => None,
+                };
+                let cover = client_cover.as_ref().unwrap_or(&default_cover);
+
+                let (stego_img, info, stats) =
+                    stego::embed_with(cover, &secret, &options).map_err(|e| e.to_string())?;
+                let mut png = Vec::new();
+                stego_img
+                    .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                    .map_err(|e| format!("PNG encoding failed: {}", e))?;
+                output_budget
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                        left.checked_sub(png.len() as u64)
+                    })
+                    .map_err(|_| format!("Batch output exceeds {} bytes", max_output_bytes))?;
+                telemetry::record_embed(secret.len(), png.len(), &stats, &options);
+                Ok((png, info.capacity_bytes))
+            })
+            .await;
+
+            match result {
+                Ok(Ok((png, capacity_bytes))) => {
+                    item.output = Some(output.clone());
+                    item.capacity_bytes = Some(capacity_bytes);
+                    (item, Some((output, png)))
+                }
+                Ok(Err(e)) => {
+                    item.error = Some(e);
+                    (item, None)
+                }
+                Err(e) => {
+                    item.error = Some(format!("Embedding task failed: {}", e));
+                    (item, None)
+                }
+            }
+        }));
+    }
+
+    // Images go into the archive as soon as their turn comes, so each is held once
+    let mut items = Vec::with_capacity(tasks.len());
+    let mut archive = batch::ArchiveWriter::new();
+    let mut succeeded = 0;
+    for task in tasks {
+        let (item, image) = task
+            .await
+            .map_err(|e| AppError::Internal(format!("Batch task failed: {}", e)))?;
+        items.push(item);
+        if let Some((name, png)) = image {
+            archive
+                .add_image(&name, &png)
+                .map_err(|e| AppError::Internal(format!("ZIP encoding failed: {}", e)))?;
+            succeeded += 1;
+        }
+    }
+
+    let batch_id = Uuid::new_v4().to_string();
+    let failed = items.len() - succeeded;
+    let settings = settings.echo(OutputFormat::Binary);
+    let metadata = {
+        let mut metadata = vec![
+            ("x-batch-id", batch_id.clone()),
+            ("x-batch-succeeded", succeeded.to_string()),
+            ("x-batch-failed", failed.to_string()),
+        ];
+        metadata.extend(settings.headers());
+        metadata
+    };
+
+    let manifest = BatchManifest {
+        batch_id: batch_id.clone(),
+        settings,
+        total: items.len(),
+        succeeded,
+        failed,
+        items,
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| AppError::Internal(format!("Manifest encoding failed: {}", e)))?;
+    let archive = archive
+        .finish(&manifest)
+        .map_err(|e| AppError::Internal(format!("ZIP encoding failed: {}", e)))?;
+
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    state.metrics.record_request(&state.node_id, latency_ms, true);
+
+    let filename = format!("stego-batch-{}.zip", batch_id);
+    Ok(binary_response("application/zip", &filename, metadata, archive))
+}
+
+// ============================================================================
+// Extract Handler
+// ============================================================================
+
+#[derive(Serialize)]
+pub struct ExtractResponse {
+    request_id: String,
+    recovered_size_bytes: u64,
+    recovered_mime: String,
+    recovered_extension: String,
+    recovered_b64: String,
+    /// Effective settings (image extraction only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settings: Option<SettingsResponse>,
+}
+
+/// Returns JSON with the secret base64-encoded, or the raw secret (sniffed
+/// `Content-Type`) when `Accept` prefers `application/octet-stream` or that type
+pub async fn extract_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Response, AppError> {
+    let start = Instant::now();
+
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+
+    // Extract uploaded stego file and settings fields
+    let mut stego_bytes = None;
+    let mut settings_fields = SettingsFields::default();
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::BadRequest(format!("Failed to read multipart: {}", e))
+    })? {
+        match field.name() {
+            Some("file") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+                })?;
+                stego_bytes = Some(data.to_vec());
+            }
+            Some(name) if SettingsFields::NAMES.contains(&name) => {
+                let name = name.to_string();
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read {}: {}", name, e))
+                })?;
+                settings_fields.set(&name, text);
+            }
+            _ => {}
+        }
+    }
+
+    let stego_bytes = stego_bytes.ok_or_else(|| {
+        AppError::BadRequest("No file field found in multipart data".to_string())
+    })?;
+
+    // Extract secret: from a PNG chunk or PDF update if present (which ignore
+    // lsb_per_channel and passphrase), else from the pixels
+    let settings = settings_fields.resolve(&state.config.stego)?;
+    let options = settings.extract_options();
+
+    let recovered = blocking(move || {
+        if stego::container_carrier(&stego_bytes).is_some() {
+            stego::extract_container(&stego_bytes, &options)
+        } else {
+            let stego_img = image::load_from_memory(&stego_bytes)
+                .map_err(|e| AppError::BadRequest(format!("Invalid image file: {}", e)))?;
+            stego::extract_with(&stego_img, &options)
+        }
+        .map_err(|e| match e {
+            stego::StegoError::UnsupportedOptions(msg) => AppError::BadRequest(msg),
+            _ => AppError::UnprocessableEntity(format!("Extraction failed: {}", e)),
+        })
+    })
+    .await?;
+
+    let recovered_size = recovered.len() as u64;
+    let content_type = stego::sniff(&recovered);
+
+    let request_id = Uuid::new_v4().to_string();
+
+    // Record metrics
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    state.metrics.record_request(&state.node_id, latency_ms, true);
+
+    let output_format = settings.output_format(&headers, &["application/octet-stream", content_type.mime]);
+    let settings = settings.echo(output_format);
+
+    if output_format == OutputFormat::Binary {
+        let mut metadata = vec![
+            ("x-request-id", request_id),
+            ("x-recovered-size-bytes", recovered_size.to_string()),
+            ("x-recovered-mime", content_type.mime.to_string()),
+            ("x-recovered-extension", content_type.extension.to_string()),
+        ];
+        metadata.extend(settings.headers());
+        let filename = format!("recovered.{}", content_type.extension);
+        return Ok(binary_response(content_type.mime, &filename, metadata, recovered));
+    }
+
+    let recovered_b64 = blocking(move || Ok(BASE64.encode(&recovered))).await?;
+
+    Ok(Json(ExtractResponse {
+        request_id,
+        recovered_size_bytes: recovered_size,
+        recovered_mime: content_type.mime.to_string(),
+        recovered_extension: content_type.extension.to_string(),
+        recovered_b64,
+        settings: Some(settings),
+    })
+    .into_response())
+}
+
+// ============================================================================
+// Probe Handler
+// ============================================================================
+
+#[derive(Serialize)]
+pub struct ProbeResponse {
+    request_id: String,
+    payload_present: bool,
+    combinations_tried: usize,
+    candidate: Option<ProbeCandidateResponse>,
+}
+
+#[derive(Serialize)]
+pub struct ProbeCandidateResponse {
+    carrier: String,
+    lsb_per_channel: u8,
+    channel_order: String,
+    authenticated: bool,
+    masked: bool,
+    declared_size_bytes: u64,
+    fits_carrier: bool,
+    crc_valid: bool,
+    compressed: bool,
+    encrypted: bool,
+}
+
+pub async fn probe_handler(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<Json<ProbeResponse>, AppError> {
+    let start = Instant::now();
+
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+
+    // Extract uploaded image
+    let mut image_bytes = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::BadRequest(format!("Failed to read multipart: {}", e))
+    })? {
+        if field.name() == Some("file") {
+            let data = field.bytes().await.map_err(|e| {
+                AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+            })?;
+            image_bytes = Some(data.to_vec());
+            break;
+        }
+    }
+
+    let image_bytes = image_bytes.ok_or_else(|| {
+        AppError::BadRequest("No file field found in multipart data".to_string())
+    })?;
+
+    // PNG chunk and PDF update containers, then the pixels of any decodable image
+    let report = stego::probe_file(&image_bytes)
+        .map_err(|e| AppError::BadRequest(format!("Invalid image file: {}", e)))?;
+
+    let candidate = report.candidate.map(|c| ProbeCandidateResponse {
+        carrier: c.carrier.as_str().to_string(),
+        lsb_per_channel: c.lsb_per_channel,
+        channel_order: c.channel_order.as_str().to_string(),
+        authenticated: c.authenticated,
+        masked: c.masked,
+        declared_size_bytes: c.declared_size_bytes,
+        fits_carrier: c.fits_carrier,
+        crc_valid: c.crc_valid,
+        compressed: c.compressed,
+        encrypted: c.encrypted,
+    });
+
+    let request_id = Uuid::new_v4().to_string();
+
+    // Record metrics
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    state.metrics.record_request(&state.node_id, latency_ms, true);
+
+    Ok(Json(ProbeResponse {
+        request_id,
+        payload_present: report.payload_present,
+        combinations_tried: report.combinations_tried,
+        candidate,
+    }))
+}
+
+// ============================================================================
+// Audio Handlers (WAV carrier)
+// ============================================================================
+
+#[derive(Serialize)]
+pub struct AudioEmbedResponse {
+    request_id: String,
+    audio_info: AudioInfoResponse,
+    secret_size_bytes: u64,
+    payload_size_bytes: u64,
+    stego_audio_b64: String,
+    notes: String,
+}
+
+#[derive(Serialize)]
+pub struct AudioInfoResponse {
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+    num_samples: u64,
+    lsb_per_sample: u8,
+    capacity_bytes: u64,
+}
+
+pub async fn embed_audio_handler(
     State(state): State<Arc<AppState>>,
     mut multipart: Multipart,
-) -> Result<Json<EmbedResponse>, AppError> {
+) -> Result<Json<AudioEmbedResponse>, AppError> {
     let start = Instant::now();
 
     if state.is_paused() {
         return Err(AppError::ServiceUnavailable);
     }
 
-    // Extract uploaded file
+    // Extract uploaded secret and cover WAV
     let mut secret_bytes = None;
+    let mut cover_wav = None;
 
     while let Some(field) = multipart.next_field().await.map_err(|e| {
         AppError::BadRequest(format!("Failed to read multipart: {}", e))
     })? {
-        if field.name() == Some("file") {
-            let data = field.bytes().await.map_err(|e| {
-                AppError::BadRequest(format!("Failed to read file bytes: {}", e))
-            })?;
-            secret_bytes = Some(data.to_vec());
-            break;
-        }
+        let target = match field.name() {
+            Some("file") => &mut secret_bytes,
+            Some("cover") => &mut cover_wav,
+            _ => continue,
+        };
+        let data = field.bytes().await.map_err(|e| {
+            AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+        })?;
+        *target = Some(data.to_vec());
     }
 
     let secret_bytes = secret_bytes.ok_or_else(|| {
         AppError::BadRequest("No file field found in multipart data".to_string())
     })?;
+    let cover_wav = cover_wav.ok_or_else(|| {
+        AppError::BadRequest("No cover field found in multipart data".to_string())
+    })?;
 
     let secret_size = secret_bytes.len() as u64;
 
-    // Get cover image
-    let cover = state.cover_image.read().await;
-
     // Perform embedding
-    let lsb_per_channel = state.config.stego.lsb_per_channel;
+    let lsb_per_sample = state.config.stego.lsb_per_channel;
     let compress = state.config.stego.compress;
 
-    let (stego_img, cover_info) = stego::embed(&cover, &secret_bytes, lsb_per_channel, compress)
-        .map_err(|e| match e {
-            stego::StegoError::CapacityExceeded { needed, available } => {
-                AppError::PayloadTooLarge { needed, available }
+    let (stego_wav, audio_info) =
+        stego::embed_audio(&cover_wav, &secret_bytes, lsb_per_sample, compress).map_err(|e| {
+            match e {
+                stego::StegoError::CapacityExceeded { needed, available } => {
+                    AppError::PayloadTooLarge { needed, available }
+                }
+                stego::StegoError::InvalidCover(msg) => {
+                    AppError::BadRequest(format!("Invalid WAV cover: {}", msg))
+                }
+                _ => AppError::Internal(format!("Embedding failed: {}", e)),
             }
-            _ => AppError::Internal(format!("Embedding failed: {}", e)),
         })?;
 
-    // Encode stego image to PNG bytes
-    let mut png_bytes = Vec::new();
-    stego_img
-        .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
-        .map_err(|e| AppError::Internal(format!("PNG encoding failed: {}", e)))?;
-
-    let stego_b64 = BASE64.encode(&png_bytes);
-    let payload_size = png_bytes.len() as u64;
+    let payload_size = stego_wav.len() as u64;
+    let stego_b64 = BASE64.encode(&stego_wav);
 
     let request_id = Uuid::new_v4().to_string();
 
@@ -116,35 +1412,24 @@ pub async fn embed_handler(
     let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
     state.metrics.record_request(&state.node_id, latency_ms, true);
 
-    Ok(Json(EmbedResponse {
+    Ok(Json(AudioEmbedResponse {
         request_id,
-        cover_info: CoverInfoResponse {
-            width: cover_info.width,
-            height: cover_info.height,
-            channels: cover_info.channels,
-            lsb_per_channel: cover_info.lsb_per_channel,
-            capacity_bytes: cover_info.capacity_bytes,
+        audio_info: AudioInfoResponse {
+            sample_rate: audio_info.sample_rate,
+            channels: audio_info.channels,
+            bits_per_sample: audio_info.bits_per_sample,
+            num_samples: audio_info.num_samples,
+            lsb_per_sample: audio_info.lsb_per_sample,
+            capacity_bytes: audio_info.capacity_bytes,
         },
         secret_size_bytes: secret_size,
         payload_size_bytes: payload_size,
-        stego_image_b64: stego_b64,
+        stego_audio_b64: stego_b64,
         notes: "steganography (no normal encryption)".to_string(),
     }))
 }
 
-// ============================================================================
-// Extract Handler
-// ============================================================================
-
-#[derive(Serialize)]
-pub struct ExtractResponse {
-    request_id: String,
-    recovered_size_bytes: u64,
-    recovered_mime: String,
-    recovered_b64: String,
-}
-
-pub async fn extract_handler(
+pub async fn extract_audio_handler(
     State(state): State<Arc<AppState>>,
     mut multipart: Multipart,
 ) -> Result<Json<ExtractResponse>, AppError> {
@@ -154,8 +1439,8 @@ pub async fn extract_handler(
         return Err(AppError::ServiceUnavailable);
     }
 
-    // Extract uploaded stego file
-    let mut stego_bytes = None;
+    // Extract uploaded stego WAV
+    let mut stego_wav = None;
 
     while let Some(field) = multipart.next_field().await.map_err(|e| {
         AppError::BadRequest(format!("Failed to read multipart: {}", e))
@@ -164,29 +1449,29 @@ pub async fn extract_handler(
             let data = field.bytes().await.map_err(|e| {
                 AppError::BadRequest(format!("Failed to read file bytes: {}", e))
             })?;
-            stego_bytes = Some(data.to_vec());
+            stego_wav = Some(data.to_vec());
             break;
         }
     }
 
-    let stego_bytes = stego_bytes.ok_or_else(|| {
+    let stego_wav = stego_wav.ok_or_else(|| {
         AppError::BadRequest("No file field found in multipart data".to_string())
     })?;
 
-    // Load stego image
-    let stego_img = image::load_from_memory(&stego_bytes)
-        .map_err(|e| AppError::BadRequest(format!("Invalid image file: {}", e)))?;
-
     // Extract secret
-    let lsb_per_channel = state.config.stego.lsb_per_channel;
+    let lsb_per_sample = state.config.stego.lsb_per_channel;
     let compress = state.config.stego.compress;
 
-    let recovered = stego::extract(&stego_img, lsb_per_channel, compress)
-        .map_err(|e| AppError::UnprocessableEntity(format!("Extraction failed: {}", e)))?;
+    let recovered = stego::extract_audio(&stego_wav, lsb_per_sample, compress).map_err(|e| match e {
+        stego::StegoError::InvalidCover(msg) => {
+            AppError::BadRequest(format!("Invalid WAV file: {}", msg))
+        }
+        _ => AppError::UnprocessableEntity(format!("Extraction failed: {}", e)),
+    })?;
 
     let recovered_size = recovered.len() as u64;
-    let recovered_mime = stego::get_mime_type(&recovered).to_string();
-    let recovered_b64 = BASE64.encode(&recovered);
+    let content_type = stego::sniff(&recovered);
+    let recovered_b64 = blocking(move || Ok(BASE64.encode(&recovered))).await?;
 
     let request_id = Uuid::new_v4().to_string();
 
@@ -197,29 +1482,476 @@ pub async fn extract_handler(
     Ok(Json(ExtractResponse {
         request_id,
         recovered_size_bytes: recovered_size,
-        recovered_mime,
+        recovered_mime: content_type.mime.to_string(),
+        recovered_extension: content_type.extension.to_string(),
         recovered_b64,
+        settings: None,
     }))
 }
 
 // ============================================================================
-// Dataset Handler (for stress testing)
+// Cover Management Handlers (replicated through the Raft log)
 // ============================================================================
 
-pub async fn dataset_handler(
-    Path(index): Path<usize>,
-) -> Result<impl IntoResponse, AppError> {
-    if index >= 50 {
+#[derive(Serialize)]
+pub struct CoverListResponse {
+    default_id: Option<String>,
+    covers: Vec<CoverMeta>,
+}
+
+/// A proposal without a majority is not a failure: it may still be applied,
+/// so the client is told to check the resource before retrying
+fn replication_error(e: anyhow::Error) -> AppError {
+    match e.downcast_ref::<control_plane::Uncommitted>() {
+        Some(uncommitted) => AppError::Uncommitted(RetryableError::after(
+            format!(
+                "Replication pending: {}. Check the resource before retrying",
+                uncommitted
+            ),
+            std::time::Duration::from_secs(1),
+        )),
+        None => AppError::Internal(format!("Replication failed: {}", e)),
+    }
+}
+
+/// Upload a cover on the leader; its metadata is committed through the log and
+/// followers fetch the file from this node
+pub async fn upload_cover_handler(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<CoverMeta>), AppError> {
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+    check_is_leader(&state).await?;
+
+    let mut cover_bytes = None;
+    let mut name = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::BadRequest(format!("Failed to read multipart: {}", e))
+    })? {
+        match field.name() {
+            Some("file") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+                })?;
+                cover_bytes = Some(data.to_vec());
+            }
+            Some("name") => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read name: {}", e))
+                })?;
+                name = Some(text);
+            }
+            _ => {}
+        }
+    }
+
+    let cover_bytes = cover_bytes.ok_or_else(|| {
+        AppError::BadRequest("No file field found in multipart data".to_string())
+    })?;
+    let img = decode_client_cover(&cover_bytes, state.config.stego.max_pixels)?;
+
+    let sha256 = state
+        .covers
+        .store_blob(&cover_bytes)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to store cover: {}", e)))?;
+
+    let id = Uuid::new_v4().to_string();
+    let meta = CoverMeta {
+        name: name.unwrap_or_else(|| id.clone()),
+        id,
+        sha256,
+        size_bytes: cover_bytes.len() as u64,
+        width: img.width(),
+        height: img.height(),
+        mime: stego::sniff(&cover_bytes).mime.to_string(),
+        origin_node: state.node_id.clone(),
+    };
+
+    replication::propose(&state, &Command::Cover(CoverCommand::Put(meta.clone())))
+        .await
+        .map_err(replication_error)?;
+
+    Ok((StatusCode::CREATED, Json(meta)))
+}
+
+pub async fn list_covers_handler(State(state): State<Arc<AppState>>) -> Json<CoverListResponse> {
+    Json(CoverListResponse {
+        default_id: state.covers.default_id().await,
+        covers: state.covers.list().await,
+    })
+}
+
+pub async fn get_cover_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<CoverMeta>, AppError> {
+    state.covers.get(&id).await.map(Json).ok_or(AppError::NotFound)
+}
+
+/// The cover file as uploaded; only served from this node's local copy
+pub async fn cover_blob_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let meta = state.covers.get(&id).await.ok_or(AppError::NotFound)?;
+    let bytes = state.covers.blob(&meta, &[]).await.map_err(|_| AppError::NotFound)?;
+
+    Ok(([(header::CONTENT_TYPE, meta.mime)], bytes).into_response())
+}
+
+pub async fn delete_cover_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+    check_is_leader(&state).await?;
+    state.covers.get(&id).await.ok_or(AppError::NotFound)?;
+    if state.covers.default_id().await.as_deref() == Some(id.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Cover {} is the default cover; set another default before deleting it",
+            id
+        )));
+    }
+
+    replication::propose(&state, &Command::Cover(CoverCommand::Delete { id }))
+        .await
+        .map_err(replication_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Make a managed cover the default embedding cover on every node
+pub async fn set_default_cover_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<CoverMeta>, AppError> {
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+    check_is_leader(&state).await?;
+    let meta = state.covers.get(&id).await.ok_or(AppError::NotFound)?;
+
+    replication::propose(&state, &Command::Cover(CoverCommand::SetDefault { id }))
+        .await
+        .map_err(replication_error)?;
+
+    Ok(Json(meta))
+}
+
+// ============================================================================
+// Job Handlers (asynchronous embed/extract, replicated through the Raft log)
+// ============================================================================
+
+#[derive(Serialize)]
+pub struct JobStatusResponse {
+    id: String,
+    kind: JobKind,
+    state: &'static str,
+    /// Percent complete; only known on the node executing the job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<u8>,
+    /// Node executing or having executed the job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<JobResultInfo>,
+}
+
+#[derive(Serialize)]
+pub struct JobResultInfo {
+    size_bytes: u64,
+    mime: String,
+    filename: String,
+}
+
+impl JobStatusResponse {
+    fn new(record: &JobRecord, local_progress: Option<u8>) -> Self {
+        let mut status = Self {
+            id: record.spec.id.clone(),
+            kind: record.spec.kind,
+            state: record.state.as_str(),
+            progress: None,
+            node: None,
+            error: None,
+            result: None,
+        };
+        match &record.state {
+            JobState::Queued => status.progress = Some(0),
+            JobState::Running { node } => {
+                status.progress = local_progress;
+                status.node = Some(node.clone());
+            }
+            JobState::Succeeded { node, output } => {
+                status.progress = Some(100);
+                status.node = Some(node.clone());
+                status.result = Some(JobResultInfo {
+                    size_bytes: output.size_bytes,
+                    mime: output.mime.clone(),
+                    filename: output.filename.clone(),
+                });
+            }
+            JobState::Failed { error } => status.error = Some(error.clone()),
+        }
+        status
+    }
+}
+
+/// Queue an embed or extract job on the leader; inputs are stored here and
+/// copied to every node when the submission commits
+pub async fn submit_job_handler(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<Response, AppError> {
+    if state.is_paused() {
+        return Err(AppError::ServiceUnavailable);
+    }
+    check_is_leader(&state).await?;
+
+    let mut kind = None;
+    let mut input = None;
+    let mut cover_bytes = None;
+    let mut settings_fields = SettingsFields::default();
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::BadRequest(format!("Failed to read multipart: {}", e))
+    })? {
+        match field.name() {
+            Some("kind") => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read kind: {}", e))
+                })?;
+                kind = match text.trim() {
+                    "embed" => Some(JobKind::Embed),
+                    "extract" => Some(JobKind::Extract),
+                    other => {
+                        return Err(AppError::BadRequest(format!(
+                            "Unknown kind: {} (expected \"embed\" or \"extract\")",
+                            other
+                        )))
+                    }
+                };
+            }
+            Some("file") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read file bytes: {}", e))
+                })?;
+                input = Some(data.to_vec());
+            }
+            Some("cover") => {
+                let data = field.bytes().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read cover bytes: {}", e))
+                })?;
+                cover_bytes = Some(data.to_vec());
+            }
+            Some(name) if SettingsFields::NAMES.contains(&name) => {
+                let name = name.to_string();
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read {}: {}", name, e))
+                })?;
+                settings_fields.set(&name, text);
+            }
+            _ => {}
+        }
+    }
+
+    let kind = kind.ok_or_else(|| {
+        AppError::BadRequest("No kind field found in multipart data".to_string())
+    })?;
+    let input = input.ok_or_else(|| {
+        AppError::BadRequest("No file field found in multipart data".to_string())
+    })?;
+    // Job inputs are persisted on every node, so secrets that must not be
+    // stored (passphrases) and per-response choices are not accepted
+    if settings_fields.passphrase.is_some() {
+        return Err(AppError::BadRequest(
+            "passphrase is not supported for jobs".to_string(),
+        ));
+    }
+    if settings_fields.output_format.is_some() {
+        return Err(AppError::BadRequest(
+            "output_format is not supported for jobs; results are always binary".to_string(),
+        ));
+    }
+    let settings = settings_fields.resolve(&state.config.stego)?;
+
+    if let Some(bytes) = &cover_bytes {
+        if kind != JobKind::Embed {
+            return Err(AppError::BadRequest(
+                "cover is only accepted for embed jobs".to_string(),
+            ));
+        }
+        decode_client_cover(bytes, state.config.stego.max_pixels)?;
+    }
+
+    let store_error = |e: anyhow::Error| AppError::Internal(format!("Failed to store job input: {}", e));
+    let input_sha256 = state.jobs.store_blob(&input).await.map_err(store_error)?;
+    let cover_sha256 = match &cover_bytes {
+        Some(bytes) => Some(state.jobs.store_blob(bytes).await.map_err(store_error)?),
+        None => None,
+    };
+
+    let spec = JobSpec {
+        id: Uuid::new_v4().to_string(),
+        kind,
+        input_sha256,
+        cover_sha256,
+        lsb_per_channel: settings.lsb_per_channel,
+        compress: settings.compression == stego::Compression::Deflate,
+        origin_node: state.node_id.clone(),
+    };
+    let id = spec.id.clone();
+
+    replication::propose(&state, &Command::Job(JobCommand::Submit(spec)))
+        .await
+        .map_err(|e| match replication_error(e) {
+            // Name the job so the client can poll it instead of resubmitting
+            AppError::Uncommitted(mut err) => {
+                err.error = format!("Job {}: {}", id, err.error);
+                AppError::Uncommitted(err)
+            }
+            other => other,
+        })?;
+
+    let record = state.jobs.get(&id).await.ok_or_else(|| {
+        AppError::Internal(format!("Job {} was not applied", id))
+    })?;
+    let location = format!("/api/jobs/{}", id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(JobStatusResponse::new(&record, None)),
+    )
+        .into_response())
+}
+
+pub async fn job_status_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatusResponse>, AppError> {
+    let record = state.jobs.get(&id).await.ok_or(AppError::NotFound)?;
+    Ok(Json(JobStatusResponse::new(&record, state.jobs.progress(&id))))
+}
+
+/// The job's result file once it succeeded; 202 with the status until then
+pub async fn job_result_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let record = state.jobs.get(&id).await.ok_or(AppError::NotFound)?;
+
+    match &record.state {
+        JobState::Succeeded { node, output } => {
+            let bytes = state
+                .jobs
+                .blob(&id, &output.sha256, &state.peer_urls(node))
+                .await
+                .map_err(|e| AppError::Internal(format!("Job result unavailable: {}", e)))?;
+            let mut metadata = vec![("x-job-id".to_string(), id.clone())];
+            metadata.extend(output.metadata.iter().cloned());
+            Ok(binary_response(&output.mime, &output.filename, metadata, bytes))
+        }
+        JobState::Failed { error } => Err(AppError::UnprocessableEntity(format!(
+            "Job {} failed: {}",
+            id, error
+        ))),
+        JobState::Queued | JobState::Running { .. } => Ok((
+            StatusCode::ACCEPTED,
+            Json(JobStatusResponse::new(&record, state.jobs.progress(&id))),
+        )
+            .into_response()),
+    }
+}
+
+/// A job input or result file, for peers copying it; only served from this
+/// node's local copy
+pub async fn job_blob_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, sha256)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    let record = state.jobs.get(&id).await.ok_or(AppError::NotFound)?;
+    if !record.references(&sha256) {
         return Err(AppError::NotFound);
     }
+    let bytes = state.jobs.blob(&id, &sha256, &[]).await.map_err(|_| AppError::NotFound)?;
 
-    // Generate synthetic image
-    let img = stego::utils::generate_dataset_image(index, 800, 600);
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], bytes).into_response())
+}
 
-    // Encode to PNG
-    let mut png_bytes = Vec::new();
-    img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
-        .map_err(|e| AppError::Internal(format!("PNG encoding failed: {}", e)))?;
+// ============================================================================
+// Dataset Handler (for stress testing)
+// ============================================================================
+
+#[derive(Serialize)]
+pub struct DatasetListResponse {
+    count: usize,
+    pattern: common::CoverPattern,
+    seed: u64,
+    lsb_per_channel: u8,
+    images: Vec<DatasetImageResponse>,
+}
+
+#[derive(Serialize)]
+pub struct DatasetImageResponse {
+    index: usize,
+    width: u32,
+    height: u32,
+    capacity_bytes: u64,
+}
+
+pub async fn dataset_list_handler(State(state): State<Arc<AppState>>) -> Json<DatasetListResponse> {
+    let dataset = &state.config.dataset;
+    let params = stego::DatasetParams::from(dataset);
+    let lsb_per_channel = state.config.stego.lsb_per_channel;
+
+    let images = (0..dataset.count)
+        .filter_map(|index| stego::dataset_spec(&params, index))
+        .map(|spec| DatasetImageResponse {
+            index: spec.index,
+            width: spec.width,
+            height: spec.height,
+            capacity_bytes: stego::capacity_bytes(spec.width, spec.height, lsb_per_channel),
+        })
+        .collect();
+
+    Json(DatasetListResponse {
+        count: dataset.count,
+        pattern: dataset_pattern(&state),
+        seed: dataset.seed,
+        lsb_per_channel,
+        images,
+    })
+}
+
+pub async fn dataset_handler(
+    State(state): State<Arc<AppState>>,
+    Path(index): Path<usize>,
+) -> Result<impl IntoResponse, AppError> {
+    let params = stego::DatasetParams::from(&state.config.dataset);
+    let spec = stego::dataset_spec(&params, index).ok_or(AppError::NotFound)?;
+
+    let png_bytes = match state.dataset_cache.get(index) {
+        Some(png_bytes) => png_bytes,
+        None => {
+            // Generate synthetic image
+            let img = stego::generate_dataset_image(&spec, dataset_pattern(&state));
+
+            // Encode to PNG
+            let mut png_bytes = Vec::new();
+            img.write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+                .map_err(|e| AppError::Internal(format!("PNG encoding failed: {}", e)))?;
+            let png_bytes = bytes::Bytes::from(png_bytes);
+            state.dataset_cache.insert(index, png_bytes.clone());
+            png_bytes
+        }
+    };
 
     Ok((
         StatusCode::OK,
@@ -228,6 +1960,14 @@ pub async fn dataset_handler(
     ))
 }
 
+fn dataset_pattern(state: &AppState) -> common::CoverPattern {
+    state
+        .config
+        .dataset
+        .pattern
+        .unwrap_or(state.config.stego.cover_pattern)
+}
+
 // ============================================================================
 // Cluster Status Handler
 // ============================================================================
@@ -345,12 +2085,13 @@ pub async fn health_handler(
     Ok(Json(serde_json::json!({ "status": "healthy" })))
 }
 
-pub async fn metrics_handler() -> impl IntoResponse {
-    // Return simple Prometheus-compatible metrics
-    let body = "# HELP requests_total Total number of requests\n\
-                # TYPE requests_total counter\n\
-                requests_total 0\n";
-    (StatusCode::OK, [("Content-Type", "text/plain")], body)
+/// Request, embed and Raft series in the Prometheus text format
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.prometheus.render(),
+    )
 }
 
 // ============================================================================
@@ -368,9 +2109,15 @@ pub async fn serve_index() -> Html<&'static str> {
 #[derive(Debug)]
 pub enum AppError {
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
     NotFound,
     PayloadTooLarge { needed: u64, available: u64 },
     UnprocessableEntity(String),
+    Conflict(String),
+    TooManyRequests(RetryableError),
+    /// Replication outcome unknown: the change may still be committed
+    Uncommitted(RetryableError),
     Internal(String),
     ServiceUnavailable,
     NotLeader(NotLeaderResponse),
@@ -384,9 +2131,39 @@ impl IntoResponse for AppError {
                 let body = Json(resp);
                 (status, body).into_response()
             }
+            AppError::TooManyRequests(err) => {
+                let retry_after = err.retry_after_secs.unwrap_or(1).to_string();
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after)],
+                    Json(err),
+                )
+                    .into_response()
+            }
+            AppError::Uncommitted(err) => {
+                let retry_after = err.retry_after_secs.unwrap_or(1).to_string();
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, retry_after)],
+                    Json(err),
+                )
+                    .into_response()
+            }
+            AppError::Unauthorized(msg) => {
+                let body = Json(serde_json::json!({
+                    "error": msg,
+                }));
+                (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer")],
+                    body,
+                )
+                    .into_response()
+            }
             _ => {
                 let (status, message) = match self {
                     AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+                    AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
                     AppError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
                     AppError::PayloadTooLarge { needed, available } => (
                         StatusCode::PAYLOAD_TOO_LARGE,
@@ -396,11 +2173,15 @@ impl IntoResponse for AppError {
                         ),
                     ),
                     AppError::UnprocessableEntity(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+                    AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
                     AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
                     AppError::ServiceUnavailable => {
                         (StatusCode::SERVICE_UNAVAILABLE, "Service paused".to_string())
                     }
-                    AppError::NotLeader(_) => unreachable!(),
+                    AppError::NotLeader(_)
+                    | AppError::TooManyRequests(_)
+                    | AppError::Uncommitted(_)
+                    | AppError::Unauthorized(_) => unreachable!(),
                 };
 
                 let body = Json(serde_json::json!({
@@ -421,50 +2202,12 @@ pub async fn raft_append_entries_handler(
     State(state): State<Arc<AppState>>,
     Json(req): Json<control_plane::AppendEntriesRequest>,
 ) -> Result<Json<control_plane::AppendEntriesResponse>, AppError> {
-    // Update term if needed
-    if req.term > state.raft_node.get_term().await {
-        state.raft_node.advance_term(req.term).await;
-    }
-
-    // If request is from leader, update leader and role
-    if req.term >= state.raft_node.get_term().await {
-        state.raft_node.set_current_leader(Some(req.leader_id)).await;
-        
-        // If we're currently leader but received AppendEntries from another leader
-        // with same or higher term, we must step down (only one leader per term)
-        let our_node_id = state.raft_node.node_id();
-        let is_stepping_down = state.raft_node.is_leader().await && req.leader_id != our_node_id;
-        
-        if is_stepping_down {
-            tracing::warn!(
-                "Node {} stepping down: received AppendEntries from {} in term {}",
-                state.node_id,
-                req.leader_id,
-                req.term
-            );
-            state.raft_node.set_follower().await;
-            
-            // CRITICAL FIX: When stepping down, give leader grace period to establish
-            // This prevents immediate re-elections after a legitimate AppendEntries
-            // Grace period = full election timeout (50-100ms) to allow new leader to send heartbeats
-            let grace_period_ms = state.raft_node.election_timeout_ms() + 50;
-            state.raft_node.record_heartbeat_with_grace(grace_period_ms).await;
-        } else if !state.raft_node.is_leader().await {
-            state.raft_node.set_follower().await;
-            // Regular heartbeat record for normal AppendEntries
-            state.raft_node.record_heartbeat().await;
-        } else {
-            // We're still leader, just record the heartbeat
-            state.raft_node.record_heartbeat().await;
-        }
-    }
-
-    // Respond with current term and success
-    let response = control_plane::AppendEntriesResponse {
-        term: state.raft_node.get_term().await,
-        success: true,
-        conflict_opt: None,
-    };
+    // Term checks, stepping down and the log consistency check live in the node
+    let response = state
+        .raft_node
+        .handle_append_entries(&req)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to append entries: {}", e)))?;
 
     tracing::debug!(
         "Node {} received AppendEntries from leader {} (term: {})",
@@ -480,8 +2223,12 @@ pub async fn raft_request_vote_handler(
     State(state): State<Arc<AppState>>,
     Json(req): Json<control_plane::RequestVoteRequest>,
 ) -> Result<Json<control_plane::RequestVoteResponse>, AppError> {
-    // Use the new grant_vote logic which handles term updates and vote tracking
-    let vote_granted = state.raft_node.grant_vote(req.candidate_id, req.term).await;
+    // Use the new grant_vote logic which handles term updates, vote tracking and
+    // the log up-to-date check
+    let vote_granted = state
+        .raft_node
+        .grant_vote(req.candidate_id, req.term, (req.last_log_index, req.last_log_term))
+        .await;
 
     let response = control_plane::RequestVoteResponse {
         term: state.raft_node.get_term().await,
@@ -490,3 +2237,198 @@ pub async fn raft_request_vote_handler(
 
     Ok(Json(response))
 }
+
+/// Body of a response stored for an `Idempotency-Key`, for peers replicating
+/// it; only served from this node's local copy
+pub async fn stored_response_body_handler(
+    State(state): State<Arc<AppState>>,
+    Path(sha256): Path<String>,
+) -> Result<Response, AppError> {
+    let bytes = state.idempotency.local_body(&sha256).await.ok_or(AppError::NotFound)?;
+
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], bytes).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const PNG_TYPES: [&str; 2] = ["image/png", "application/octet-stream"];
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_prefers_binary_defaults_to_json() {
+        assert!(!prefers_binary(&HeaderMap::new(), &PNG_TYPES));
+        assert!(!prefers_binary(&accept("*/*"), &PNG_TYPES));
+        assert!(!prefers_binary(&accept("application/json"), &PNG_TYPES));
+        assert!(!prefers_binary(&accept("image/png, application/json"), &PNG_TYPES));
+    }
+
+    #[test]
+    fn test_prefers_binary_exact_types() {
+        assert!(prefers_binary(&accept("image/png"), &PNG_TYPES));
+        assert!(prefers_binary(&accept("application/octet-stream"), &PNG_TYPES));
+        assert!(prefers_binary(&accept("application/json;q=0.5, image/png"), &PNG_TYPES));
+        assert!(!prefers_binary(&accept("image/png;q=0.5, application/json"), &PNG_TYPES));
+    }
+
+    #[test]
+    fn test_prefers_binary_wildcards() {
+        assert!(prefers_binary(&accept("image/*"), &PNG_TYPES));
+        assert!(prefers_binary(&accept("image/*, */*;q=0.1"), &PNG_TYPES));
+        // application/* covers JSON as well, so it ties and JSON wins
+        assert!(!prefers_binary(&accept("application/*"), &PNG_TYPES));
+        assert!(prefers_binary(&accept("application/*, application/json;q=0.2"), &PNG_TYPES));
+        assert!(!prefers_binary(&accept("image/*"), &["application/zip"]));
+    }
+
+    #[test]
+    fn test_prefers_binary_q_zero_excludes() {
+        assert!(!prefers_binary(&accept("image/png;q=0"), &PNG_TYPES));
+        assert!(!prefers_binary(&accept("image/*;q=0, application/json;q=0.1"), &PNG_TYPES));
+        // The most specific range wins over a broader one
+        assert!(!prefers_binary(&accept("image/*, image/png;q=0"), &["image/png"]));
+        assert!(prefers_binary(&accept("*/*, application/json;q=0"), &PNG_TYPES));
+    }
+
+    fn settings(fields: &[(&str, &str)]) -> SettingsFields {
+        let mut settings = SettingsFields::default();
+        for (name, value) in fields {
+            settings.set(name, value.to_string());
+        }
+        settings
+    }
+
+    #[test]
+    fn test_stego_options_from_config() {
+        let config = StegoConfig {
+            compress: false,
+            mac_key: Some("shared".to_string()),
+            ..StegoConfig::default()
+        };
+
+        let embed = embed_options(&config);
+        assert_eq!(embed.lsb_per_channel, 1);
+        assert_eq!(embed.compression, stego::Compression::None);
+        assert_eq!(embed.mac_key.as_deref(), Some(b"shared".as_slice()));
+        assert!(embed.traversal_key.is_none());
+        assert!(embed.mask.is_none());
+
+        let extract = extract_options(&StegoConfig::default());
+        assert_eq!(extract.compression, stego::Compression::Deflate);
+        assert!(extract.mac_key.is_none());
+    }
+
+    #[test]
+    fn test_resolve_passphrase_uses_one_lsb() {
+        let config = StegoConfig { lsb_per_channel: 2, ..StegoConfig::default() };
+
+        let keyed = settings(&[("passphrase", "correct horse")]).resolve(&config).unwrap();
+        assert_eq!(keyed.lsb_per_channel, 1);
+        let plain = settings(&[]).resolve(&config).unwrap();
+        assert_eq!(plain.lsb_per_channel, 2);
+
+        let explicit = settings(&[("passphrase", "correct horse"), ("lsb_per_channel", "1")]);
+        assert_eq!(explicit.resolve(&config).unwrap().lsb_per_channel, 1);
+        let conflicting = settings(&[("passphrase", "correct horse"), ("lsb_per_channel", "2")]);
+        assert!(matches!(conflicting.resolve(&config), Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_decode_client_image_checks_max_pixels() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(8, 4)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        assert_eq!(decode_client_image(&png, 32, "cover").unwrap().width(), 8);
+        assert!(decode_client_image(&png, 0, "cover").is_ok());
+        match decode_client_image(&png, 31, "mask_image") {
+            Err(AppError::BadRequest(msg)) => assert!(msg.contains("mask_image"), "{}", msg),
+            other => panic!("expected BadRequest, got {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(
+            build_embed_mask(None, None, Some(png), 31),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_binary_response_streams_whole_body() {
+        let body: Vec<u8> = (0..BINARY_CHUNK_SIZE * 2 + 17).map(|i| i as u8).collect();
+        let response = binary_response(
+            "image/png",
+            "out.png",
+            vec![("x-request-id", "abc".to_string())],
+            body.clone(),
+        );
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(response.headers()["x-request-id"], "abc");
+        let received = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(received.as_ref(), body.as_slice());
+    }
+
+    /// POST a multipart body with one `archive` field of `len` bytes
+    async fn post_archive(app: axum::Router, len: usize) -> StatusCode {
+        use tower::ServiceExt;
+
+        let mut body = b"--b\r\ncontent-disposition: form-data; name=\"archive\"; filename=\"a.zip\"\r\n\r\n".to_vec();
+        body.resize(body.len() + len, 0);
+        body.extend_from_slice(b"\r\n--b--\r\n");
+        let request = axum::http::Request::post("/api/embed/batch")
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+            .body(Body::from(body))
+            .unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_batch_body_limit_allows_large_archives() {
+        // Reads the archive field as `embed_batch_handler` does
+        async fn read_archive(mut multipart: Multipart) -> StatusCode {
+            loop {
+                match multipart.next_field().await {
+                    Ok(Some(field)) => {
+                        if let Err(e) = field.bytes().await {
+                            return e.status();
+                        }
+                    }
+                    Ok(None) => return StatusCode::OK,
+                    Err(e) => return e.status(),
+                }
+            }
+        }
+        let app = |limit: Option<DefaultBodyLimit>| {
+            let route = axum::routing::post(read_archive);
+            let route = match limit {
+                Some(limit) => route.layer(limit),
+                None => route,
+            };
+            axum::Router::new().route("/api/embed/batch", route)
+        };
+        let len = 3 * 1024 * 1024;
+
+        // axum's default 2 MB limit refuses it
+        assert_eq!(post_archive(app(None), len).await, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let limits = StegoLimits::default();
+        assert_eq!(post_archive(app(Some(batch_body_limit(&limits))), len).await, StatusCode::OK);
+
+        let small = StegoLimits {
+            max_entry_bytes: 1024 * 1024,
+            max_archive_bytes: 1024 * 1024,
+            ..StegoLimits::default()
+        };
+        assert_eq!(
+            post_archive(app(Some(batch_body_limit(&small))), len).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
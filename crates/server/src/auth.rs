@@ -0,0 +1,282 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use common::{AuthConfig, ClusterConfig, Role};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{Any, CorsLayer};
+
+use crate::api::AppError;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// The API key a request was authenticated with, for per-client accounting
+/// further down the stack
+#[derive(Debug, Clone)]
+pub struct ClientId {
+    pub name: String,
+    pub roles: Vec<Role>,
+}
+
+/// Configured API keys, by the hex SHA-256 of the key
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKey>,
+}
+
+struct ApiKey {
+    name: String,
+    roles: Vec<Role>,
+}
+
+fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+impl ApiKeys {
+    pub fn from_config(config: &AuthConfig) -> Self {
+        let mut keys: HashMap<String, ApiKey> = config
+            .keys
+            .iter()
+            .map(|key| {
+                let entry = ApiKey {
+                    name: key.name.clone(),
+                    roles: key.roles.clone(),
+                };
+                (key.sha256.trim().to_ascii_lowercase(), entry)
+            })
+            .collect();
+
+        // Nodes copy cover, job and response files through /api routes too
+        if let Some(peer_key) = &config.peer_key {
+            let entry = ApiKey {
+                name: "peer".to_string(),
+                roles: vec![Role::Peer, Role::User],
+            };
+            keys.insert(hash(peer_key), entry);
+        }
+        Self { keys }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// The client `req` is made by, `None` on open routes or while
+    /// authentication is off; `/admin/*` is refused without keys
+    fn authorize(&self, req: &Request) -> Result<Option<ClientId>, AppError> {
+        let Some(role) = required_role(req.uri().path()) else {
+            return Ok(None);
+        };
+        if !self.is_enabled() {
+            if role == Role::Operator {
+                return Err(AppError::Forbidden(
+                    "Admin routes are disabled until auth keys are configured".to_string(),
+                ));
+            }
+            return Ok(None);
+        }
+
+        let key = presented_key(req.headers())
+            .ok_or_else(|| AppError::Unauthorized("Missing API key".to_string()))?;
+        let entry = self
+            .keys
+            .get(&hash(key))
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+        if !entry.roles.contains(&role) {
+            tracing::warn!(
+                "API key {} lacks the {} role for {} {}",
+                entry.name,
+                role.as_str(),
+                req.method(),
+                req.uri().path()
+            );
+            return Err(AppError::Forbidden(format!(
+                "API key lacks the {} role",
+                role.as_str()
+            )));
+        }
+        Ok(Some(ClientId {
+            name: entry.name.clone(),
+            roles: entry.roles.clone(),
+        }))
+    }
+}
+
+/// Role a route requires; the GUI, `/healthz`, `/metrics` and
+/// `/cluster/status` stay open
+fn required_role(path: &str) -> Option<Role> {
+    if path.starts_with("/api/") {
+        Some(Role::User)
+    } else if path.starts_with("/admin/") {
+        Some(Role::Operator)
+    } else if path.starts_with("/raft/") {
+        Some(Role::Peer)
+    } else {
+        None
+    }
+}
+
+/// The key sent as `Authorization: Bearer <key>` or `X-Api-Key: <key>`
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+}
+
+/// Reject requests without a key holding the route's role
+pub async fn middleware(
+    State(keys): State<Arc<ApiKeys>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(client) = keys.authorize(&req)? {
+        req.extensions_mut().insert(client);
+    }
+    Ok(next.run(req).await)
+}
+
+/// CORS for the GUI: the nodes' own origins (the GUI talks to every node) plus
+/// `auth.cors_origins`
+pub fn cors_layer(config: &ClusterConfig) -> CorsLayer {
+    let origins: Vec<HeaderValue> = config
+        .nodes
+        .iter()
+        .map(|n| format!("http://{}:{}", n.ip, n.http_port))
+        .chain(config.auth.cors_origins.iter().cloned())
+        .filter_map(|origin| match HeaderValue::from_str(&origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin {:?}", origin);
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            HeaderName::from_static(API_KEY_HEADER),
+            HeaderName::from_static("idempotency-key"),
+        ])
+        .expose_headers(Any)
+        .max_age(Duration::from_secs(600))
+}
+
+/// HTTP client for node-to-node file copies, sending `auth.peer_key` if set
+pub fn peer_client(config: &AuthConfig) -> anyhow::Result<reqwest::Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(peer_key) = &config.peer_key {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", peer_key))?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .default_headers(headers)
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use common::ApiKeyConfig;
+
+    fn keys(peer_key: Option<&str>) -> ApiKeys {
+        let config = AuthConfig {
+            keys: vec![
+                ApiKeyConfig {
+                    name: "alice".to_string(),
+                    sha256: hash("alice-key").to_ascii_uppercase(),
+                    roles: vec![Role::User],
+                },
+                ApiKeyConfig {
+                    name: "ops".to_string(),
+                    sha256: hash("ops-key"),
+                    roles: vec![Role::Operator],
+                },
+            ],
+            peer_key: peer_key.map(str::to_string),
+            cors_origins: Vec::new(),
+        };
+        ApiKeys::from_config(&config)
+    }
+
+    fn request(path: &str, key: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri(path);
+        if let Some(key) = key {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", key));
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn client(result: Result<Option<ClientId>, AppError>) -> Option<String> {
+        result.unwrap().map(|client| client.name)
+    }
+
+    #[test]
+    fn test_required_role() {
+        assert_eq!(required_role("/api/embed"), Some(Role::User));
+        assert_eq!(required_role("/admin/fail"), Some(Role::Operator));
+        assert_eq!(required_role("/raft/append-entries"), Some(Role::Peer));
+        for open in ["/", "/static/app.js", "/healthz", "/metrics", "/cluster/status", "/api", "/adminfail"] {
+            assert_eq!(required_role(open), None, "{}", open);
+        }
+    }
+
+    #[test]
+    fn test_presented_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(presented_key(&headers), None);
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static(" plain "));
+        assert_eq!(presented_key(&headers), Some("plain"));
+
+        // A bearer token wins over X-Api-Key; other schemes are ignored
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        assert_eq!(presented_key(&headers), Some("token"));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic dXNlcg=="));
+        assert_eq!(presented_key(&headers), Some("plain"));
+    }
+
+    #[test]
+    fn test_authorize_checks_roles() {
+        let keys = keys(Some("peer-secret"));
+        assert_eq!(client(keys.authorize(&request("/api/embed", Some("alice-key")))).as_deref(), Some("alice"));
+        assert_eq!(client(keys.authorize(&request("/admin/fail", Some("ops-key")))).as_deref(), Some("ops"));
+        assert_eq!(client(keys.authorize(&request("/raft/vote", Some("peer-secret")))).as_deref(), Some("peer"));
+        assert_eq!(client(keys.authorize(&request("/api/embed", Some("peer-secret")))).as_deref(), Some("peer"));
+        assert_eq!(client(keys.authorize(&request("/healthz", None))), None);
+
+        assert!(matches!(keys.authorize(&request("/api/embed", None)), Err(AppError::Unauthorized(_))));
+        assert!(matches!(keys.authorize(&request("/api/embed", Some("wrong"))), Err(AppError::Unauthorized(_))));
+        assert!(matches!(keys.authorize(&request("/admin/fail", Some("alice-key"))), Err(AppError::Forbidden(_))));
+        assert!(matches!(keys.authorize(&request("/raft/vote", Some("ops-key"))), Err(AppError::Forbidden(_))));
+    }
+
+    #[test]
+    fn test_admin_routes_fail_closed_without_keys() {
+        let keys = ApiKeys::from_config(&AuthConfig::default());
+        assert!(!keys.is_enabled());
+
+        assert_eq!(client(keys.authorize(&request("/api/embed", None))), None);
+        assert_eq!(client(keys.authorize(&request("/raft/vote", None))), None);
+        assert!(matches!(keys.authorize(&request("/admin/fail", None)), Err(AppError::Forbidden(_))));
+        assert!(matches!(keys.authorize(&request("/admin/restore", Some("any"))), Err(AppError::Forbidden(_))));
+    }
+}
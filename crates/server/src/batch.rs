@@ -0,0 +1,190 @@
+use std::io::{Cursor, Read, Write};
+use zip::write::SimpleFileOptions;
+
+/// Directory of a batch archive holding covers; every other file is a secret
+pub const COVERS_DIR: &str = "covers/";
+
+/// Secrets and covers of one batch, as `(name, bytes)` in upload order
+#[derive(Default)]
+pub struct BatchInput {
+    pub secrets: Vec<(String, Vec<u8>)>,
+    pub covers: Vec<(String, Vec<u8>)>,
+}
+
+impl BatchInput {
+    /// Bytes of every secret and cover
+    pub fn total_bytes(&self) -> u64 {
+        self.secrets
+            .iter()
+            .chain(&self.covers)
+            .map(|(_, data)| data.len() as u64)
+            .sum()
+    }
+
+    /// The cover whose file stem matches the secret's, if any
+    pub fn cover_for(&self, secret_name: &str) -> Option<&(String, Vec<u8>)> {
+        let stem = stem(secret_name);
+        self.covers.iter().find(|(name, _)| self::stem(name) == stem)
+    }
+}
+
+/// File name without directories and its last extension (`docs/a.tar.gz` -> `a.tar`)
+pub fn stem(name: &str) -> &str {
+    let file = name.rsplit('/').next().unwrap_or(name);
+    match file.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file,
+    }
+}
+
+/// Split a ZIP into secrets and covers, skipping directories and rejecting
+/// entries that inflate beyond `max_entry_bytes` or, together, beyond
+/// `max_total_bytes`
+pub fn read_archive(bytes: &[u8], max_entry_bytes: u64, max_total_bytes: u64) -> anyhow::Result<BatchInput> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut input = BatchInput::default();
+    let mut remaining = max_total_bytes;
+
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+
+        // The declared size can lie, so cap what is actually inflated
+        let limit = max_entry_bytes.min(remaining);
+        let mut data = Vec::new();
+        entry.take(limit.saturating_add(1)).read_to_end(&mut data)?;
+        if data.len() as u64 > max_entry_bytes {
+            anyhow::bail!("{} is larger than {} bytes", name, max_entry_bytes);
+        }
+        if data.len() as u64 > remaining {
+            anyhow::bail!("Archive inflates to more than {} bytes", max_total_bytes);
+        }
+        remaining -= data.len() as u64;
+
+        match name.strip_prefix(COVERS_DIR) {
+            Some(cover) if !cover.is_empty() => input.covers.push((name, data)),
+            _ => input.secrets.push((name, data)),
+        }
+    }
+    Ok(input)
+}
+
+/// Name of the per-item results file in a batch's output archive
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// The output ZIP, built as items complete: stego PNGs (already compressed,
+/// so stored) plus the deflated manifest
+pub struct ArchiveWriter {
+    writer: zip::ZipWriter<Cursor<Vec<u8>>>,
+}
+
+impl ArchiveWriter {
+    pub fn new() -> Self {
+        Self {
+            writer: zip::ZipWriter::new(Cursor::new(Vec::new())),
+        }
+    }
+
+    pub fn add_image(&mut self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        self.writer.start_file(name, stored)?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// Add the manifest and return the finished archive
+    pub fn finish(mut self, manifest: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let deflated = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        self.writer.start_file(MANIFEST_NAME, deflated)?;
+        self.writer.write_all(manifest)?;
+        Ok(self.writer.finish()?.into_inner())
+    }
+}
+
+impl Default for ArchiveWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let deflated = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, data) in entries {
+            if name.ends_with('/') {
+                writer.add_directory(*name, deflated).unwrap();
+            } else {
+                writer.start_file(*name, deflated).unwrap();
+                writer.write_all(data).unwrap();
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("docs/report.pdf"), "report");
+        assert_eq!(stem("covers/report.png"), "report");
+        assert_eq!(stem("a.tar.gz"), "a.tar");
+        assert_eq!(stem("README"), "README");
+        assert_eq!(stem(".hidden"), ".hidden");
+        assert_eq!(stem("dir/"), "");
+    }
+
+    #[test]
+    fn test_read_archive_splits_secrets_and_covers() {
+        let archive = zip(&[
+            ("docs/", b""),
+            ("docs/report.pdf", b"secret"),
+            ("covers/", b""),
+            ("covers/report.png", b"cover"),
+            ("notes.txt", b"more"),
+        ]);
+        let input = read_archive(&archive, 1024, 1024).unwrap();
+
+        let names: Vec<&str> = input.secrets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["docs/report.pdf", "notes.txt"]);
+        assert_eq!(input.covers.len(), 1);
+        assert_eq!(input.cover_for("docs/report.pdf").unwrap().1, b"cover");
+        assert!(input.cover_for("notes.txt").is_none());
+        assert_eq!(input.total_bytes(), 15);
+    }
+
+    #[test]
+    fn test_archive_writer_stores_images_and_manifest() {
+        let mut writer = ArchiveWriter::new();
+        writer.add_image("report.png", b"png").unwrap();
+        let bytes = writer.finish(b"{}").unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let image = archive.by_name("report.png").unwrap();
+        assert_eq!(image.compression(), zip::CompressionMethod::Stored);
+        drop(image);
+        let mut manifest = String::new();
+        archive.by_name(MANIFEST_NAME).unwrap().read_to_string(&mut manifest).unwrap();
+        assert_eq!(manifest, "{}");
+    }
+
+    #[test]
+    fn test_read_archive_caps_inflated_sizes() {
+        // Highly compressible: a few hundred bytes on the wire
+        let big = vec![0u8; 100_000];
+        let archive = zip(&[("a.bin", &big), ("b.bin", &big)]);
+
+        let err = read_archive(&archive, 99_999, u64::MAX).err().unwrap();
+        assert!(err.to_string().contains("a.bin is larger than 99999"), "{}", err);
+
+        let err = read_archive(&archive, 100_000, 150_000).err().unwrap();
+        assert!(err.to_string().contains("more than 150000"), "{}", err);
+
+        assert_eq!(read_archive(&archive, 100_000, 200_000).unwrap().total_bytes(), 200_000);
+        assert!(read_archive(b"not a zip", 1024, 1024).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncWriteExt;
+
+/// Content-addressed files (named by hex SHA-256) whose metadata is replicated
+/// through the Raft log; nodes missing a file fetch it from a peer
+pub struct BlobStore {
+    dir: PathBuf,
+    client: reqwest::Client,
+}
+
+impl BlobStore {
+    /// `client` fetches from peers (see `auth::peer_client`)
+    pub fn new(dir: impl Into<PathBuf>, client: reqwest::Client) -> Self {
+        Self {
+            dir: dir.into(),
+            client,
+        }
+    }
+
+    fn path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(sha256)
+    }
+
+    /// Store `bytes`, returning their hex SHA-256
+    pub async fn put(&self, bytes: &[u8]) -> anyhow::Result<String> {
+        let sha256 = format!("{:x}", Sha256::digest(bytes));
+        self.write(&sha256, bytes).await?;
+        Ok(sha256)
+    }
+
+    async fn write(&self, sha256: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(sha256);
+        let tmp = path.with_extension("part");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    /// Start storing a blob whose content arrives in chunks
+    pub async fn writer(&self) -> anyhow::Result<BlobWriter> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let tmp = self.dir.join(format!("{}.part", uuid::Uuid::new_v4()));
+        let file = tokio::fs::File::create(&tmp).await?;
+        Ok(BlobWriter {
+            dir: self.dir.clone(),
+            tmp,
+            file,
+            hasher: Sha256::new(),
+        })
+    }
+
+    pub async fn remove(&self, sha256: &str) {
+        let _ = tokio::fs::remove_file(self.path(sha256)).await;
+    }
+
+    /// Hashes of the stored blobs last written more than `min_age` ago
+    pub async fn older_than(&self, min_age: Duration) -> Vec<String> {
+        let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+            return Vec::new();
+        };
+        let mut hashes = Vec::new();
+        while let Ok(Some(entry)) = dir.next_entry().await {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            // Skip downloads in progress (`<sha256>.part`)
+            if name.contains('.') {
+                continue;
+            }
+            let age = entry
+                .metadata()
+                .await
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if age.is_some_and(|age| age > min_age) {
+                hashes.push(name);
+            }
+        }
+        hashes
+    }
+
+    /// The blob, fetched from `urls` (tried in order) and verified against its
+    /// hash if it is not stored locally yet
+    pub async fn get(&self, sha256: &str, urls: &[String]) -> anyhow::Result<Vec<u8>> {
+        if let Ok(bytes) = tokio::fs::read(self.path(sha256)).await {
+            return Ok(bytes);
+        }
+
+        for url in urls {
+            let bytes = match self.fetch(url).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    tracing::debug!("Failed to fetch {}: {}", url, e);
+                    continue;
+                }
+            };
+
+            if format!("{:x}", Sha256::digest(&bytes)) != sha256 {
+                tracing::warn!("Blob from {} does not match its hash", url);
+                continue;
+            }
+            self.write(sha256, &bytes).await?;
+            tracing::info!("Fetched {} ({} bytes)", url, bytes.len());
+            return Ok(bytes);
+        }
+
+        anyhow::bail!("Blob {} is not stored on any reachable node", sha256)
+    }
+
+    async fn fetch(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let resp = self.client.get(url).send().await?.error_for_status()?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+}
+
+/// A blob written chunk by chunk; dropping it before `commit` discards it
+pub struct BlobWriter {
+    dir: PathBuf,
+    tmp: PathBuf,
+    file: tokio::fs::File,
+    hasher: Sha256,
+}
+
+impl BlobWriter {
+    pub async fn write(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        self.hasher.update(chunk);
+        self.file.write_all(chunk).await?;
+        Ok(())
+    }
+
+    /// Store the blob, returning its hex SHA-256
+    pub async fn commit(mut self) -> anyhow::Result<String> {
+        self.file.flush().await?;
+        let sha256 = format!("{:x}", std::mem::take(&mut self.hasher).finalize());
+        tokio::fs::rename(&self.tmp, self.dir.join(&sha256)).await?;
+        Ok(sha256)
+    }
+}
+
+impl Drop for BlobWriter {
+    fn drop(&mut self) {
+        // Already renamed once committed
+        let _ = std::fs::remove_file(&self.tmp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tempfile::TempDir;
+
+    /// Serve `bytes` at `/blob` on a local port, returning the URL
+    async fn serve(bytes: &'static [u8]) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/blob", get(move || async move { bytes }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/blob", addr)
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    #[tokio::test]
+    async fn test_put_and_get_local() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::new(dir.path(), reqwest::Client::new());
+
+        let hash = store.put(b"cover bytes").await.unwrap();
+        assert_eq!(hash, sha256(b"cover bytes"));
+        assert_eq!(store.get(&hash, &[]).await.unwrap(), b"cover bytes");
+
+        store.remove(&hash).await;
+        assert!(store.get(&hash, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_writer_commits_or_discards() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::new(dir.path(), reqwest::Client::new());
+
+        let mut writer = store.writer().await.unwrap();
+        writer.write(b"response ").await.unwrap();
+        writer.write(b"body").await.unwrap();
+        let hash = writer.commit().await.unwrap();
+        assert_eq!(hash, sha256(b"response body"));
+        assert_eq!(store.get(&hash, &[]).await.unwrap(), b"response body");
+
+        let mut writer = store.writer().await.unwrap();
+        writer.write(b"partial").await.unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_fetches_and_verifies_peer_copies() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::new(dir.path(), reqwest::Client::new());
+        let hash = sha256(b"secret input");
+
+        // A tampered copy is skipped for the next URL, then the verified one is kept
+        let urls = vec![serve(b"tampered input").await, serve(b"secret input").await];
+        assert_eq!(store.get(&hash, &urls).await.unwrap(), b"secret input");
+        assert_eq!(store.get(&hash, &[]).await.unwrap(), b"secret input");
+
+        let other = sha256(b"missing");
+        let err = store.get(&other, &urls[..1]).await.unwrap_err();
+        assert!(err.to_string().contains("not stored"), "{}", err);
+        assert!(!dir.path().join(&other).exists());
+    }
+
+    #[tokio::test]
+    async fn test_older_than_skips_recent_and_partial_files() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::new(dir.path(), reqwest::Client::new());
+        let hash = store.put(b"old").await.unwrap();
+        std::fs::write(dir.path().join("abc.part"), b"partial").unwrap();
+
+        assert!(store.older_than(Duration::from_secs(3600)).await.is_empty());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(store.older_than(Duration::ZERO).await, vec![hash]);
+    }
+}
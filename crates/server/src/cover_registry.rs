@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+
+use crate::blobs::BlobStore;
+
+/// Metadata of a managed cover, replicated through the Raft log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverMeta {
+    pub id: String,
+    pub name: String,
+    /// Hex SHA-256 of the uploaded file; also its blob file name
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub width: u32,
+    pub height: u32,
+    pub mime: String,
+    /// Node that received the upload and holds the blob
+    pub origin_node: String,
+}
+
+/// Returned when deleting the current default cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultCoverInUse;
+
+/// Covers managed through `/api/covers`: metadata applied from the log, blobs
+/// stored locally under `data/node-<id>/covers` and fetched from peers on demand
+pub struct CoverRegistry {
+    blobs: BlobStore,
+    covers: RwLock<BTreeMap<String, CoverMeta>>,
+    default_id: RwLock<Option<String>>,
+}
+
+impl CoverRegistry {
+    pub fn new(dir: impl Into<PathBuf>, client: reqwest::Client) -> Self {
+        Self {
+            blobs: BlobStore::new(dir, client),
+            covers: RwLock::new(BTreeMap::new()),
+            default_id: RwLock::new(None),
+        }
+    }
+
+    pub async fn list(&self) -> Vec<CoverMeta> {
+        self.covers.read().await.values().cloned().collect()
+    }
+
+    pub async fn get(&self, id: &str) -> Option<CoverMeta> {
+        self.covers.read().await.get(id).cloned()
+    }
+
+    pub async fn default_id(&self) -> Option<String> {
+        self.default_id.read().await.clone()
+    }
+
+    pub async fn insert(&self, meta: CoverMeta) {
+        self.covers.write().await.insert(meta.id.clone(), meta);
+    }
+
+    /// Remove a cover. The default cover is kept until another one is set:
+    /// falling back to each node's own cover file would make nodes diverge.
+    pub async fn remove(&self, id: &str) -> Result<Option<CoverMeta>, DefaultCoverInUse> {
+        if self.default_id.read().await.as_deref() == Some(id) {
+            return Err(DefaultCoverInUse);
+        }
+        let Some(meta) = self.covers.write().await.remove(id) else {
+            return Ok(None);
+        };
+
+        // Blobs are shared by covers with identical content
+        let still_used = self.covers.read().await.values().any(|c| c.sha256 == meta.sha256);
+        if !still_used {
+            self.blobs.remove(&meta.sha256).await;
+        }
+        Ok(Some(meta))
+    }
+
+    pub async fn set_default(&self, id: &str) {
+        *self.default_id.write().await = Some(id.to_string());
+    }
+
+    /// Store an uploaded file, returning its hex SHA-256
+    pub async fn store_blob(&self, bytes: &[u8]) -> anyhow::Result<String> {
+        self.blobs.put(bytes).await
+    }
+
+    /// The cover's file, fetched from the nodes at `peer_urls` (base URLs, tried
+    /// in order) if it is not stored locally yet
+    pub async fn blob(&self, meta: &CoverMeta, peer_urls: &[String]) -> anyhow::Result<Vec<u8>> {
+        let urls: Vec<String> = peer_urls
+            .iter()
+            .map(|base| format!("{}/api/covers/{}/blob", base, meta.id))
+            .collect();
+        self.blobs.get(&meta.sha256, &urls).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn meta(id: &str, sha256: &str) -> CoverMeta {
+        CoverMeta {
+            id: id.to_string(),
+            name: format!("{}.png", id),
+            sha256: sha256.to_string(),
+            size_bytes: 5,
+            width: 1,
+            height: 1,
+            mime: "image/png".to_string(),
+            origin_node: "node-1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remove_keeps_the_default_cover() {
+        let dir = TempDir::new().unwrap();
+        let registry = CoverRegistry::new(dir.path(), reqwest::Client::new());
+        let sha256 = registry.store_blob(b"cover").await.unwrap();
+        registry.insert(meta("a", &sha256)).await;
+        registry.insert(meta("b", &sha256)).await;
+        registry.set_default("a").await;
+
+        assert_eq!(registry.remove("a").await.unwrap_err(), DefaultCoverInUse);
+        assert!(registry.get("a").await.is_some());
+
+        registry.set_default("b").await;
+        assert_eq!(registry.remove("a").await.unwrap().unwrap().id, "a");
+        assert!(registry.remove("a").await.unwrap().is_none());
+        // The blob is still used by "b"
+        assert_eq!(registry.blob(&meta("b", &sha256), &[]).await.unwrap(), b"cover");
+    }
+}
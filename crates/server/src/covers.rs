@@ -0,0 +1,145 @@
+use image::{DynamicImage, GenericImageView};
+use std::path::{Path, PathBuf};
+
+/// Indexed metadata for one cover in the library directory
+#[derive(Debug, Clone)]
+pub struct CoverEntry {
+    pub id: String,
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub texture_score: f64,
+}
+
+impl CoverEntry {
+    /// Payload capacity when embedding with `lsb_per_channel` bits per channel
+    pub fn capacity_bytes(&self, lsb_per_channel: u8) -> u64 {
+        stego::capacity_bytes(self.width, self.height, lsb_per_channel)
+    }
+
+    /// Payload capacity under `options`, counting only the slots its mask leaves
+    /// writable (0 if the mask does not fit this cover)
+    pub fn capacity_for(&self, options: &stego::EmbedOptions) -> u64 {
+        match &options.mask {
+            Some(mask) => stego::masked_capacity(self.width, self.height, mask).unwrap_or(0),
+            None => self.capacity_bytes(options.lsb_per_channel),
+        }
+    }
+}
+
+/// Covers found in `stego.cover_library_dir`, indexed once at startup
+#[derive(Debug, Default)]
+pub struct CoverLibrary {
+    entries: Vec<CoverEntry>,
+}
+
+impl CoverLibrary {
+    /// Index every PNG/JPEG in `dir`; unreadable files are skipped with a warning
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let mut entries = Vec::new();
+
+        for dir_entry in std::fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            let is_image = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg"))
+                .unwrap_or(false);
+            if !is_image {
+                continue;
+            }
+
+            let img = match image::open(&path) {
+                Ok(img) => img,
+                Err(e) => {
+                    tracing::warn!("Skipping cover {:?}: {}", path, e);
+                    continue;
+                }
+            };
+
+            let (width, height) = img.dimensions();
+            let id = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            entries.push(CoverEntry {
+                id,
+                width,
+                height,
+                texture_score: stego::texture_score(&img),
+                path,
+            });
+        }
+
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        tracing::info!("Indexed {} covers from {:?}", entries.len(), dir);
+
+        Ok(Self { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Pick the cover with the smallest capacity under `options` that still
+    /// fits `required_bytes`, preferring the highest texture score among equally
+    /// sized covers
+    pub fn select(&self, required_bytes: u64, options: &stego::EmbedOptions) -> Option<&CoverEntry> {
+        self.entries
+            .iter()
+            .map(|entry| (entry, entry.capacity_for(options)))
+            .filter(|(_, capacity)| *capacity >= required_bytes)
+            .min_by(|(a, a_capacity), (b, b_capacity)| {
+                a_capacity
+                    .cmp(b_capacity)
+                    .then(b.texture_score.total_cmp(&a.texture_score))
+            })
+            .map(|(entry, _)| entry)
+    }
+
+    /// Largest capacity in the library under `options` (0 if empty)
+    pub fn max_capacity(&self, options: &stego::EmbedOptions) -> u64 {
+        self.entries
+            .iter()
+            .map(|entry| entry.capacity_for(options))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn load_image(&self, entry: &CoverEntry) -> anyhow::Result<DynamicImage> {
+        Ok(image::open(&entry.path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, width: u32, height: u32) -> CoverEntry {
+        CoverEntry {
+            id: id.to_string(),
+            path: PathBuf::from(id),
+            width,
+            height,
+            texture_score: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_select_uses_masked_capacity() {
+        let library = CoverLibrary {
+            entries: vec![entry("small.png", 100, 100), entry("large.png", 120, 120)],
+        };
+        let plain = stego::EmbedOptions::new();
+        let blue_only = stego::EmbedOptions::new()
+            .mask(stego::EmbedMask::new().channels(stego::ChannelMask::BLUE));
+
+        // 3750 bytes unmasked, about a third of that in the blue channel alone
+        assert_eq!(library.select(1500, &plain).unwrap().id, "small.png");
+        assert_eq!(library.select(1500, &blue_only).unwrap().id, "large.png");
+        assert!(library.select(2000, &blue_only).is_none());
+        assert!(library.max_capacity(&blue_only) < library.max_capacity(&plain) / 2);
+    }
+}
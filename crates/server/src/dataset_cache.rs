@@ -0,0 +1,83 @@
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Encoded bytes kept in total; the default dataset (about 2 MB per image) fits entirely
+const MAX_CACHED_BYTES: usize = 256 * 1024 * 1024;
+
+/// Larger images are regenerated on every request rather than crowding out the rest
+const MAX_CACHED_IMAGE_BYTES: usize = MAX_CACHED_BYTES / 8;
+
+/// Encoded PNGs of recently served `/api/dataset/:index` images.
+///
+/// Images depend only on the config and the index, so an entry never goes
+/// stale; the oldest are evicted once more than `MAX_CACHED_BYTES` are held.
+#[derive(Default)]
+pub struct DatasetCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    images: HashMap<usize, Bytes>,
+    order: VecDeque<usize>,
+    bytes: usize,
+}
+
+impl DatasetCache {
+    pub fn get(&self, index: usize) -> Option<Bytes> {
+        self.inner.lock().unwrap().images.get(&index).cloned()
+    }
+
+    pub fn insert(&self, index: usize, png: Bytes) {
+        if png.len() > MAX_CACHED_IMAGE_BYTES {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.bytes += png.len();
+        match inner.images.insert(index, png) {
+            Some(previous) => inner.bytes -= previous.len(),
+            None => inner.order.push_back(index),
+        }
+
+        while inner.bytes > MAX_CACHED_BYTES {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = inner.images.remove(&oldest) {
+                inner.bytes -= evicted.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_oldest_beyond_capacity() {
+        let cache = DatasetCache::default();
+        let per_image = MAX_CACHED_BYTES / 10;
+        for index in 0..=10 {
+            cache.insert(index, Bytes::from(vec![index as u8; per_image]));
+        }
+        cache.insert(1, Bytes::from_static(b"again"));
+
+        assert!(cache.get(0).is_none());
+        assert_eq!(cache.get(1).unwrap(), Bytes::from_static(b"again"));
+        assert_eq!(cache.get(10).unwrap().len(), per_image);
+        assert!(cache.inner.lock().unwrap().bytes <= MAX_CACHED_BYTES);
+    }
+
+    #[test]
+    fn test_skips_oversized_images() {
+        let cache = DatasetCache::default();
+        cache.insert(0, Bytes::from(vec![0; MAX_CACHED_IMAGE_BYTES + 1]));
+        cache.insert(1, Bytes::from(vec![1; MAX_CACHED_IMAGE_BYTES]));
+
+        assert!(cache.get(0).is_none());
+        assert!(cache.get(1).is_some());
+        assert_eq!(cache.inner.lock().unwrap().bytes, MAX_CACHED_IMAGE_BYTES);
+    }
+}
@@ -0,0 +1,472 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use common::IdempotencyConfig;
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+use crate::api::AppError;
+use crate::auth::ClientId;
+use crate::blobs::BlobStore;
+use crate::replication::{self, Command, IdempotencyCommand};
+use crate::state::AppState;
+
+const KEY_HEADER: &str = "idempotency-key";
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Scope of requests without an API key (authentication disabled)
+const ANONYMOUS_CLIENT: &str = "";
+
+/// A response replayed for retries carrying the same `Idempotency-Key`.
+///
+/// Stored responses are replicated through the Raft log when the leader
+/// handled the request; the body is a blob fetched from `origin_node`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredResponse {
+    /// API key name the key belongs to (keys of different clients never clash)
+    #[serde(default)]
+    pub client: String,
+    pub key: String,
+    /// Route the key was first used on
+    pub path: String,
+    /// SHA-256 of the first request's body; a retry must send the same body
+    #[serde(default)]
+    pub request_sha256: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body_sha256: String,
+    /// Unix time (seconds) the response was stored
+    pub created_at: u64,
+    pub origin_node: String,
+}
+
+/// Client name and `Idempotency-Key`
+type ScopedKey = (String, String);
+
+/// Successful responses by client and `Idempotency-Key`, bodies under
+/// `data/node-<id>/responses`, kept for `idempotency.ttl_secs`
+pub struct IdempotencyStore {
+    blobs: BlobStore,
+    responses: RwLock<HashMap<ScopedKey, StoredResponse>>,
+    /// Keys whose first request is still being handled on this node
+    in_flight: Mutex<HashSet<ScopedKey>>,
+    ttl_secs: u64,
+    /// Larger responses are kept on the node that produced them only
+    max_replicated_bytes: u64,
+    /// Larger responses are not stored
+    max_stored_bytes: u64,
+}
+
+/// Marks a key as in flight until dropped
+struct InFlight<'a> {
+    store: &'a IdempotencyStore,
+    key: ScopedKey,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.store.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl StoredResponse {
+    fn scoped_key(&self) -> ScopedKey {
+        (self.client.clone(), self.key.clone())
+    }
+}
+
+impl IdempotencyStore {
+    pub fn new(dir: impl Into<PathBuf>, client: reqwest::Client, config: &IdempotencyConfig) -> Self {
+        Self {
+            blobs: BlobStore::new(dir, client),
+            responses: RwLock::new(HashMap::new()),
+            in_flight: Mutex::new(HashSet::new()),
+            ttl_secs: config.ttl_secs,
+            max_replicated_bytes: config.max_replicated_bytes,
+            max_stored_bytes: config.max_stored_bytes,
+        }
+    }
+
+    /// Whether `stored` is still within `idempotency.ttl_secs`
+    pub fn is_live(&self, stored: &StoredResponse) -> bool {
+        stored.created_at.saturating_add(self.ttl_secs) > now_secs()
+    }
+
+    pub async fn get(&self, client: &str, key: &str) -> Option<StoredResponse> {
+        let responses = self.responses.read().await;
+        responses
+            .get(&(client.to_string(), key.to_string()))
+            .filter(|stored| self.is_live(stored))
+            .cloned()
+    }
+
+    /// Keep `stored` unless it has expired or the key already has a live response
+    pub async fn insert(&self, stored: StoredResponse) {
+        if !self.is_live(&stored) {
+            return;
+        }
+        let mut responses = self.responses.write().await;
+        match responses.get(&stored.scoped_key()) {
+            Some(existing) if self.is_live(existing) => {}
+            _ => {
+                responses.insert(stored.scoped_key(), stored);
+            }
+        }
+    }
+
+    /// The locally stored body `sha256` of a live response
+    pub async fn local_body(&self, sha256: &str) -> Option<Vec<u8>> {
+        let referenced = self
+            .responses
+            .read()
+            .await
+            .values()
+            .any(|stored| stored.body_sha256 == sha256 && self.is_live(stored));
+        if !referenced {
+            return None;
+        }
+        self.blobs.get(sha256, &[]).await.ok()
+    }
+
+    /// Claim `key` for a first request; `None` while another one holds it
+    fn begin(&self, key: ScopedKey) -> Option<InFlight<'_>> {
+        if !self.in_flight.lock().unwrap().insert(key.clone()) {
+            return None;
+        }
+        Some(InFlight { store: self, key })
+    }
+
+    /// The stored body, fetched from the nodes at `peer_urls` (base URLs, tried
+    /// in order) if it is not stored locally yet
+    pub async fn body(&self, stored: &StoredResponse, peer_urls: &[String]) -> anyhow::Result<Vec<u8>> {
+        let urls: Vec<String> = peer_urls
+            .iter()
+            .map(|base| format!("{}/raft/responses/{}", base, stored.body_sha256))
+            .collect();
+        self.blobs.get(&stored.body_sha256, &urls).await
+    }
+
+    /// Write a response body to the blob store as it is read, returning the
+    /// body to send and, unless it exceeded `idempotency.max_stored_bytes` or
+    /// could not be written, its hex SHA-256 and size
+    async fn store_body(&self, body: Body) -> Result<(Body, Option<(String, u64)>), axum::Error> {
+        let mut rest = body.into_data_stream();
+        let mut frames: Vec<Bytes> = Vec::new();
+        // The frames read so far, then the rest of the body unread
+        let passthrough = |frames: Vec<Bytes>, rest| {
+            let frames = stream::iter(frames.into_iter().map(Ok));
+            Body::from_stream(frames.chain(rest))
+        };
+
+        let mut writer = match self.blobs.writer().await {
+            Ok(writer) => writer,
+            Err(e) => {
+                tracing::warn!("Failed to store response: {}", e);
+                return Ok((Body::from_stream(rest), None));
+            }
+        };
+        let mut size = 0u64;
+        while let Some(frame) = rest.next().await {
+            let frame = frame?;
+            size += frame.len() as u64;
+            if size > self.max_stored_bytes {
+                frames.push(frame);
+                return Ok((passthrough(frames, rest), None));
+            }
+            if let Err(e) = writer.write(&frame).await {
+                tracing::warn!("Failed to store response: {}", e);
+                frames.push(frame);
+                return Ok((passthrough(frames, rest), None));
+            }
+            frames.push(frame);
+        }
+
+        match writer.commit().await {
+            Ok(sha256) => Ok((passthrough(frames, rest), Some((sha256, size)))),
+            Err(e) => {
+                tracing::warn!("Failed to store response: {}", e);
+                Ok((passthrough(frames, rest), None))
+            }
+        }
+    }
+
+    /// Drop expired responses and bodies no live response uses
+    async fn sweep(&self) {
+        let mut responses = self.responses.write().await;
+        let expired: Vec<StoredResponse> = responses
+            .values()
+            .filter(|stored| !self.is_live(stored))
+            .cloned()
+            .collect();
+
+        for stored in expired {
+            responses.remove(&stored.scoped_key());
+            if !responses.values().any(|r| r.body_sha256 == stored.body_sha256) {
+                self.blobs.remove(&stored.body_sha256).await;
+            }
+        }
+    }
+}
+
+/// What identifies the first request made with a key
+struct RequestSummary {
+    client: String,
+    key: String,
+    path: String,
+    request_sha256: String,
+}
+
+/// Replay the stored response for a repeated `Idempotency-Key`, or run the
+/// handler and store its response if it succeeded
+pub async fn middleware(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let store = &state.idempotency;
+    let Some(key) = req.headers().get(KEY_HEADER) else {
+        return next.run(req).await;
+    };
+    if store.ttl_secs == 0 {
+        return next.run(req).await;
+    }
+    let key = match key.to_str() {
+        Ok(key) if (1..=255).contains(&key.len()) => key.to_string(),
+        _ => {
+            return AppError::BadRequest(
+                "Idempotency-Key must be 1 to 255 visible ASCII characters".to_string(),
+            )
+            .into_response()
+        }
+    };
+    let path = req.uri().path().to_string();
+    let client = match req.extensions().get::<ClientId>() {
+        Some(client) => client.name.clone(),
+        None => ANONYMOUS_CLIENT.to_string(),
+    };
+
+    // The body is hashed so a retry can be told apart from a different request;
+    // the route's body limit (`api::request_body_limit`) has the same size
+    let max_request_bytes = state.config.stego.limits.max_request_bytes as usize;
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, max_request_bytes).await {
+        Ok(body) => body,
+        Err(e) => {
+            return AppError::BadRequest(format!("Failed to read request body: {}", e))
+                .into_response()
+        }
+    };
+    let request_sha256 = format!("{:x}", Sha256::digest(&body));
+    let req = Request::from_parts(parts, Body::from(body));
+
+    let Some(_in_flight) = store.begin((client.clone(), key.clone())) else {
+        return AppError::Conflict(
+            "A request with this Idempotency-Key is still in progress".to_string(),
+        )
+        .into_response();
+    };
+
+    if let Some(stored) = store.get(&client, &key).await {
+        if stored.path != path {
+            return AppError::UnprocessableEntity(format!(
+                "Idempotency-Key was already used for {}",
+                stored.path
+            ))
+            .into_response();
+        }
+        if stored.request_sha256 != request_sha256 {
+            return AppError::UnprocessableEntity(
+                "Idempotency-Key was already used with a different request body".to_string(),
+            )
+            .into_response();
+        }
+        match replay(&state, &stored).await {
+            Ok(response) => return response,
+            // Lost with every node holding it; handle the request again
+            Err(e) => tracing::warn!("Cannot replay response for key {}: {}", key, e),
+        }
+    }
+
+    let response = next.run(req).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let request = RequestSummary {
+        client,
+        key,
+        path,
+        request_sha256,
+    };
+    remember(&state, request, response).await
+}
+
+async fn replay(state: &AppState, stored: &StoredResponse) -> anyhow::Result<Response> {
+    let peer_urls = state.peer_urls(&stored.origin_node);
+    let body = state.idempotency.body(stored, &peer_urls).await?;
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = StatusCode::from_u16(stored.status)?;
+    let headers = response.headers_mut();
+    for (name, value) in &stored.headers {
+        headers.append(HeaderName::try_from(name.as_str())?, HeaderValue::try_from(value.as_str())?);
+    }
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    Ok(response)
+}
+
+/// Store a successful response (replicated if this node leads and it is at
+/// most `idempotency.max_replicated_bytes`) and return it
+async fn remember(state: &AppState, request: RequestSummary, response: Response) -> Response {
+    let (parts, body) = response.into_parts();
+    let (body, stored_body) = match state.idempotency.store_body(body).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            return AppError::Internal(format!("Failed to read response: {}", e)).into_response()
+        }
+    };
+    let Some((body_sha256, size)) = stored_body else {
+        tracing::debug!("Response for key {} is not stored", request.key);
+        return Response::from_parts(parts, body);
+    };
+
+    let stored = StoredResponse {
+        client: request.client,
+        key: request.key,
+        path: request.path,
+        request_sha256: request.request_sha256,
+        status: parts.status.as_u16(),
+        headers: parts
+            .headers
+            .iter()
+            .filter(|(name, _)| **name != axum::http::header::CONTENT_LENGTH)
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body_sha256,
+        created_at: now_secs(),
+        origin_node: state.node_id.clone(),
+    };
+    state.idempotency.insert(stored.clone()).await;
+    let replicate = size <= state.idempotency.max_replicated_bytes;
+    if replicate && state.raft_node.is_leader().await {
+        let command = Command::Idempotency(IdempotencyCommand::Put(stored));
+        if let Err(e) = replication::propose(state, &command).await {
+            tracing::warn!("Response kept on this node only: {}", e);
+        }
+    }
+
+    Response::from_parts(parts, body)
+}
+
+/// Periodically drop expired responses
+pub fn start_sweeper(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            state.idempotency.sweep().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn store(ttl_secs: u64) -> (IdempotencyStore, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let config = IdempotencyConfig {
+            ttl_secs,
+            ..IdempotencyConfig::default()
+        };
+        (IdempotencyStore::new(dir.path(), reqwest::Client::new(), &config), dir)
+    }
+
+    fn stored(client: &str, key: &str, body_sha256: &str, created_at: u64) -> StoredResponse {
+        StoredResponse {
+            client: client.to_string(),
+            key: key.to_string(),
+            path: "/api/embed".to_string(),
+            request_sha256: "req".to_string(),
+            status: 200,
+            headers: Vec::new(),
+            body_sha256: body_sha256.to_string(),
+            created_at,
+            origin_node: "n1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_by_client() {
+        let (store, _dir) = store(60);
+        store.insert(stored("alice", "k1", "a", now_secs())).await;
+        store.insert(stored("bob", "k1", "b", now_secs())).await;
+
+        assert_eq!(store.get("alice", "k1").await.unwrap().body_sha256, "a");
+        assert_eq!(store.get("bob", "k1").await.unwrap().body_sha256, "b");
+        assert!(store.get(ANONYMOUS_CLIENT, "k1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_insert_keeps_first_live_response() {
+        let (store, _dir) = store(60);
+        store.insert(stored("alice", "k1", "first", now_secs())).await;
+        store.insert(stored("alice", "k1", "second", now_secs())).await;
+        assert_eq!(store.get("alice", "k1").await.unwrap().body_sha256, "first");
+
+        // An expired response is never inserted, nor replayed
+        let old = stored("alice", "k2", "old", now_secs() - 120);
+        assert!(!store.is_live(&old));
+        store.insert(old).await;
+        assert!(store.get("alice", "k2").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_begin_claims_key_until_dropped() {
+        let (store, _dir) = store(60);
+        let key = ("alice".to_string(), "k1".to_string());
+
+        let in_flight = store.begin(key.clone()).unwrap();
+        assert!(store.begin(key.clone()).is_none());
+        assert!(store.begin(("bob".to_string(), "k1".to_string())).is_some());
+        drop(in_flight);
+        assert!(store.begin(key).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_store_body_skips_bodies_over_the_cap() {
+        let dir = TempDir::new().unwrap();
+        let config = IdempotencyConfig {
+            max_stored_bytes: 8,
+            ..IdempotencyConfig::default()
+        };
+        let store = IdempotencyStore::new(dir.path(), reqwest::Client::new(), &config);
+        let chunks = |parts: &'static [&'static [u8]]| {
+            Body::from_stream(stream::iter(parts.iter().map(|p| Ok::<_, axum::Error>(Bytes::from_static(p)))))
+        };
+
+        let (body, stored) = store.store_body(chunks(&[b"smal", b"l"])).await.unwrap();
+        let (sha256, size) = stored.unwrap();
+        assert_eq!(size, 5);
+        assert_eq!(store.blobs.get(&sha256, &[]).await.unwrap(), b"small");
+        assert_eq!(axum::body::to_bytes(body, usize::MAX).await.unwrap().as_ref(), b"small");
+
+        // The whole body still reaches the client, but nothing is kept
+        let (body, stored) = store.store_body(chunks(&[b"too ", b"large", b" body"])).await.unwrap();
+        assert!(stored.is_none());
+        assert_eq!(axum::body::to_bytes(body, usize::MAX).await.unwrap().as_ref(), b"too large body");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
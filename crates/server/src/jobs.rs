@@ -0,0 +1,535 @@
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+use crate::api;
+use crate::blobs::BlobStore;
+use crate::replication::{self, Command, JobCommand};
+use crate::state::AppState;
+use crate::telemetry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Embed,
+    Extract,
+}
+
+/// Job inputs and settings, replicated when the job is submitted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSpec {
+    pub id: String,
+    pub kind: JobKind,
+    /// Secret (embed) or stego file (extract)
+    pub input_sha256: String,
+    /// Client-supplied cover (embed only; the node's default cover otherwise)
+    pub cover_sha256: Option<String>,
+    pub lsb_per_channel: u8,
+    pub compress: bool,
+    /// Node that received the submission and holds the inputs
+    pub origin_node: String,
+}
+
+/// Result file of a finished job, with the metadata the synchronous endpoints
+/// return as `X-*` headers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobOutput {
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub mime: String,
+    pub filename: String,
+    pub metadata: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum JobState {
+    Queued,
+    Running { node: String },
+    Succeeded { node: String, output: JobOutput },
+    Failed { error: String },
+}
+
+impl JobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running { .. } => "running",
+            JobState::Succeeded { .. } => "succeeded",
+            JobState::Failed { .. } => "failed",
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self, JobState::Succeeded { .. } | JobState::Failed { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JobRecord {
+    pub spec: JobSpec,
+    pub state: JobState,
+    /// Unix time (seconds) the leader recorded the outcome
+    pub finished_at: Option<u64>,
+}
+
+impl JobRecord {
+    /// Whether `sha256` is one of this job's input or result files
+    pub fn references(&self, sha256: &str) -> bool {
+        let output = match &self.state {
+            JobState::Succeeded { output, .. } => Some(output.sha256.as_str()),
+            _ => None,
+        };
+        self.spec.input_sha256 == sha256
+            || self.spec.cover_sha256.as_deref() == Some(sha256)
+            || output == Some(sha256)
+    }
+}
+
+/// Jobs submitted through `/api/jobs`: state applied from the Raft log, input
+/// and result files under `data/node-<id>/jobs`, kept for `jobs.ttl_secs`
+/// after they finish
+pub struct JobRegistry {
+    blobs: BlobStore,
+    jobs: RwLock<BTreeMap<String, JobRecord>>,
+    /// Progress (0-100) of jobs executing on this node
+    progress: Mutex<HashMap<String, u8>>,
+    ttl_secs: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl JobRegistry {
+    pub fn new(dir: impl Into<PathBuf>, client: reqwest::Client, ttl_secs: u64) -> Self {
+        Self {
+            blobs: BlobStore::new(dir, client),
+            jobs: RwLock::new(BTreeMap::new()),
+            progress: Mutex::new(HashMap::new()),
+            ttl_secs,
+        }
+    }
+
+    pub async fn get(&self, id: &str) -> Option<JobRecord> {
+        self.jobs.read().await.get(id).cloned()
+    }
+
+    pub async fn submit(&self, spec: JobSpec) {
+        let record = JobRecord {
+            spec,
+            state: JobState::Queued,
+            finished_at: None,
+        };
+        self.jobs
+            .write()
+            .await
+            .entry(record.spec.id.clone())
+            .or_insert(record);
+    }
+
+    /// Move an unfinished job to `state`; finished jobs keep their first outcome
+    pub async fn transition(&self, id: &str, state: JobState) {
+        if let Some(record) = self.jobs.write().await.get_mut(id) {
+            if !record.state.is_finished() {
+                record.state = state;
+            }
+        }
+    }
+
+    /// Record the outcome of an unfinished job, finished at `finished_at` as
+    /// seen by the leader so every node expires it alike
+    pub async fn finish(&self, id: &str, state: JobState, finished_at: u64) {
+        if let Some(record) = self.jobs.write().await.get_mut(id) {
+            if !record.state.is_finished() {
+                record.state = state;
+                record.finished_at = Some(finished_at);
+            }
+        }
+    }
+
+    /// Finished jobs older than `jobs.ttl_secs` at `now` (none if it is 0)
+    pub async fn expired(&self, now: u64) -> Vec<String> {
+        if self.ttl_secs == 0 {
+            return Vec::new();
+        }
+        self.jobs
+            .read()
+            .await
+            .values()
+            .filter(|record| {
+                record
+                    .finished_at
+                    .is_some_and(|at| at.saturating_add(self.ttl_secs) <= now)
+            })
+            .map(|record| record.spec.id.clone())
+            .collect()
+    }
+
+    /// Forget a job and delete the files no remaining job uses
+    pub async fn delete(&self, id: &str) {
+        let mut jobs = self.jobs.write().await;
+        let Some(record) = jobs.remove(id) else {
+            return;
+        };
+        let output = match &record.state {
+            JobState::Succeeded { output, .. } => Some(&output.sha256),
+            _ => None,
+        };
+        let files = std::iter::once(&record.spec.input_sha256)
+            .chain(&record.spec.cover_sha256)
+            .chain(output);
+        for sha256 in files {
+            if !jobs.values().any(|other| other.references(sha256)) {
+                self.blobs.remove(sha256).await;
+            }
+        }
+    }
+
+    /// Delete files no job references that are older than `jobs.ttl_secs`,
+    /// such as inputs of submissions that were never committed
+    pub async fn sweep_orphans(&self) {
+        if self.ttl_secs == 0 {
+            return;
+        }
+        let stale = self.blobs.older_than(Duration::from_secs(self.ttl_secs)).await;
+        let jobs = self.jobs.read().await;
+        for sha256 in stale {
+            if !jobs.values().any(|record| record.references(&sha256)) {
+                self.blobs.remove(&sha256).await;
+            }
+        }
+    }
+
+    /// Jobs still to run: queued, or started by a node that may have failed since
+    async fn pending(&self) -> Vec<JobSpec> {
+        self.jobs
+            .read()
+            .await
+            .values()
+            .filter(|record| !record.state.is_finished())
+            .map(|record| record.spec.clone())
+            .collect()
+    }
+
+    /// Mark a job as executing here; false if it already is
+    fn claim(&self, id: &str) -> bool {
+        let mut progress = self.progress.lock().unwrap();
+        if progress.contains_key(id) {
+            return false;
+        }
+        progress.insert(id.to_string(), 0);
+        true
+    }
+
+    fn release(&self, id: &str) {
+        self.progress.lock().unwrap().remove(id);
+    }
+
+    fn set_progress(&self, id: &str, percent: u8) {
+        if let Some(p) = self.progress.lock().unwrap().get_mut(id) {
+            *p = percent;
+        }
+    }
+
+    /// Progress of a job executing on this node
+    pub fn progress(&self, id: &str) -> Option<u8> {
+        self.progress.lock().unwrap().get(id).copied()
+    }
+
+    pub async fn store_blob(&self, bytes: &[u8]) -> anyhow::Result<String> {
+        self.blobs.put(bytes).await
+    }
+
+    /// A job file, fetched from the nodes at `peer_urls` (base URLs, tried in
+    /// order) if it is not stored locally yet
+    pub async fn blob(&self, job_id: &str, sha256: &str, peer_urls: &[String]) -> anyhow::Result<Vec<u8>> {
+        let urls: Vec<String> = peer_urls
+            .iter()
+            .map(|base| format!("{}/api/jobs/{}/blobs/{}", base, job_id, sha256))
+            .collect();
+        self.blobs.get(sha256, &urls).await
+    }
+}
+
+/// Run pending jobs while this node is the leader.
+///
+/// Jobs left running by a previous leader are started again from their
+/// replicated inputs.
+pub fn start_job_runner(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(500));
+        loop {
+            interval.tick().await;
+            if state.is_paused() || !state.raft_node.is_leader().await {
+                continue;
+            }
+
+            for spec in state.jobs.pending().await {
+                if !state.jobs.claim(&spec.id) {
+                    continue;
+                }
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let id = spec.id.clone();
+                    run(&state, spec).await;
+                    state.jobs.release(&id);
+                });
+            }
+        }
+    });
+}
+
+/// Delete expired jobs through the log while this node is the leader, and
+/// unreferenced files on every node
+pub fn start_job_sweeper(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            if state.raft_node.is_leader().await && !state.is_paused() {
+                for id in state.jobs.expired(now_secs()).await {
+                    let delete = Command::Job(JobCommand::Delete { id: id.clone() });
+                    if let Err(e) = replication::propose(&state, &delete).await {
+                        tracing::warn!("Failed to expire job {}: {}", id, e);
+                        break;
+                    }
+                }
+            }
+            state.jobs.sweep_orphans().await;
+        }
+    });
+}
+
+async fn run(state: &Arc<AppState>, spec: JobSpec) {
+    let start = Command::Job(JobCommand::Start {
+        id: spec.id.clone(),
+        node: state.node_id.clone(),
+    });
+    if let Err(e) = replication::propose(state, &start).await {
+        tracing::warn!("Failed to start job {}: {}", spec.id, e);
+        return;
+    }
+    tracing::info!("Running {:?} job {}", spec.kind, spec.id);
+
+    let finish = match execute(state, &spec).await {
+        Ok(output) => JobCommand::Complete {
+            id: spec.id.clone(),
+            node: state.node_id.clone(),
+            output,
+            finished_at: now_secs(),
+        },
+        Err(e) => JobCommand::Fail {
+            id: spec.id.clone(),
+            error: e.to_string(),
+            finished_at: now_secs(),
+        },
+    };
+
+    // Unless committed, the job stays pending for this or the next leader
+    if let Err(e) = replication::propose(state, &Command::Job(finish)).await {
+        tracing::warn!("Failed to record outcome of job {}: {}", spec.id, e);
+    }
+}
+
+async fn execute(state: &Arc<AppState>, spec: &JobSpec) -> anyhow::Result<JobOutput> {
+    let peer_urls = state.peer_urls(&spec.origin_node);
+    let input = state.jobs.blob(&spec.id, &spec.input_sha256, &peer_urls).await?;
+    let cover = match &spec.cover_sha256 {
+        Some(sha256) => Some(state.jobs.blob(&spec.id, sha256, &peer_urls).await?),
+        None => None,
+    };
+    state.jobs.set_progress(&spec.id, 10);
+
+    let (bytes, mime, filename, metadata) = match spec.kind {
+        JobKind::Embed => {
+            let cover = match cover {
+                Some(bytes) => image::load_from_memory(&bytes)?,
+                None => state.cover_image.read().await.clone(),
+            };
+            let options = api::embed_options(&state.config.stego)
+                .lsb_per_channel(spec.lsb_per_channel)
+                .compression(spec.compress.into());
+
+            let secret_size = input.len();
+            let jobs = state.jobs.clone();
+            let id = spec.id.clone();
+            let (png, info) = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                let (stego_img, info, stats) = stego::embed_with(&cover, &input, &options)?;
+                jobs.set_progress(&id, 70);
+
+                let mut png = Vec::new();
+                stego_img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+                telemetry::record_embed(input.len(), png.len(), &stats, &options);
+                Ok((png, info))
+            })
+            .await??;
+
+            let metadata = vec![
+                ("x-cover-width".to_string(), info.width.to_string()),
+                ("x-cover-height".to_string(), info.height.to_string()),
+                ("x-lsb-per-channel".to_string(), info.lsb_per_channel.to_string()),
+                ("x-capacity-bytes".to_string(), info.capacity_bytes.to_string()),
+                ("x-secret-size-bytes".to_string(), secret_size.to_string()),
+            ];
+            let filename = format!("stego-{}.png", spec.id);
+            (png, "image/png".to_string(), filename, metadata)
+        }
+        JobKind::Extract => {
+            let options = api::extract_options(&state.config.stego)
+                .lsb_per_channel(spec.lsb_per_channel)
+                .compression(spec.compress.into());
+
+            let recovered = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                if stego::container_carrier(&input).is_some() {
+                    return Ok(stego::extract_container(&input, &options)?);
+                }
+                let stego_img = image::load_from_memory(&input)?;
+                Ok(stego::extract_with(&stego_img, &options)?)
+            })
+            .await??;
+
+            let content_type = stego::sniff(&recovered);
+            let metadata = vec![
+                ("x-recovered-size-bytes".to_string(), recovered.len().to_string()),
+                ("x-recovered-mime".to_string(), content_type.mime.to_string()),
+                ("x-recovered-extension".to_string(), content_type.extension.to_string()),
+            ];
+            let filename = format!("recovered.{}", content_type.extension);
+            (recovered, content_type.mime.to_string(), filename, metadata)
+        }
+    };
+    state.jobs.set_progress(&spec.id, 90);
+
+    let sha256 = state.jobs.store_blob(&bytes).await?;
+    Ok(JobOutput {
+        sha256,
+        size_bytes: bytes.len() as u64,
+        mime,
+        filename,
+        metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn registry(ttl_secs: u64) -> (JobRegistry, TempDir) {
+        let dir = TempDir::new().unwrap();
+        (JobRegistry::new(dir.path(), reqwest::Client::new(), ttl_secs), dir)
+    }
+
+    fn spec(id: &str, input_sha256: &str) -> JobSpec {
+        JobSpec {
+            id: id.to_string(),
+            kind: JobKind::Embed,
+            input_sha256: input_sha256.to_string(),
+            cover_sha256: None,
+            lsb_per_channel: 1,
+            compress: true,
+            origin_node: "n1".to_string(),
+        }
+    }
+
+    fn output(sha256: &str) -> JobOutput {
+        JobOutput {
+            sha256: sha256.to_string(),
+            size_bytes: 3,
+            mime: "image/png".to_string(),
+            filename: "out.png".to_string(),
+            metadata: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_transitions_keep_first_outcome() {
+        let (jobs, _dir) = registry(60);
+        jobs.submit(spec("a", "in")).await;
+        assert_eq!(jobs.get("a").await.unwrap().state.as_str(), "queued");
+        assert_eq!(jobs.pending().await.len(), 1);
+
+        // A repeated submission does not reset the job
+        jobs.transition("a", JobState::Running { node: "n1".to_string() }).await;
+        jobs.submit(spec("a", "in")).await;
+        assert_eq!(jobs.get("a").await.unwrap().state.as_str(), "running");
+
+        let succeeded = JobState::Succeeded {
+            node: "n1".to_string(),
+            output: output("out"),
+        };
+        jobs.finish("a", succeeded, 100).await;
+        jobs.finish("a", JobState::Failed { error: "late".to_string() }, 200).await;
+        jobs.transition("a", JobState::Queued).await;
+
+        let record = jobs.get("a").await.unwrap();
+        assert_eq!(record.state.as_str(), "succeeded");
+        assert_eq!(record.finished_at, Some(100));
+        assert!(record.references("in") && record.references("out"));
+        assert!(jobs.pending().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_after_ttl() {
+        let (jobs, _dir) = registry(60);
+        jobs.submit(spec("done", "a")).await;
+        jobs.submit(spec("queued", "b")).await;
+        jobs.finish("done", JobState::Failed { error: "x".to_string() }, 1000).await;
+
+        assert!(jobs.expired(1059).await.is_empty());
+        assert_eq!(jobs.expired(1060).await, vec!["done".to_string()]);
+
+        let (forever, _dir) = registry(0);
+        forever.submit(spec("done", "a")).await;
+        forever.finish("done", JobState::Failed { error: "x".to_string() }, 0).await;
+        assert!(forever.expired(u64::MAX).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_keeps_files_other_jobs_use() {
+        let (jobs, _dir) = registry(60);
+        let shared = jobs.store_blob(b"shared input").await.unwrap();
+        let result = jobs.store_blob(b"result").await.unwrap();
+        jobs.submit(spec("a", &shared)).await;
+        jobs.submit(spec("b", &shared)).await;
+        let succeeded = JobState::Succeeded {
+            node: "n1".to_string(),
+            output: output(&result),
+        };
+        jobs.finish("a", succeeded, 0).await;
+
+        jobs.delete("a").await;
+        assert!(jobs.get("a").await.is_none());
+        assert!(jobs.blob("b", &result, &[]).await.is_err());
+        assert!(jobs.blob("b", &shared, &[]).await.is_ok());
+
+        jobs.delete("b").await;
+        assert!(jobs.blob("b", &shared, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_orphans_removes_unreferenced_files() {
+        let (jobs, dir) = registry(60);
+        let used = jobs.store_blob(b"used").await.unwrap();
+        let orphan = jobs.store_blob(b"orphan").await.unwrap();
+        jobs.submit(spec("a", &used)).await;
+
+        // Recent files are left alone: their submission may still commit
+        jobs.sweep_orphans().await;
+        assert!(dir.path().join(&orphan).exists());
+
+        let aged = JobRegistry::new(dir.path(), reqwest::Client::new(), 1);
+        aged.submit(spec("a", &used)).await;
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        aged.sweep_orphans().await;
+        assert!(dir.path().join(&used).exists());
+        assert!(!dir.path().join(&orphan).exists());
+    }
+}
@@ -1,8 +1,20 @@
 mod api;
+mod auth;
+mod batch;
+mod blobs;
+mod cover_registry;
+mod covers;
+mod dataset_cache;
+mod idempotency;
+mod jobs;
+mod rate_limit;
+mod replication;
 mod state;
+mod telemetry;
 
 use anyhow::Result;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -10,7 +22,6 @@ use common::ClusterConfig;
 use std::env;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -42,14 +53,52 @@ async fn main() -> Result<()> {
 
     tracing::info!("Starting node {} on {}:{}", node_id, node_config.ip, node_config.http_port);
 
-    // Initialize app state
-    let state = AppState::new(node_id.clone(), config.clone()).await?;
+    // Initialize app state and apply the replicated log (cover metadata)
+    let state = Arc::new(AppState::new(node_id.clone(), config.clone()).await?);
+    replication::start_apply_loop(state.clone());
+    jobs::start_job_runner(state.clone());
+    jobs::start_job_sweeper(state.clone());
+    idempotency::start_sweeper(state.clone());
+
+    // Routes under /api, /admin and /raft need an API key with the matching role
+    let api_keys = Arc::new(auth::ApiKeys::from_config(&config.auth));
+    if !api_keys.is_enabled() {
+        tracing::warn!("No API keys configured; authentication is disabled and /admin routes are refused");
+    }
+
+    // Per-client token buckets and upload quotas (keyed by API key, else source IP)
+    let rate_limiter = Arc::new(rate_limit::RateLimiter::new(config.rate_limit.clone()));
+
+    // Retries with the same Idempotency-Key get the first response again
+    let idempotent = middleware::from_fn_with_state(state.clone(), idempotency::middleware);
 
     // Build router
     let app = Router::new()
         // API routes
-        .route("/api/embed", post(api::embed_handler))
-        .route("/api/extract", post(api::extract_handler))
+        .route(
+            "/api/embed",
+            post(api::embed_handler).layer((api::request_body_limit(&config.stego.limits), idempotent.clone())),
+        )
+        .route(
+            "/api/embed/batch",
+            post(api::embed_batch_handler).layer(api::batch_body_limit(&config.stego.limits)),
+        )
+        .route(
+            "/api/extract",
+            post(api::extract_handler).layer((api::request_body_limit(&config.stego.limits), idempotent)),
+        )
+        .route("/api/probe", post(api::probe_handler))
+        .route("/api/audio/embed", post(api::embed_audio_handler))
+        .route("/api/audio/extract", post(api::extract_audio_handler))
+        .route("/api/covers", get(api::list_covers_handler).post(api::upload_cover_handler))
+        .route("/api/covers/:id", get(api::get_cover_handler).delete(api::delete_cover_handler))
+        .route("/api/covers/:id/blob", get(api::cover_blob_handler))
+        .route("/api/covers/:id/default", post(api::set_default_cover_handler))
+        .route("/api/jobs", post(api::submit_job_handler))
+        .route("/api/jobs/:id", get(api::job_status_handler))
+        .route("/api/jobs/:id/result", get(api::job_result_handler))
+        .route("/api/jobs/:id/blobs/:sha256", get(api::job_blob_handler))
+        .route("/api/dataset", get(api::dataset_list_handler))
         .route("/api/dataset/:index", get(api::dataset_handler))
         .route("/cluster/status", get(api::cluster_status_handler))
         .route("/admin/fail", post(api::admin_fail_handler))
@@ -59,24 +108,23 @@ async fn main() -> Result<()> {
         // Raft RPC routes
         .route("/raft/append-entries", post(api::raft_append_entries_handler))
         .route("/raft/request-vote", post(api::raft_request_vote_handler))
+        .route("/raft/responses/:sha256", get(api::stored_response_body_handler))
         // Static files
         .route("/", get(api::serve_index))
         .nest_service("/static", ServeDir::new("static"))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any)
-        )
+        .layer(middleware::from_fn_with_state(rate_limiter, rate_limit::middleware))
+        .layer(middleware::from_fn_with_state(api_keys, auth::middleware))
+        .layer(auth::cors_layer(&config))
+        .layer(middleware::from_fn(telemetry::middleware))
         .layer(TraceLayer::new_for_http())
-        .with_state(Arc::new(state));
+        .with_state(state);
 
     // Bind and serve
     let addr = SocketAddr::from(([0, 0, 0, 0], node_config.http_port));
     tracing::info!("Server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -0,0 +1,291 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::Response,
+};
+use common::{RateLimitConfig, Role, RouteLimit};
+use control_plane::RetryableError;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::api::AppError;
+use crate::auth::ClientId;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Idle buckets are dropped once this many are tracked, then the least
+/// recently used ones
+const MAX_BUCKETS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Bytes uploaded by one client on one UTC day
+struct Quota {
+    day: u64,
+    bytes: u64,
+}
+
+/// Token buckets per (client, route) and daily upload quotas per client.
+///
+/// State is local to the node, so each node enforces the limits on its own.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<(String, usize), Bucket>>,
+    quotas: Mutex<HashMap<String, Quota>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            quotas: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.routes.is_empty() || self.config.daily_quota_bytes > 0
+    }
+
+    /// Index of the limit with the longest prefix of `path`
+    fn route(&self, path: &str) -> Option<(usize, &RouteLimit)> {
+        self.config
+            .routes
+            .iter()
+            .enumerate()
+            .filter(|(_, limit)| path.starts_with(&limit.prefix))
+            .max_by_key(|(_, limit)| limit.prefix.len())
+    }
+
+    /// Take a token for `client` on `path` at `now`, or return how long until
+    /// one is available
+    fn take(&self, client: &str, path: &str, now: Instant) -> Result<(), Duration> {
+        let Some((index, limit)) = self.route(path) else {
+            return Ok(());
+        };
+        let rate = limit.requests_per_sec.max(f64::MIN_POSITIVE);
+        let burst = f64::from(limit.burst.max(1));
+        let key = (client.to_string(), index);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key) {
+            // A bucket idle long enough to refill completely holds no state
+            buckets.retain(|_, b| now.duration_since(b.updated).as_secs_f64() * rate < burst);
+            if buckets.len() >= MAX_BUCKETS {
+                let oldest = buckets
+                    .iter()
+                    .min_by_key(|(_, b)| b.updated)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    buckets.remove(&oldest);
+                }
+            }
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    /// Count `bytes` against `client`'s daily quota at Unix time `now`, or
+    /// return the time until the quota resets at UTC midnight
+    fn charge(&self, client: &str, bytes: u64, now: u64) -> Result<(), Duration> {
+        let limit = self.config.daily_quota_bytes;
+        if limit == 0 {
+            return Ok(());
+        }
+        let day = now / SECS_PER_DAY;
+
+        let mut quotas = self.quotas.lock().unwrap();
+        quotas.retain(|_, quota| quota.day == day);
+        let quota = quotas
+            .entry(client.to_string())
+            .or_insert(Quota { day, bytes: 0 });
+
+        if quota.bytes.saturating_add(bytes) > limit {
+            return Err(Duration::from_secs((day + 1) * SECS_PER_DAY - now));
+        }
+        quota.bytes += bytes;
+        Ok(())
+    }
+}
+
+/// Reject requests over their client's route rate or daily upload quota
+/// (`/api` POST bodies) with 429
+pub async fn middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let path = req.uri().path();
+    let client_id = req.extensions().get::<ClientId>();
+    // Peer traffic keeps the cluster alive and is never throttled
+    let is_peer = client_id.is_some_and(|client| client.roles.contains(&Role::Peer));
+    if !limiter.is_enabled() || is_peer {
+        return Ok(next.run(req).await);
+    }
+
+    let client = match client_id {
+        Some(client) => format!("key:{}", client.name),
+        None => match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            None => "unknown".to_string(),
+        },
+    };
+
+    if let Err(wait) = limiter.take(&client, path, Instant::now()) {
+        return Err(AppError::TooManyRequests(RetryableError::after(
+            format!("Rate limit exceeded for {}", path),
+            wait,
+        )));
+    }
+
+    if req.method() == Method::POST && path.starts_with("/api/") {
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        match length {
+            Some(length) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                if let Err(wait) = limiter.charge(&client, length, now) {
+                    return Err(AppError::TooManyRequests(RetryableError::after(
+                        "Daily upload quota exceeded",
+                        wait,
+                    )));
+                }
+            }
+            None if limiter.config.daily_quota_bytes > 0 => {
+                return Err(AppError::BadRequest(
+                    "Content-Length is required while upload quotas are enabled".to_string(),
+                ))
+            }
+            None => {}
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_sec: f64, burst: u32, daily_quota_bytes: u64) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            routes: vec![
+                RouteLimit {
+                    prefix: "/api/".to_string(),
+                    requests_per_sec: 1000.0,
+                    burst: 1000,
+                },
+                RouteLimit {
+                    prefix: "/api/embed".to_string(),
+                    requests_per_sec,
+                    burst,
+                },
+            ],
+            daily_quota_bytes,
+        })
+    }
+
+    #[test]
+    fn test_take_spends_burst_then_refills() {
+        let limiter = limiter(2.0, 3, 0);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.take("key:alice", "/api/embed", start).is_ok());
+        }
+        let wait = limiter.take("key:alice", "/api/embed", start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+
+        // Other clients, other routes and unlimited paths have their own buckets
+        assert!(limiter.take("key:bob", "/api/embed", start).is_ok());
+        assert!(limiter.take("key:alice", "/api/extract", start).is_ok());
+        assert!(limiter.take("key:alice", "/healthz", start).is_ok());
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.take("key:alice", "/api/embed", later).is_ok());
+        assert!(limiter.take("key:alice", "/api/embed", later).is_err());
+    }
+
+    #[test]
+    fn test_take_evicts_least_recently_used_bucket() {
+        let limiter = limiter(0.001, 1, 0);
+        let start = Instant::now();
+        assert!(limiter.take("key:oldest", "/api/embed", start).is_ok());
+        // No bucket refills within the test, so pruning idle ones frees nothing
+        for i in 1..MAX_BUCKETS {
+            let now = start + Duration::from_millis(i as u64);
+            assert!(limiter.take(&format!("ip:{}", i), "/api/embed", now).is_ok());
+        }
+        let now = start + Duration::from_secs(60);
+        assert!(limiter.take("ip:new", "/api/embed", now).is_ok());
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_BUCKETS);
+        assert!(!buckets.contains_key(&("key:oldest".to_string(), 1)));
+        assert!(buckets.contains_key(&("ip:1".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_charge_enforces_daily_quota() {
+        let limiter = limiter(1.0, 1, 100);
+        let noon = 20_000 * SECS_PER_DAY + SECS_PER_DAY / 2;
+
+        assert!(limiter.charge("key:alice", 60, noon).is_ok());
+        assert!(limiter.charge("key:alice", 40, noon).is_ok());
+        let wait = limiter.charge("key:alice", 1, noon).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(SECS_PER_DAY / 2));
+        assert!(limiter.charge("key:bob", 100, noon).is_ok());
+        assert!(limiter.charge("key:bob", 1, noon).is_err());
+
+        // A rejected upload is not counted
+        assert!(limiter.charge("key:carol", 101, noon).is_err());
+        assert!(limiter.charge("key:carol", 100, noon).is_ok());
+    }
+
+    #[test]
+    fn test_charge_resets_at_utc_midnight() {
+        let limiter = limiter(1.0, 1, 100);
+        let midnight = 20_001 * SECS_PER_DAY;
+
+        assert!(limiter.charge("key:alice", 100, midnight - 1).is_ok());
+        assert_eq!(limiter.charge("key:alice", 1, midnight - 1).unwrap_err(), Duration::from_secs(1));
+        assert!(limiter.charge("key:alice", 100, midnight).is_ok());
+        assert!(limiter.charge("key:alice", 1, midnight).is_err());
+        assert_eq!(limiter.quotas.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_disabled_without_routes_or_quota() {
+        assert!(!RateLimiter::new(RateLimitConfig::default()).is_enabled());
+        let limiter = RateLimiter::new(RateLimitConfig {
+            routes: Vec::new(),
+            daily_quota_bytes: 1,
+        });
+        assert!(limiter.is_enabled());
+        assert!(limiter.take("key:alice", "/api/embed", Instant::now()).is_ok());
+    }
+}
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cover_registry::CoverMeta;
+use crate::idempotency::StoredResponse;
+use crate::jobs::{JobOutput, JobSpec, JobState};
+use crate::state::AppState;
+
+/// State changes replicated through the Raft log and applied on every node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Cover(CoverCommand),
+    Job(JobCommand),
+    Idempotency(IdempotencyCommand),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CoverCommand {
+    Put(CoverMeta),
+    Delete { id: String },
+    SetDefault { id: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JobCommand {
+    Submit(JobSpec),
+    Start { id: String, node: String },
+    Complete {
+        id: String,
+        node: String,
+        output: JobOutput,
+        /// Unix time on the leader, the start of the job's retention
+        #[serde(default)]
+        finished_at: u64,
+    },
+    Fail {
+        id: String,
+        error: String,
+        #[serde(default)]
+        finished_at: u64,
+    },
+    /// Drop an expired job and its files
+    Delete { id: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum IdempotencyCommand {
+    Put(StoredResponse),
+}
+
+/// Commit `command` through the leader's log, then apply it locally
+pub async fn propose(state: &AppState, command: &Command) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(command)?;
+    control_plane::propose(&state.raft_node, &state.network, data).await?;
+    apply_committed(state).await
+}
+
+/// Committed entries read and applied at a time, so replaying a long log
+/// after a restart does not load all of it at once
+const APPLY_PAGE_ENTRIES: usize = 256;
+
+/// Apply every committed entry not applied yet, in log order.
+///
+/// Files the entries reference are fetched from peers first, without holding
+/// `last_applied`, so a slow or unreachable peer does not stall other appliers;
+/// applying itself only reads local copies. Files of jobs deleted later in the
+/// log are not fetched at all.
+pub async fn apply_committed(state: &AppState) -> anyhow::Result<()> {
+    let start = *state.last_applied.lock().await;
+    let deleted_jobs = deleted_jobs_since(state, start)?;
+
+    let mut from = start;
+    loop {
+        let entries = committed_page(state, from)?;
+        let Some(&(last_index, _)) = entries.last() else {
+            return Ok(());
+        };
+        from = last_index;
+
+        for command in entries.iter().filter_map(|(_, command)| command.as_ref()) {
+            if job_id(command).is_some_and(|id| deleted_jobs.contains(id)) {
+                continue;
+            }
+            fetch_files(state, command).await;
+        }
+
+        let mut last_applied = state.last_applied.lock().await;
+        for (index, command) in entries {
+            // Another caller may have applied these while files were fetched
+            if index <= *last_applied {
+                continue;
+            }
+            if let Some(command) = command {
+                apply(state, command).await;
+            }
+            *last_applied = index;
+        }
+    }
+}
+
+/// The next page of committed entries after `from`, decoded
+fn committed_page(state: &AppState, from: u64) -> anyhow::Result<Vec<(u64, Option<Command>)>> {
+    Ok(control_plane::committed_since(&state.raft_node, from, APPLY_PAGE_ENTRIES)?
+        .into_iter()
+        .map(|entry| match serde_json::from_slice::<Command>(&entry.data) {
+            Ok(command) => (entry.index, Some(command)),
+            Err(e) => {
+                tracing::warn!("Skipping undecodable log entry {}: {}", entry.index, e);
+                (entry.index, None)
+            }
+        })
+        .collect())
+}
+
+/// Ids of jobs deleted by committed entries after `from`
+fn deleted_jobs_since(state: &AppState, mut from: u64) -> anyhow::Result<HashSet<String>> {
+    let mut deleted = HashSet::new();
+    loop {
+        let entries = control_plane::committed_since(&state.raft_node, from, APPLY_PAGE_ENTRIES)?;
+        let Some(last) = entries.last() else {
+            return Ok(deleted);
+        };
+        from = last.index;
+        for entry in entries {
+            if let Ok(Command::Job(JobCommand::Delete { id })) = serde_json::from_slice(&entry.data) {
+                deleted.insert(id);
+            }
+        }
+    }
+}
+
+/// The job a command's files belong to
+fn job_id(command: &Command) -> Option<&str> {
+    match command {
+        Command::Job(JobCommand::Submit(spec)) => Some(&spec.id),
+        Command::Job(JobCommand::Complete { id, .. }) => Some(id),
+        _ => None,
+    }
+}
+
+/// Copy the files `command` refers to from the nodes holding them, so the
+/// command can be served even if its origin node fails
+async fn fetch_files(state: &AppState, command: &Command) {
+    match command {
+        Command::Cover(CoverCommand::Put(meta)) => {
+            if let Err(e) = state.covers.blob(meta, &state.peer_urls(&meta.origin_node)).await {
+                tracing::warn!("Cover {} applied without its blob: {}", meta.id, e);
+            }
+        }
+        Command::Cover(CoverCommand::SetDefault { id }) => {
+            // A cover put in the same batch was fetched by its `Put`
+            if let Some(meta) = state.covers.get(id).await {
+                if let Err(e) = state.covers.blob(&meta, &state.peer_urls(&meta.origin_node)).await {
+                    tracing::warn!("Default cover {} is not available: {}", id, e);
+                }
+            }
+        }
+        Command::Cover(CoverCommand::Delete { .. }) => {}
+        Command::Job(JobCommand::Submit(spec)) => {
+            let peer_urls = state.peer_urls(&spec.origin_node);
+            let inputs = std::iter::once(&spec.input_sha256).chain(&spec.cover_sha256);
+            for sha256 in inputs {
+                if let Err(e) = state.jobs.blob(&spec.id, sha256, &peer_urls).await {
+                    tracing::warn!("Job {} applied without input {}: {}", spec.id, sha256, e);
+                }
+            }
+        }
+        Command::Job(JobCommand::Complete { id, node, output, .. }) => {
+            if let Err(e) = state.jobs.blob(id, &output.sha256, &state.peer_urls(node)).await {
+                tracing::warn!("Job {} completed without its result: {}", id, e);
+            }
+        }
+        Command::Job(JobCommand::Start { .. } | JobCommand::Fail { .. } | JobCommand::Delete { .. }) => {}
+        Command::Idempotency(IdempotencyCommand::Put(stored)) => {
+            // Replaying an old log: an expired response is never inserted
+            if !state.idempotency.is_live(stored) {
+                return;
+            }
+            let peer_urls = state.peer_urls(&stored.origin_node);
+            if let Err(e) = state.idempotency.body(stored, &peer_urls).await {
+                tracing::warn!("Response for key {} applied without its body: {}", stored.key, e);
+            }
+        }
+    }
+}
+
+async fn apply(state: &AppState, command: Command) {
+    match command {
+        Command::Cover(command) => apply_cover(state, command).await,
+        Command::Job(command) => apply_job(state, command).await,
+        Command::Idempotency(IdempotencyCommand::Put(stored)) => {
+            state.idempotency.insert(stored).await;
+        }
+    }
+}
+
+async fn apply_cover(state: &AppState, command: CoverCommand) {
+    match command {
+        CoverCommand::Put(meta) => state.covers.insert(meta).await,
+        CoverCommand::Delete { id } => {
+            // The handler refuses this too, but a SetDefault may have been
+            // committed after its check; every node skips it alike
+            if state.covers.remove(&id).await.is_err() {
+                tracing::warn!("Cover {} is the default; not deleting it", id);
+            }
+        }
+        CoverCommand::SetDefault { id } => {
+            let Some(meta) = state.covers.get(&id).await else {
+                tracing::warn!("Default cover {} is unknown", id);
+                return;
+            };
+            // Local copy only: `fetch_files` already tried the peers
+            let img = state
+                .covers
+                .blob(&meta, &[])
+                .await
+                .and_then(|bytes| Ok(image::load_from_memory(&bytes)?));
+            match img {
+                Ok(img) => {
+                    *state.cover_image.write().await = img;
+                    state.covers.set_default(&id).await;
+                    tracing::info!("Default cover is now {} ({})", meta.name, id);
+                }
+                Err(e) => tracing::warn!("Failed to load default cover {}: {}", id, e),
+            }
+        }
+    }
+}
+
+async fn apply_job(state: &AppState, command: JobCommand) {
+    match command {
+        JobCommand::Submit(spec) => state.jobs.submit(spec).await,
+        JobCommand::Start { id, node } => {
+            state.jobs.transition(&id, JobState::Running { node }).await;
+        }
+        JobCommand::Complete {
+            id,
+            node,
+            output,
+            finished_at,
+        } => {
+            let succeeded = JobState::Succeeded { node, output };
+            state.jobs.finish(&id, succeeded, finished_at).await;
+        }
+        JobCommand::Fail { id, error, finished_at } => {
+            state.jobs.finish(&id, JobState::Failed { error }, finished_at).await;
+        }
+        JobCommand::Delete { id } => state.jobs.delete(&id).await,
+    }
+}
+
+/// Apply entries committed by the leader (followers learn about them from heartbeats)
+pub fn start_apply_loop(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+            if let Err(e) = apply_committed(&state).await {
+                tracing::warn!("Failed to apply committed entries: {}", e);
+            }
+        }
+    });
+}
@@ -1,27 +1,57 @@
-use common::ClusterConfig;
+use crate::auth;
+use crate::cover_registry::CoverRegistry;
+use crate::covers::CoverLibrary;
+use crate::dataset_cache::DatasetCache;
+use crate::idempotency::IdempotencyStore;
+use crate::jobs::JobRegistry;
+use crate::telemetry;
+use common::{ClusterConfig, StegoConfig};
 use control_plane::{MetricsCollector, RaftNetworkClient, RaftNode, RaftNodeConfig, start_raft_tasks};
 use image::DynamicImage;
+use metrics_exporter_prometheus::PrometheusHandle;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 pub struct AppState {
     pub node_id: String,
     pub config: ClusterConfig,
     pub cover_image: Arc<RwLock<DynamicImage>>,
+    pub cover_library: Arc<CoverLibrary>,
+    /// Encoded `/api/dataset/:index` images
+    pub dataset_cache: DatasetCache,
     pub metrics: Arc<MetricsCollector>,
+    /// Renders the series served at `/metrics`
+    pub prometheus: PrometheusHandle,
     pub raft_node: Arc<RaftNode>,
+    pub network: Arc<RaftNetworkClient>,
+    /// Covers managed through `/api/covers`, replicated via the Raft log
+    pub covers: Arc<CoverRegistry>,
+    /// Jobs submitted through `/api/jobs`, replicated via the Raft log
+    pub jobs: Arc<JobRegistry>,
+    /// Responses replayed for repeated `Idempotency-Key`s
+    pub idempotency: Arc<IdempotencyStore>,
+    /// Index of the last log entry applied to `covers`, `jobs` and `idempotency`
+    pub last_applied: Mutex<u64>,
     pub is_paused: Arc<AtomicBool>,
 }
 
 impl AppState {
     pub async fn new(node_id: String, config: ClusterConfig) -> anyhow::Result<Self> {
         // Load or generate cover image
-        let cover_image = Self::load_or_generate_cover().await?;
+        let cover_image = Self::load_or_generate_cover(&config.stego).await?;
 
-        // Initialize metrics collector
+        // Index the optional cover library for automatic cover selection
+        let cover_library = match &config.stego.cover_library_dir {
+            Some(dir) => CoverLibrary::load(dir)?,
+            None => CoverLibrary::default(),
+        };
+
+        // Initialize metrics collector, and the Prometheus recorder before any
+        // Raft state is recorded
         let metrics = Arc::new(MetricsCollector::new());
+        let prometheus = telemetry::install(&node_id)?;
 
         // Initialize Raft node (simplified - just track state, not full consensus)
         let node_config_data = config.find_node(&node_id)
@@ -60,22 +90,51 @@ impl AppState {
         raft_node.restore_state().await?;
 
         // Initialize network client for Raft communication
-        let network = Arc::new(RaftNetworkClient::new(peers));
+        let mut network = RaftNetworkClient::new(peers);
+        if let Some(peer_key) = &config.auth.peer_key {
+            network = network.with_auth_token(peer_key)?;
+        }
+        let network = Arc::new(network);
 
         // Start Raft background tasks (election monitoring, heartbeat sending)
-        start_raft_tasks(raft_node.clone(), network);
+        start_raft_tasks(raft_node.clone(), network.clone());
+
+        // Managed cover blobs live next to the node's Raft data
+        let peer_client = auth::peer_client(&config.auth)?;
+        let covers = CoverRegistry::new(
+            format!("./data/node-{}/covers", node_numeric_id),
+            peer_client.clone(),
+        );
+        let jobs = JobRegistry::new(
+            format!("./data/node-{}/jobs", node_numeric_id),
+            peer_client.clone(),
+            config.jobs.ttl_secs,
+        );
+        let idempotency = IdempotencyStore::new(
+            format!("./data/node-{}/responses", node_numeric_id),
+            peer_client,
+            &config.idempotency,
+        );
 
         Ok(Self {
             node_id,
             config,
             cover_image: Arc::new(RwLock::new(cover_image)),
+            cover_library: Arc::new(cover_library),
+            dataset_cache: DatasetCache::default(),
             metrics,
+            prometheus,
             raft_node,
+            network,
+            covers: Arc::new(covers),
+            jobs: Arc::new(jobs),
+            idempotency: Arc::new(idempotency),
+            last_applied: Mutex::new(0),
             is_paused: Arc::new(AtomicBool::new(false)),
         })
     }
 
-    async fn load_or_generate_cover() -> anyhow::Result<DynamicImage> {
+    pub async fn load_or_generate_cover(stego_config: &StegoConfig) -> anyhow::Result<DynamicImage> {
         let cover_path = PathBuf::from("assets/cover.png");
 
         if cover_path.exists() {
@@ -83,8 +142,17 @@ impl AppState {
             let img = image::open(&cover_path)?;
             Ok(img)
         } else {
-            tracing::info!("Generating default cover image");
-            let img = stego::generate_cover_image(1920, 1080);
+            tracing::info!(
+                "Generating default cover image ({:?} pattern, seed {})",
+                stego_config.cover_pattern,
+                stego_config.cover_seed
+            );
+            let img = stego::generate_cover(
+                stego_config.cover_pattern,
+                1920,
+                1080,
+                stego_config.cover_seed,
+            );
 
             // Create directory and save
             if let Some(parent) = cover_path.parent() {
@@ -97,6 +165,16 @@ impl AppState {
         }
     }
 
+    /// HTTP base URLs of the other nodes, `first` (a node id) leading if present
+    pub fn peer_urls(&self, first: &str) -> Vec<String> {
+        let mut nodes: Vec<_> = self.config.nodes.iter().filter(|n| n.id != self.node_id).collect();
+        nodes.sort_by_key(|n| n.id != first);
+        nodes
+            .iter()
+            .map(|n| format!("http://{}:{}", n.ip, n.http_port))
+            .collect()
+    }
+
     pub fn is_paused(&self) -> bool {
         self.is_paused.load(Ordering::SeqCst)
     }
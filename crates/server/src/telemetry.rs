@@ -0,0 +1,161 @@
+use axum::{extract::MatchedPath, extract::Request, middleware::Next, response::Response};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::Instant;
+
+/// Request latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Payload size buckets, 1 KiB to 64 MiB in powers of four
+const SIZE_BUCKETS: [f64; 9] = [
+    1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0, 67108864.0,
+];
+
+/// Compressed size over original size; above 1 when compression did not pay off
+const RATIO_BUCKETS: [f64; 10] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0];
+
+/// Install the global Prometheus recorder, labelling every series with `node_id`.
+///
+/// Series recorded before this call (or by a second call's recorder) are dropped.
+pub fn install(node_id: &str) -> anyhow::Result<PrometheusHandle> {
+    let handle = builder(node_id)?.install_recorder()?;
+    describe();
+    Ok(handle)
+}
+
+fn builder(node_id: &str) -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new()
+        .add_global_label("node", node_id)
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), &LATENCY_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Suffix("_bytes".to_string()), &SIZE_BUCKETS)?
+        .set_buckets_for_metric(
+            Matcher::Full("stego_compression_ratio".to_string()),
+            &RATIO_BUCKETS,
+        )
+}
+
+/// Help text for every series, on the current recorder
+fn describe() {
+    metrics::describe_counter!(
+        "http_requests_total",
+        "HTTP requests by route, method and status"
+    );
+    metrics::describe_histogram!(
+        "http_request_duration_seconds",
+        metrics::Unit::Seconds,
+        "HTTP request latency by route, method and status"
+    );
+    metrics::describe_histogram!(
+        "stego_embed_secret_bytes",
+        metrics::Unit::Bytes,
+        "Size of embedded secrets"
+    );
+    metrics::describe_histogram!(
+        "stego_embed_output_bytes",
+        metrics::Unit::Bytes,
+        "Size of produced stego images"
+    );
+    metrics::describe_histogram!(
+        "stego_compression_ratio",
+        "Compressed payload size over secret size for compressed embeds (frame overhead excluded)"
+    );
+    control_plane::describe_raft_metrics();
+}
+
+/// Count every request and time it, labelled by its route pattern (not the
+/// concrete path, so ids do not blow up the series count); static files and
+/// unknown paths share the `other` route
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "other".to_string(), |path| path.as_str().to_string());
+    let method = req.method().to_string();
+
+    let response = next.run(req).await;
+
+    let labels = [
+        ("route", route),
+        ("method", method),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels)
+        .record(start.elapsed().as_secs_f64());
+    response
+}
+
+/// Record the sizes of one embed and, when compression is enabled, how well
+/// the secret compressed (`frame` as returned by the embedder)
+pub fn record_embed(
+    secret_bytes: usize,
+    output_bytes: usize,
+    frame: &stego::FrameStats,
+    options: &stego::EmbedOptions,
+) {
+    metrics::histogram!("stego_embed_secret_bytes").record(secret_bytes as f64);
+    metrics::histogram!("stego_embed_output_bytes").record(output_bytes as f64);
+
+    if options.compression != stego::Compression::None && secret_bytes > 0 {
+        metrics::histogram!("stego_compression_ratio")
+            .record(frame.payload_bytes as f64 / secret_bytes as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_render_labels_requests_by_route_and_status() {
+        let recorder = builder("n1").unwrap().build_recorder();
+        let handle = recorder.handle();
+        let app = Router::new()
+            .route("/api/covers/:id", get(|| async { "cover" }))
+            .layer(axum::middleware::from_fn(middleware));
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        metrics::with_local_recorder(&recorder, || {
+            describe();
+            runtime.block_on(async {
+                for uri in ["/api/covers/a", "/api/covers/b", "/static/missing.js"] {
+                    let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+                    app.clone().oneshot(req).await.unwrap();
+                }
+            });
+            let frame = stego::FrameStats {
+                payload_bytes: 250,
+                frame_bytes: 262,
+            };
+            record_embed(1000, 5000, &frame, &stego::EmbedOptions::new());
+        });
+
+        let rendered = handle.render();
+        let series = |name: &str, labels: &[&str]| -> Option<String> {
+            rendered
+                .lines()
+                .find(|line| line.starts_with(name) && labels.iter().all(|label| line.contains(label)))
+                .map(str::to_string)
+        };
+
+        assert!(rendered.contains("# HELP http_requests_total HTTP requests by route, method and status"));
+        let covers = series(
+            "http_requests_total{",
+            &[r#"node="n1""#, r#"route="/api/covers/:id""#, r#"method="GET""#, r#"status="200""#],
+        )
+        .unwrap();
+        assert!(covers.ends_with(" 2"), "{}", covers);
+        let other = series("http_requests_total{", &[r#"route="other""#, r#"status="404""#]).unwrap();
+        assert!(other.ends_with(" 1"), "{}", other);
+        assert!(series("http_request_duration_seconds_count{", &[r#"route="/api/covers/:id""#]).is_some());
+        assert!(!rendered.contains("/api/covers/a"));
+
+        // 250 compressed bytes of a 1000 byte secret; the 12 header bytes are not counted
+        let ratio = series("stego_compression_ratio_sum{", &[]).unwrap();
+        assert!(ratio.ends_with(" 0.25"), "{}", ratio);
+    }
+}
@@ -0,0 +1,369 @@
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use common::{ClusterConfig, StegoConfig};
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(name = "stego-cli")]
+#[command(about = "Offline LSB steganography tool for Phase-1 images")]
+struct Args {
+    /// Cluster config providing the `stego` defaults (built-in defaults if missing)
+    #[arg(long, default_value = "config/cluster.yaml")]
+    config: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Where `embed` stores the payload
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CarrierArg {
+    /// Pixel LSBs: stealthy, capacity bounded by the cover
+    Pixels,
+    /// Private PNG chunk: any size, visible to chunk listings
+    PngChunk,
+    /// Incremental update of a PDF cover (--cover must be a PDF)
+    PdfUpdate,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Hide a file inside a cover image, writing a PNG
+    Embed {
+        #[arg(long)]
+        secret: PathBuf,
+        /// Cover image (generated from the configured pattern and seed if omitted)
+        #[arg(long)]
+        cover: Option<PathBuf>,
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        lsb: Option<u8>,
+        /// `--compress` alone enables DEFLATE; `--compress false` disables it
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        compress: Option<bool>,
+        /// Overrides `stego.mac_key`
+        #[arg(long)]
+        mac_key: Option<String>,
+        #[arg(long, value_enum, default_value = "pixels")]
+        carrier: CarrierArg,
+    },
+    /// Recover a hidden file (written to stdout if --out is omitted)
+    Extract {
+        #[arg(long)]
+        input: PathBuf,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        lsb: Option<u8>,
+        /// `--compress` alone enables DEFLATE; `--compress false` disables it
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        compress: Option<bool>,
+        /// Overrides `stego.mac_key`
+        #[arg(long)]
+        mac_key: Option<String>,
+    },
+    /// Show how many bytes a cover can hold, and whether a secret fits
+    Capacity {
+        #[arg(long)]
+        cover: Option<PathBuf>,
+        #[arg(long)]
+        secret: Option<PathBuf>,
+        #[arg(long)]
+        lsb: Option<u8>,
+        /// `--compress` alone enables DEFLATE; `--compress false` disables it
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        compress: Option<bool>,
+    },
+    /// Look for a PNG chunk or PDF update payload, then for one in the pixels
+    /// under every bit depth and channel order
+    Probe { image: PathBuf },
+    /// Print texture and LSB-plane statistics, plus a probe summary
+    Analyze { image: PathBuf },
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = load_stego_config(&args.config)?;
+
+    match args.command {
+        Command::Embed {
+            secret,
+            cover,
+            out,
+            compress,
+            mac_key,
+            carrier: carrier @ (CarrierArg::PngChunk | CarrierArg::PdfUpdate),
+            lsb: _,
+        } => {
+            let secret_bytes = read_file(&secret)?;
+            let mut options = stego::EmbedOptions::new()
+                .compression(compress.unwrap_or(config.compress).into());
+            if let Some(key) = mac_key.as_ref().or(config.mac_key.as_ref()) {
+                options = options.mac_key(key.as_bytes());
+            }
+
+            let (stego_bytes, carrier_name) = match carrier {
+                CarrierArg::PdfUpdate => {
+                    let cover = cover.context("--carrier pdf-update needs a PDF --cover")?;
+                    let (pdf, _) = stego::embed_pdf_update(&read_file(&cover)?, &secret_bytes, &options)?;
+                    (pdf, "PDF update")
+                }
+                _ => {
+                    let cover_png = load_cover_png(cover.as_deref(), &config)?;
+                    let (png, _) = stego::embed_png_chunk(&cover_png, &secret_bytes, &options)?;
+                    (png, "PNG chunk")
+                }
+            };
+
+            std::fs::write(&out, &stego_bytes)
+                .with_context(|| format!("Failed to write {:?}", out))?;
+            println!(
+                "Embedded {} bytes into a {} carrier ({} bytes) -> {:?}",
+                secret_bytes.len(),
+                carrier_name,
+                stego_bytes.len(),
+                out
+            );
+        }
+        Command::Embed {
+            secret,
+            cover,
+            out,
+            lsb,
+            compress,
+            mac_key,
+            carrier: CarrierArg::Pixels,
+        } => {
+            let secret_file = File::open(&secret)
+                .with_context(|| format!("Failed to read {:?}", secret))?;
+            let secret_len = secret_file.metadata()?.len();
+            let cover_img = load_cover(cover.as_deref(), &config)?;
+            let lsb = lsb.unwrap_or(config.lsb_per_channel);
+            let compress = compress.unwrap_or(config.compress);
+            let mac_key = mac_key.or(config.mac_key);
+
+            // Streamed so large secrets are never buffered whole
+            let (stego_img, cover_info) = stego::embed_stream(
+                &cover_img,
+                BufReader::new(secret_file),
+                lsb,
+                compress,
+                mac_key.as_deref().map(str::as_bytes),
+            )?;
+
+            stego_img
+                .save_with_format(&out, ImageFormat::Png)
+                .with_context(|| format!("Failed to write {:?}", out))?;
+
+            println!(
+                "Embedded {} bytes into {}x{} cover (capacity {} bytes) -> {:?}",
+                secret_len,
+                cover_info.width,
+                cover_info.height,
+                cover_info.capacity_bytes,
+                out
+            );
+        }
+        Command::Extract {
+            input,
+            out,
+            lsb,
+            compress,
+            mac_key,
+        } => {
+            let input_bytes = read_file(&input)?;
+            let lsb = lsb.unwrap_or(config.lsb_per_channel);
+            let compress = compress.unwrap_or(config.compress);
+            let mac_key = mac_key.or(config.mac_key);
+            let mac_key = mac_key.as_deref().map(str::as_bytes);
+
+            // PNG chunk and PDF update payloads are small enough to buffer
+            if let Some(carrier) = stego::container_carrier(&input_bytes) {
+                let mut options = stego::ExtractOptions::new().compression(compress.into());
+                if let Some(key) = mac_key {
+                    options = options.mac_key(key);
+                }
+                let recovered = stego::extract_container(&input_bytes, &options)?;
+                match out {
+                    Some(out) => {
+                        std::fs::write(&out, &recovered)
+                            .with_context(|| format!("Failed to write {:?}", out))?;
+                        println!(
+                            "Recovered {} bytes from {} -> {:?}",
+                            recovered.len(),
+                            carrier.as_str(),
+                            out
+                        );
+                    }
+                    None => std::io::stdout().lock().write_all(&recovered)?,
+                }
+                return Ok(());
+            }
+
+            let stego_img = image::load_from_memory(&input_bytes)
+                .with_context(|| format!("Failed to open image {:?}", input))?;
+
+            // Verified first, then streamed out without buffering the secret
+            match out {
+                Some(out) => {
+                    let file = File::create(&out)
+                        .with_context(|| format!("Failed to write {:?}", out))?;
+                    let written = stego::extract_stream(
+                        &stego_img,
+                        lsb,
+                        compress,
+                        mac_key,
+                        BufWriter::new(file),
+                    )?;
+                    println!("Recovered {} bytes -> {:?}", written, out);
+                }
+                None => {
+                    let stdout = std::io::stdout().lock();
+                    stego::extract_stream(&stego_img, lsb, compress, mac_key, stdout)?;
+                }
+            }
+        }
+        Command::Capacity {
+            cover,
+            secret,
+            lsb,
+            compress,
+        } => {
+            let cover_img = load_cover(cover.as_deref(), &config)?;
+            let lsb = lsb.unwrap_or(config.lsb_per_channel);
+            let compress = compress.unwrap_or(config.compress);
+
+            let (width, height) = cover_img.dimensions();
+            let capacity = stego::capacity_bytes(width, height, lsb);
+            println!("Cover: {}x{}, {} LSB per channel", width, height, lsb);
+            println!("Capacity: {} bytes", capacity);
+
+            if let Some(secret) = secret {
+                let secret_bytes = read_file(&secret)?;
+                let required = stego::required_capacity(&secret_bytes, compress)?;
+                println!(
+                    "Secret: {} bytes, {} bytes embedded (compress={})",
+                    secret_bytes.len(),
+                    required,
+                    compress
+                );
+                println!("Fits: {}", if required <= capacity { "yes" } else { "no" });
+            }
+        }
+        Command::Probe { image } => {
+            let report = stego::probe_file(&read_file(&image)?)
+                .with_context(|| format!("Failed to open image {:?}", image))?;
+            print_probe(&report);
+        }
+        Command::Analyze { image } => {
+            let img = open_image(&image)?;
+            let (width, height) = img.dimensions();
+
+            println!("Image: {}x{}", width, height);
+            println!("Texture score: {:.4}", stego::texture_score(&img));
+
+            // An untouched natural image rarely has a perfectly balanced LSB plane
+            let rgb_img = img.to_rgb8();
+            let mut ones = [0u64; 3];
+            for pixel in rgb_img.pixels() {
+                for (c, count) in ones.iter_mut().enumerate() {
+                    *count += (pixel[c] & 1) as u64;
+                }
+            }
+            let total = (width as u64 * height as u64).max(1) as f64;
+            println!(
+                "LSB ones ratio: R {:.4}, G {:.4}, B {:.4}",
+                ones[0] as f64 / total,
+                ones[1] as f64 / total,
+                ones[2] as f64 / total
+            );
+
+            print_probe(&stego::probe(&img));
+        }
+    }
+
+    Ok(())
+}
+
+/// `stego` section of the cluster config, or built-in defaults if the file is absent
+fn load_stego_config(path: &Path) -> Result<StegoConfig> {
+    if !path.exists() {
+        eprintln!("Config {:?} not found, using built-in defaults", path);
+        return Ok(StegoConfig::default());
+    }
+
+    let config = ClusterConfig::from_file(path)
+        .with_context(|| format!("Failed to load config {:?}", path))?;
+    Ok(config.stego)
+}
+
+fn load_cover(path: Option<&Path>, config: &StegoConfig) -> Result<DynamicImage> {
+    match path {
+        Some(path) => open_image(path),
+        None => Ok(stego::generate_cover(
+            config.cover_pattern,
+            1920,
+            1080,
+            config.cover_seed,
+        )),
+    }
+}
+
+/// Cover as PNG bytes: a PNG file is kept byte for byte, anything else is re-encoded
+fn load_cover_png(path: Option<&Path>, config: &StegoConfig) -> Result<Vec<u8>> {
+    if let Some(path) = path {
+        let bytes = read_file(path)?;
+        if image::guess_format(&bytes).ok() == Some(ImageFormat::Png) {
+            return Ok(bytes);
+        }
+    }
+
+    let mut png = Vec::new();
+    load_cover(path, config)?.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+fn open_image(path: &Path) -> Result<DynamicImage> {
+    image::open(path).with_context(|| format!("Failed to open image {:?}", path))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))
+}
+
+fn print_probe(report: &stego::ProbeReport) {
+    println!(
+        "Payload present: {} ({} parameter combinations tried)",
+        if report.payload_present { "yes" } else { "no" },
+        report.combinations_tried
+    );
+
+    match &report.candidate {
+        Some(c) => {
+            let header = if c.authenticated { "authenticated" } else { "plain" };
+            match c.carrier {
+                stego::Carrier::Pixels => println!(
+                    "  Parameters: {} LSB, {} order, {} header",
+                    c.lsb_per_channel,
+                    c.channel_order.as_str(),
+                    header
+                ),
+                carrier => println!("  Carrier: {}, {} header", carrier.as_str(), header),
+            }
+            if c.masked {
+                println!("  Region/channel mask: yes");
+            }
+            println!("  Declared size: {} bytes", c.declared_size_bytes);
+            println!(
+                "  Fits carrier: {}, CRC valid: {}",
+                c.fits_carrier, c.crc_valid
+            );
+            println!("  Compressed: {}, Encrypted: {}", c.compressed, c.encrypted);
+        }
+        None => println!("  No frame header found"),
+    }
+}
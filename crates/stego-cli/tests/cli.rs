@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod cli_tests {
+    use assert_cmd::Command;
+    use image::{ImageBuffer, Rgb};
+    use predicates::prelude::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// `stego-cli` with built-in defaults (the config path does not exist)
+    fn stego_cli(dir: &Path) -> Command {
+        let mut cmd = Command::cargo_bin("stego-cli").unwrap();
+        cmd.arg("--config").arg(dir.join("missing.yaml"));
+        cmd
+    }
+
+    fn write_fixtures(dir: &Path) {
+        let cover = ImageBuffer::from_fn(64, 64, |x, y| {
+            Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8])
+        });
+        cover.save(dir.join("cover.png")).unwrap();
+        std::fs::write(dir.join("secret.txt"), b"meet at the usual place").unwrap();
+    }
+
+    fn embed(dir: &Path, compress: &[&str]) {
+        stego_cli(dir)
+            .args(["embed", "--secret"])
+            .arg(dir.join("secret.txt"))
+            .arg("--cover")
+            .arg(dir.join("cover.png"))
+            .arg("--out")
+            .arg(dir.join("stego.png"))
+            .args(compress)
+            .assert()
+            .success();
+    }
+
+    fn extract(dir: &Path, compress: &[&str]) -> assert_cmd::assert::Assert {
+        stego_cli(dir)
+            .args(["extract", "--input"])
+            .arg(dir.join("stego.png"))
+            .args(compress)
+            .assert()
+    }
+
+    #[test]
+    fn test_bare_compress_flag_round_trip() {
+        let dir = TempDir::new().unwrap();
+        write_fixtures(dir.path());
+
+        embed(dir.path(), &["--compress"]);
+        extract(dir.path(), &["--compress"])
+            .success()
+            .stdout("meet at the usual place");
+    }
+
+    #[test]
+    fn test_compress_false_round_trip() {
+        let dir = TempDir::new().unwrap();
+        write_fixtures(dir.path());
+
+        embed(dir.path(), &["--compress", "false"]);
+        extract(dir.path(), &["--compress=false"])
+            .success()
+            .stdout("meet at the usual place");
+        extract(dir.path(), &["--compress"]).failure();
+    }
+
+    #[test]
+    fn test_compress_rejects_non_bool() {
+        let dir = TempDir::new().unwrap();
+        stego_cli(dir.path())
+            .args(["capacity", "--compress", "maybe"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("--compress"));
+    }
+}
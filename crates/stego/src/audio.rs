@@ -0,0 +1,332 @@
+use crate::error::{Result, StegoError};
+use crate::frame::{build_frame, read_frame};
+use crate::lsb::bits_to_bytes;
+
+#[derive(Debug, Clone)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub num_samples: u64,
+    pub lsb_per_sample: u8,
+    pub capacity_bytes: u64,
+}
+
+/// Parsed layout of a PCM WAV file
+struct WavLayout {
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+    data_offset: usize,
+    data_len: usize,
+}
+
+impl WavLayout {
+    fn bytes_per_sample(&self) -> usize {
+        self.bits_per_sample as usize / 8
+    }
+
+    fn num_samples(&self) -> usize {
+        self.data_len / self.bytes_per_sample()
+    }
+
+    /// Byte offset of the least significant byte of a sample (WAV is little-endian)
+    fn sample_offset(&self, sample_index: usize) -> usize {
+        self.data_offset + sample_index * self.bytes_per_sample()
+    }
+}
+
+/// Embed secret bytes into a PCM WAV file (8-bit or 16-bit) using LSB steganography
+pub fn embed_audio(
+    cover_wav: &[u8],
+    secret_bytes: &[u8],
+    lsb_per_sample: u8,
+    compress: bool,
+) -> Result<(Vec<u8>, AudioInfo)> {
+    check_lsb_per_sample(lsb_per_sample)?;
+    let layout = parse_wav(cover_wav)?;
+
+    let bits_available = layout.num_samples() as u64 * lsb_per_sample as u64;
+    let capacity_bytes = bits_available / 8;
+
+    let audio_info = AudioInfo {
+        sample_rate: layout.sample_rate,
+        channels: layout.channels,
+        bits_per_sample: layout.bits_per_sample,
+        num_samples: layout.num_samples() as u64,
+        lsb_per_sample,
+        capacity_bytes,
+    };
+
+    let frame = build_frame(secret_bytes, compress, None)?;
+
+    let required_bits = frame.len() as u64 * 8;
+    if required_bits > bits_available {
+        return Err(StegoError::CapacityExceeded {
+            needed: required_bits.div_ceil(8),
+            available: capacity_bytes,
+        });
+    }
+
+    // Only the sample bytes change; every other chunk is copied verbatim
+    let mut stego = cover_wav.to_vec();
+    let bits = lsb_per_sample as usize;
+    let total_bits = frame.len() * 8;
+
+    for (sample_index, chunk_start) in (0..total_bits).step_by(bits).enumerate() {
+        let offset = layout.sample_offset(sample_index);
+        let mut value = stego[offset];
+
+        for bit_pos in 0..bits {
+            let bit_index = chunk_start + bit_pos;
+            if bit_index >= total_bits {
+                break;
+            }
+
+            let bit = (frame[bit_index / 8] >> (7 - (bit_index % 8))) & 1; // MSB first
+            let shift = bits - 1 - bit_pos;
+            value = (value & !(1 << shift)) | (bit << shift);
+        }
+
+        stego[offset] = value;
+    }
+
+    Ok((stego, audio_info))
+}
+
+/// Extract secret bytes from a stego WAV file
+pub fn extract_audio(stego_wav: &[u8], lsb_per_sample: u8, compress: bool) -> Result<Vec<u8>> {
+    check_lsb_per_sample(lsb_per_sample)?;
+    let layout = parse_wav(stego_wav)?;
+    let lsb = lsb_per_sample as usize;
+
+    let read = |offset: usize, len: usize| {
+        let bits: Vec<u8> = (offset * 8..(offset + len) * 8)
+            .map(|bit_index| {
+                let sample = layout.sample_offset(bit_index / lsb);
+                let shift = lsb - 1 - (bit_index % lsb);
+                (stego_wav[sample] >> shift) & 1
+            })
+            .collect();
+        bits_to_bytes(&bits)
+    };
+
+    let available = layout.num_samples() * lsb / 8;
+    read_frame(read, available, compress, None)
+}
+
+fn check_lsb_per_sample(lsb_per_sample: u8) -> Result<()> {
+    if !(1..=8).contains(&lsb_per_sample) {
+        return Err(StegoError::UnsupportedOptions(format!(
+            "lsb_per_sample must be between 1 and 8, got {}",
+            lsb_per_sample
+        )));
+    }
+    Ok(())
+}
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Tail shared by every `KSDATAFORMAT_SUBTYPE_*` GUID; the first two bytes
+/// carry the plain format tag
+const KSDATAFORMAT_SUBTYPE_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Format tag named by the sub-format GUID of a WAVE_FORMAT_EXTENSIBLE fmt chunk
+fn extensible_sub_format(fmt: &[u8]) -> Result<u16> {
+    if fmt.len() < 40 {
+        return Err(StegoError::InvalidCover(
+            "WAVE_FORMAT_EXTENSIBLE fmt chunk too short".to_string(),
+        ));
+    }
+    let guid = &fmt[24..40];
+    if guid[2..] != KSDATAFORMAT_SUBTYPE_TAIL {
+        return Err(StegoError::InvalidCover(
+            "Unknown WAVE_FORMAT_EXTENSIBLE sub-format GUID".to_string(),
+        ));
+    }
+    Ok(u16::from_le_bytes([guid[0], guid[1]]))
+}
+
+fn parse_wav(data: &[u8]) -> Result<WavLayout> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(StegoError::InvalidCover("Not a RIFF/WAVE file".to_string()));
+    }
+
+    let mut format = None;
+    let mut data_chunk = None;
+    let mut pos = 12;
+
+    while pos + 8 <= data.len() {
+        let chunk_id = &data[pos..pos + 4];
+        let chunk_len = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let body = pos + 8;
+        let body_len = chunk_len.min(data.len() - body);
+
+        match chunk_id {
+            b"fmt " => {
+                if body_len < 16 {
+                    return Err(StegoError::InvalidCover("WAV fmt chunk too short".to_string()));
+                }
+                let fmt = &data[body..body + body_len];
+                let audio_format = match u16::from_le_bytes([fmt[0], fmt[1]]) {
+                    WAVE_FORMAT_EXTENSIBLE => extensible_sub_format(fmt)?,
+                    tag => tag,
+                };
+                let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                let bits_per_sample = u16::from_le_bytes([fmt[14], fmt[15]]);
+                format = Some((audio_format, channels, sample_rate, bits_per_sample));
+            }
+            b"data" => data_chunk = Some((body, body_len)),
+            _ => {}
+        }
+
+        // Chunks are padded to an even length
+        pos = body + chunk_len + (chunk_len & 1);
+    }
+
+    let (audio_format, channels, sample_rate, bits_per_sample) =
+        format.ok_or_else(|| StegoError::InvalidCover("WAV has no fmt chunk".to_string()))?;
+    let (data_offset, data_len) =
+        data_chunk.ok_or_else(|| StegoError::InvalidCover("WAV has no data chunk".to_string()))?;
+
+    if audio_format != WAVE_FORMAT_PCM {
+        return Err(StegoError::InvalidCover(format!(
+            "Unsupported WAV format tag {:#x} (only PCM is supported)",
+            audio_format
+        )));
+    }
+
+    if bits_per_sample != 8 && bits_per_sample != 16 {
+        return Err(StegoError::InvalidCover(format!(
+            "Unsupported WAV bit depth {} (only 8-bit and 16-bit PCM are supported)",
+            bits_per_sample
+        )));
+    }
+
+    Ok(WavLayout {
+        sample_rate,
+        channels,
+        bits_per_sample,
+        data_offset,
+        data_len,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_wav(bits_per_sample: u16, num_samples: usize) -> Vec<u8> {
+        let bytes_per_sample = bits_per_sample as usize / 8;
+        let data_len = num_samples * bytes_per_sample;
+        let sample_rate = 8000u32;
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * bytes_per_sample as u32).to_le_bytes());
+        wav.extend_from_slice(&(bytes_per_sample as u16).to_le_bytes());
+        wav.extend_from_slice(&bits_per_sample.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data_len as u32).to_le_bytes());
+
+        for i in 0..num_samples {
+            let phase = (i as f32 * 0.05).sin();
+            if bits_per_sample == 8 {
+                wav.push((128.0 + phase * 100.0) as u8);
+            } else {
+                wav.extend_from_slice(&((phase * 20000.0) as i16).to_le_bytes());
+            }
+        }
+        wav
+    }
+
+    #[test]
+    fn test_round_trip_16bit() {
+        let cover = create_test_wav(16, 4000);
+        let secret = b"Hello, audio steganography!";
+
+        let (stego, info) = embed_audio(&cover, secret, 1, false).unwrap();
+        assert_eq!(info.bits_per_sample, 16);
+        assert_eq!(stego.len(), cover.len());
+
+        let recovered = extract_audio(&stego, 1, false).unwrap();
+        assert_eq!(secret.as_slice(), recovered.as_slice());
+    }
+
+    #[test]
+    fn test_round_trip_8bit_compressed() {
+        let cover = create_test_wav(8, 8000);
+        let secret = b"Compressed audio secret!".repeat(10);
+
+        let (stego, _info) = embed_audio(&cover, &secret, 2, true).unwrap();
+        let recovered = extract_audio(&stego, 2, true).unwrap();
+
+        assert_eq!(secret, recovered);
+    }
+
+    #[test]
+    fn test_audio_capacity_exceeded() {
+        let cover = create_test_wav(16, 100);
+        let secret = vec![0u8; 1000];
+
+        let result = embed_audio(&cover, &secret, 1, false);
+        assert!(matches!(result, Err(StegoError::CapacityExceeded { .. })));
+    }
+
+    #[test]
+    fn test_rejects_non_wav() {
+        let result = embed_audio(b"definitely not a wav file", b"secret", 1, false);
+        assert!(matches!(result, Err(StegoError::InvalidCover(_))));
+    }
+
+    /// Rewrites a 16-bit test WAV's fmt chunk as WAVE_FORMAT_EXTENSIBLE
+    fn create_extensible_wav(sub_format: u16, num_samples: usize) -> Vec<u8> {
+        let pcm = create_test_wav(16, num_samples);
+        let mut fmt = pcm[20..36].to_vec();
+        fmt[0..2].copy_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        fmt.extend_from_slice(&22u16.to_le_bytes()); // cbSize
+        fmt.extend_from_slice(&16u16.to_le_bytes()); // valid bits per sample
+        fmt.extend_from_slice(&4u32.to_le_bytes()); // channel mask: front centre
+        fmt.extend_from_slice(&sub_format.to_le_bytes());
+        fmt.extend_from_slice(&KSDATAFORMAT_SUBTYPE_TAIL);
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&((pcm.len() - 8 + 24) as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&fmt);
+        wav.extend_from_slice(&pcm[36..]);
+        wav
+    }
+
+    #[test]
+    fn test_extensible_sub_format_must_be_pcm() {
+        let pcm = create_extensible_wav(WAVE_FORMAT_PCM, 4000);
+        let (stego, _info) = embed_audio(&pcm, b"extensible", 1, false).unwrap();
+        assert_eq!(extract_audio(&stego, 1, false).unwrap(), b"extensible");
+
+        // 3 = IEEE float: flipping mantissa bits is not what the caller asked for
+        let float = create_extensible_wav(3, 4000);
+        let result = embed_audio(&float, b"extensible", 1, false);
+        assert!(matches!(result, Err(StegoError::InvalidCover(_))));
+
+        let mut unknown = pcm.clone();
+        let guid_end = 20 + 40;
+        unknown[guid_end - 1] ^= 0xFF;
+        let result = embed_audio(&unknown, b"extensible", 1, false);
+        assert!(matches!(result, Err(StegoError::InvalidCover(_))));
+    }
+}
@@ -0,0 +1,612 @@
+use crate::error::{Result, StegoError};
+use crate::frame::{compute_crc, FrameSource};
+use crate::options::{EmbedOptions, ExtractOptions, FrameStats};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Private ancillary, safe-to-copy PNG chunk type holding the frame
+/// (lowercase first and second letters: ancillary and private)
+const PNG_CHUNK_TYPE: &[u8; 4] = b"stGp";
+
+/// Largest frame a container carrier holds: the largest PNG chunk body
+/// allowed by the spec (2^31 - 1 bytes), applied to PDF streams as well
+pub const CONTAINER_CAPACITY_BYTES: u64 = i32::MAX as u64;
+
+/// PDF object type marking the stream appended by `embed_pdf_update`
+const PDF_STREAM_TYPE: &[u8] = b"/Type /StegoPayload";
+
+/// Where a payload frame is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Carrier {
+    /// LSBs of the image pixels (`embed`, `embed_masked`, ...)
+    Pixels,
+    /// Private ancillary chunk of a PNG file
+    PngChunk,
+    /// Unreferenced stream object in a PDF incremental update
+    PdfUpdate,
+}
+
+impl Carrier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Carrier::Pixels => "pixels",
+            Carrier::PngChunk => "png_chunk",
+            Carrier::PdfUpdate => "pdf_update",
+        }
+    }
+}
+
+/// Store secret bytes in a private ancillary chunk before `IEND`, replacing
+/// any chunk a previous embedding left. Pixels and all other chunks are
+/// copied verbatim, so decoders render the image unchanged.
+///
+/// Bit depth, traversal key and mask do not apply; the payload is limited
+/// only by the PNG chunk size, at the cost of being visible to any chunk
+/// listing.
+pub fn embed_png_chunk(
+    cover_png: &[u8],
+    secret_bytes: &[u8],
+    options: &EmbedOptions,
+) -> Result<(Vec<u8>, FrameStats)> {
+    let chunks = parse_png(cover_png)?;
+    let (frame, stats) = encode_container_frame(secret_bytes, options)?;
+
+    let mut stego = Vec::with_capacity(cover_png.len() + frame.len() + 12);
+    stego.extend_from_slice(PNG_SIGNATURE);
+    for chunk in chunks.iter().filter(|c| &c.chunk_type != PNG_CHUNK_TYPE) {
+        if &chunk.chunk_type == b"IEND" {
+            write_png_chunk(&mut stego, PNG_CHUNK_TYPE, &frame);
+        }
+        stego.extend_from_slice(&cover_png[chunk.start..chunk.end]);
+    }
+
+    Ok((stego, stats))
+}
+
+/// Encode the frame for a container carrier, bounded by `CONTAINER_CAPACITY_BYTES`
+fn encode_container_frame(secret_bytes: &[u8], options: &EmbedOptions) -> Result<(Vec<u8>, FrameStats)> {
+    let (frame, stats) = options.encode_frame(secret_bytes)?;
+    if stats.frame_bytes > CONTAINER_CAPACITY_BYTES {
+        return Err(StegoError::CapacityExceeded {
+            needed: stats.frame_bytes,
+            available: CONTAINER_CAPACITY_BYTES,
+        });
+    }
+    Ok((frame, stats))
+}
+
+/// Extract secret bytes stored by `embed_png_chunk`
+pub fn extract_png_chunk(stego_png: &[u8], options: &ExtractOptions) -> Result<Vec<u8>> {
+    let frame = png_frame(stego_png)?
+        .ok_or_else(|| StegoError::ExtractionFailed("PNG has no payload chunk".to_string()))?;
+    read_container_frame(frame, options)
+}
+
+/// Append secret bytes to a PDF as an incremental update: one stream object
+/// that nothing references, plus the xref section and trailer chaining back
+/// to the previous revision. Viewers show the document unchanged.
+///
+/// Bit depth, traversal key and mask do not apply.
+pub fn embed_pdf_update(
+    cover_pdf: &[u8],
+    secret_bytes: &[u8],
+    options: &EmbedOptions,
+) -> Result<(Vec<u8>, FrameStats)> {
+    let trailer = parse_pdf_trailer(cover_pdf)?;
+    let (frame, stats) = encode_container_frame(secret_bytes, options)?;
+
+    let mut stego = cover_pdf.to_vec();
+    if !stego.ends_with(b"\n") {
+        stego.push(b'\n');
+    }
+
+    let object_id = trailer.size;
+    let object_offset = stego.len();
+    stego.extend_from_slice(
+        format!(
+            "{} 0 obj\n<< {} /Length {} >>\nstream\n",
+            object_id,
+            String::from_utf8_lossy(PDF_STREAM_TYPE),
+            frame.len()
+        )
+        .as_bytes(),
+    );
+    stego.extend_from_slice(&frame);
+    stego.extend_from_slice(b"\nendstream\nendobj\n");
+
+    // Each xref entry is exactly 20 bytes, including its two-byte line ending
+    let xref_offset = stego.len();
+    let carried: String = trailer
+        .carried
+        .iter()
+        .map(|(key, value)| format!(" {} {}", key, value))
+        .collect();
+    stego.extend_from_slice(
+        format!(
+            "xref\n{} 1\n{:010} 00000 n\r\ntrailer\n<< /Size {} /Root {}{} /Prev {} >>\nstartxref\n{}\n%%EOF\n",
+            object_id,
+            object_offset,
+            object_id + 1,
+            trailer.root,
+            carried,
+            trailer.startxref,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+
+    Ok((stego, stats))
+}
+
+/// Extract secret bytes stored by `embed_pdf_update` (the latest, if several)
+pub fn extract_pdf_update(stego_pdf: &[u8], options: &ExtractOptions) -> Result<Vec<u8>> {
+    let frame = pdf_frame(stego_pdf)?
+        .ok_or_else(|| StegoError::ExtractionFailed("PDF has no payload stream".to_string()))?;
+    read_container_frame(frame, options)
+}
+
+/// Container carrier holding a payload in `file_bytes`, if any
+pub fn container_carrier(file_bytes: &[u8]) -> Option<Carrier> {
+    container_frame(file_bytes).map(|(carrier, _)| carrier)
+}
+
+/// Extract from whichever container carrier `file_bytes` uses
+pub fn extract_container(file_bytes: &[u8], options: &ExtractOptions) -> Result<Vec<u8>> {
+    let (_, frame) = container_frame(file_bytes).ok_or_else(|| {
+        StegoError::ExtractionFailed("No PNG chunk or PDF update payload found".to_string())
+    })?;
+    read_container_frame(frame, options)
+}
+
+/// Carrier and raw frame bytes of a container payload
+pub(crate) fn container_frame(file_bytes: &[u8]) -> Option<(Carrier, &[u8])> {
+    if let Ok(Some(frame)) = png_frame(file_bytes) {
+        return Some((Carrier::PngChunk, frame));
+    }
+    if let Ok(Some(frame)) = pdf_frame(file_bytes) {
+        return Some((Carrier::PdfUpdate, frame));
+    }
+    None
+}
+
+fn read_container_frame(frame: &[u8], options: &ExtractOptions) -> Result<Vec<u8>> {
+    options.read_payload(|offset, len| frame.read_at(offset, len), frame.capacity())
+}
+
+/// Byte range of one PNG chunk (length, type, body and CRC)
+struct PngChunk {
+    chunk_type: [u8; 4],
+    start: usize,
+    end: usize,
+}
+
+/// Split a PNG into its chunks, up to and including `IEND`
+fn parse_png(data: &[u8]) -> Result<Vec<PngChunk>> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(StegoError::InvalidCover("Not a PNG file".to_string()));
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = PNG_SIGNATURE.len();
+
+    while pos + 12 <= data.len() {
+        let len =
+            u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 12 + len;
+        if end > data.len() {
+            break;
+        }
+
+        let chunk_type = [data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]];
+        chunks.push(PngChunk {
+            chunk_type,
+            start: pos,
+            end,
+        });
+        if &chunk_type == b"IEND" {
+            return Ok(chunks);
+        }
+        pos = end;
+    }
+
+    Err(StegoError::InvalidCover(
+        "PNG is truncated (no IEND chunk)".to_string(),
+    ))
+}
+
+fn write_png_chunk(out: &mut Vec<u8>, chunk_type: &[u8; 4], body: &[u8]) {
+    let mut crc_input = Vec::with_capacity(4 + body.len());
+    crc_input.extend_from_slice(chunk_type);
+    crc_input.extend_from_slice(body);
+
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&crc_input);
+    out.extend_from_slice(&compute_crc(&crc_input).to_be_bytes());
+}
+
+fn png_frame(data: &[u8]) -> Result<Option<&[u8]>> {
+    Ok(parse_png(data)?
+        .into_iter()
+        .find(|c| &c.chunk_type == PNG_CHUNK_TYPE)
+        .map(|c| &data[c.start + 8..c.end - 4]))
+}
+
+/// Trailer entries every revision must repeat besides `/Size` and `/Root`
+const CARRIED_TRAILER_KEYS: [&str; 3] = ["/Info", "/ID", "/Encrypt"];
+
+/// Entries of the latest PDF trailer needed to chain an update onto it
+struct PdfTrailer {
+    /// Number of object ids in use (the next free id)
+    size: u64,
+    /// Catalog reference, e.g. "1 0 R"
+    root: String,
+    /// `/Info`, `/ID` and `/Encrypt` values present, copied verbatim
+    carried: Vec<(&'static str, String)>,
+    /// Offset of the latest cross-reference section
+    startxref: u64,
+}
+
+fn parse_pdf_trailer(data: &[u8]) -> Result<PdfTrailer> {
+    if !data.starts_with(b"%PDF-") {
+        return Err(StegoError::InvalidCover("Not a PDF file".to_string()));
+    }
+    let invalid = |what: &str| StegoError::InvalidCover(format!("PDF has no {}", what));
+
+    let startxref_pos = rfind(data, b"startxref").ok_or_else(|| invalid("startxref"))?;
+    let startxref =
+        parse_number(&data[startxref_pos + 9..]).ok_or_else(|| invalid("startxref offset"))?;
+
+    // A classic xref section cannot chain onto a cross-reference stream
+    let section = usize::try_from(startxref)
+        .ok()
+        .and_then(|offset| data.get(offset..))
+        .ok_or_else(|| invalid("cross-reference section at startxref"))?;
+    if !section[skip_whitespace(section, 0)..].starts_with(b"xref") {
+        return Err(StegoError::InvalidCover(
+            "PDF uses a cross-reference stream; only classic xref tables can be updated"
+                .to_string(),
+        ));
+    }
+
+    // The latest trailer precedes the last startxref
+    let trailer_pos = rfind(&data[..startxref_pos], b"trailer").ok_or_else(|| invalid("trailer"))?;
+    let dict_start = trailer_pos
+        + find(&data[trailer_pos..startxref_pos], b"<<").ok_or_else(|| invalid("trailer dictionary"))?;
+    let entries = dict_entries(&data[dict_start..startxref_pos])
+        .ok_or_else(|| StegoError::InvalidCover("Malformed PDF trailer dictionary".to_string()))?;
+    let entry = |key: &str| {
+        entries
+            .iter()
+            .find(|(k, _)| *k == key.as_bytes())
+            .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+    };
+
+    let size = entry("/Size")
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(|| invalid("/Size entry"))?;
+    let root = entry("/Root").ok_or_else(|| invalid("/Root entry"))?;
+    let carried = CARRIED_TRAILER_KEYS
+        .into_iter()
+        .filter_map(|key| entry(key).map(|value| (key, value)))
+        .collect();
+
+    Ok(PdfTrailer {
+        size,
+        root,
+        carried,
+        startxref,
+    })
+}
+
+/// Top-level `/Key value` pairs of the dictionary opening at `data[0..2] == b"<<"`
+fn dict_entries(data: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    let mut entries = Vec::new();
+    let mut pos = 2;
+    loop {
+        pos = skip_whitespace(data, pos);
+        if data.get(pos..pos + 2)? == b">>" {
+            return Some(entries);
+        }
+        if data[pos] != b'/' {
+            return None;
+        }
+        let key_end = token_end(data, pos + 1);
+        let value_start = skip_whitespace(data, key_end);
+        let value_end = value_end(data, value_start)?;
+        entries.push((&data[pos..key_end], &data[value_start..value_end]));
+        pos = value_end;
+    }
+}
+
+/// End of the PDF object starting at `start`: dictionary, array, string,
+/// name, `obj gen R` reference or a single number/keyword
+fn value_end(data: &[u8], start: usize) -> Option<usize> {
+    match *data.get(start)? {
+        b'<' if data.get(start + 1) == Some(&b'<') => nested_end(data, start, b"<<", b">>"),
+        b'<' => find(&data[start..], b">").map(|i| start + i + 1),
+        b'[' => nested_end(data, start, b"[", b"]"),
+        b'(' => nested_end(data, start, b"(", b")"),
+        b'/' => Some(token_end(data, start + 1)),
+        _ => {
+            let end = token_end(data, start);
+            let generation_start = skip_whitespace(data, end);
+            let generation_end = token_end(data, generation_start);
+            let r_start = skip_whitespace(data, generation_end);
+            let r_end = token_end(data, r_start);
+            let is_number = |token: &[u8]| !token.is_empty() && token.iter().all(u8::is_ascii_digit);
+            if is_number(&data[start..end])
+                && is_number(&data[generation_start..generation_end])
+                && &data[r_start..r_end] == b"R"
+            {
+                Some(r_end)
+            } else {
+                Some(end)
+            }
+        }
+    }
+}
+
+/// End of a bracketed object, counting nested `open`/`close` pairs
+fn nested_end(data: &[u8], start: usize, open: &[u8], close: &[u8]) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut pos = start;
+    while pos < data.len() {
+        if data[pos] == b'\\' && open == b"(" {
+            pos += 2;
+        } else if data[pos..].starts_with(open) {
+            depth += 1;
+            pos += open.len();
+        } else if data[pos..].starts_with(close) {
+            depth -= 1;
+            pos += close.len();
+            if depth == 0 {
+                return Some(pos);
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    None
+}
+
+fn skip_whitespace(data: &[u8], mut pos: usize) -> usize {
+    while data.get(pos).is_some_and(|b| b.is_ascii_whitespace() || *b == 0) {
+        pos += 1;
+    }
+    pos
+}
+
+/// End of a regular-character token (name body, number or keyword)
+fn token_end(data: &[u8], mut pos: usize) -> usize {
+    while data
+        .get(pos)
+        .is_some_and(|b| !b.is_ascii_whitespace() && !b"/[]<>(){}%".contains(b))
+    {
+        pos += 1;
+    }
+    pos
+}
+
+fn pdf_frame(data: &[u8]) -> Result<Option<&[u8]>> {
+    if !data.starts_with(b"%PDF-") {
+        return Err(StegoError::InvalidCover("Not a PDF file".to_string()));
+    }
+    let Some(type_pos) = rfind(data, PDF_STREAM_TYPE) else {
+        return Ok(None);
+    };
+
+    let dict = &data[type_pos..];
+    let malformed = || StegoError::ExtractionFailed("Malformed payload stream object".to_string());
+    let length_pos = find(dict, b"/Length").ok_or_else(malformed)?;
+    let length = parse_number(&dict[length_pos + 7..]).ok_or_else(malformed)? as usize;
+
+    let stream_pos = find(dict, b"stream").ok_or_else(malformed)?;
+    let mut start = type_pos + stream_pos + 6;
+    if data[start..].starts_with(b"\r\n") {
+        start += 2;
+    } else if data[start..].starts_with(b"\n") {
+        start += 1;
+    }
+
+    start
+        .checked_add(length)
+        .and_then(|end| data.get(start..end))
+        .map(Some)
+        .ok_or_else(malformed)
+}
+
+/// Unsigned decimal after optional whitespace
+fn parse_number(data: &[u8]) -> Option<u64> {
+    let digits: String = data
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take_while(|b| b.is_ascii_digit())
+        .map(|&b| b as char)
+        .collect();
+    digits.parse().ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{HEADER_LEN, TAG_LEN};
+    use crate::options::Compression;
+    use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
+    use std::io::Cursor;
+
+    fn create_test_png() -> Vec<u8> {
+        let img = ImageBuffer::from_fn(32, 32, |x, y| Rgb([(x * 8) as u8, (y * 8) as u8, 128]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    fn create_test_pdf() -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for object in [
+            "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+            "2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n",
+        ] {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(object.as_bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(b"xref\n0 3\n0000000000 65535 f\r\n");
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n\r\n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size 3 /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+
+    #[test]
+    fn test_png_chunk_round_trip() {
+        let cover = create_test_png();
+        let secret = b"bulk payload ".repeat(500);
+        let options = EmbedOptions::new().mac_key(b"k".as_slice());
+
+        let (stego, stats) = embed_png_chunk(&cover, &secret, &options).unwrap();
+        assert_eq!(container_carrier(&stego), Some(Carrier::PngChunk));
+        assert!(stats.payload_bytes < secret.len() as u64 / 10);
+        assert_eq!(stats.frame_bytes, (HEADER_LEN + TAG_LEN) as u64 + stats.payload_bytes);
+
+        // Still a valid PNG with identical pixels
+        let decoded = image::load_from_memory(&stego).unwrap();
+        let original = image::load_from_memory(&cover).unwrap();
+        assert_eq!(decoded.to_rgb8().as_raw(), original.to_rgb8().as_raw());
+
+        let extract = ExtractOptions::new().mac_key(b"k".as_slice());
+        assert_eq!(extract_png_chunk(&stego, &extract).unwrap(), secret);
+        assert!(extract_png_chunk(&stego, &ExtractOptions::new()).is_err());
+
+        // Re-embedding replaces the previous chunk
+        let (again, _) = embed_png_chunk(&stego, b"second", &EmbedOptions::new()).unwrap();
+        assert_eq!(
+            extract_container(&again, &ExtractOptions::new()).unwrap(),
+            b"second"
+        );
+        assert_eq!(
+            parse_png(&again).unwrap().len(),
+            parse_png(&cover).unwrap().len() + 1
+        );
+    }
+
+    #[test]
+    fn test_pdf_update_round_trip() {
+        let cover = create_test_pdf();
+        let options = EmbedOptions::new().compression(Compression::None);
+
+        let (stego, stats) = embed_pdf_update(&cover, b"hidden in a revision", &options).unwrap();
+        assert_eq!(stats.frame_bytes, HEADER_LEN as u64 + stats.payload_bytes);
+        assert!(stego.starts_with(&cover));
+        assert!(stego.ends_with(b"%%EOF\n"));
+        assert_eq!(container_carrier(&stego), Some(Carrier::PdfUpdate));
+
+        let extract = ExtractOptions::new().compression(Compression::None);
+        assert_eq!(
+            extract_pdf_update(&stego, &extract).unwrap(),
+            b"hidden in a revision"
+        );
+
+        // The update chains to the original xref and allocates the next object id
+        let trailer = parse_pdf_trailer(&stego).unwrap();
+        assert_eq!(trailer.size, 4);
+        assert_eq!(trailer.root, "1 0 R");
+        let object = format!("{:010} 00000 n\r\n", find(&stego, b"3 0 obj").unwrap());
+        assert!(find(&stego, object.as_bytes()).is_some());
+        assert!(find(
+            &stego,
+            format!("/Prev {}", parse_pdf_trailer(&cover).unwrap().startxref).as_bytes()
+        )
+        .is_some());
+
+        // Later updates win
+        let (again, _) = embed_pdf_update(&stego, b"newer", &options).unwrap();
+        assert_eq!(extract_pdf_update(&again, &extract).unwrap(), b"newer");
+    }
+
+    #[test]
+    fn test_invalid_containers() {
+        assert!(matches!(
+            embed_png_chunk(b"GIF89a", b"x", &EmbedOptions::new()),
+            Err(StegoError::InvalidCover(_))
+        ));
+        assert!(matches!(
+            embed_pdf_update(b"%PDF-1.4\nno trailer", b"x", &EmbedOptions::new()),
+            Err(StegoError::InvalidCover(_))
+        ));
+
+        let png = create_test_png();
+        assert!(container_carrier(&png).is_none());
+        assert!(container_carrier(&png[..png.len() - 12]).is_none());
+        assert!(extract_container(&create_test_pdf(), &ExtractOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_pdf_update_carries_trailer_entries() {
+        let cover = String::from_utf8(create_test_pdf()).unwrap().replace(
+            "<< /Size 3 /Root 1 0 R >>",
+            "<< /Size 3 /Root 1 0 R /Info 2 0 R\n/ID [<0A1B> <2C3D>] /Encrypt << /Filter /Standard /V 2 >> >>",
+        );
+        let (stego, _) = embed_pdf_update(cover.as_bytes(), b"x", &EmbedOptions::new()).unwrap();
+
+        let update = &stego[cover.len()..];
+        for entry in [
+            b"/Info 2 0 R".as_slice(),
+            b"/ID [<0A1B> <2C3D>]",
+            b"/Encrypt << /Filter /Standard /V 2 >>",
+        ] {
+            assert!(find(update, entry).is_some(), "{}", String::from_utf8_lossy(entry));
+        }
+
+        let trailer = parse_pdf_trailer(&stego).unwrap();
+        assert_eq!(trailer.root, "1 0 R");
+        assert_eq!(trailer.carried.len(), 3);
+    }
+
+    #[test]
+    fn test_pdf_update_refuses_xref_streams() {
+        let mut pdf = b"%PDF-1.5\n1 0 obj\n<< /Type /Catalog >>\nendobj\n".to_vec();
+        let xref = pdf.len();
+        pdf.extend_from_slice(
+            format!(
+                "2 0 obj\n<< /Type /XRef /Size 3 /Root 1 0 R /Length 0 >>\nstream\n\nendstream\nendobj\nstartxref\n{}\n%%EOF\n",
+                xref
+            )
+            .as_bytes(),
+        );
+
+        assert!(matches!(
+            embed_pdf_update(&pdf, b"x", &EmbedOptions::new()),
+            Err(StegoError::InvalidCover(msg)) if msg.contains("cross-reference stream")
+        ));
+    }
+
+    #[test]
+    fn test_pdf_frame_rejects_oversized_length() {
+        let mut pdf = create_test_pdf();
+        pdf.extend_from_slice(b"9 0 obj\n<< ");
+        pdf.extend_from_slice(PDF_STREAM_TYPE);
+        pdf.extend_from_slice(format!(" /Length {} >>\nstream\nabc", u64::MAX).as_bytes());
+
+        assert!(matches!(pdf_frame(&pdf), Err(StegoError::ExtractionFailed(_))));
+        assert!(container_carrier(&pdf).is_none());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::error::{Result, StegoError};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const AEAD_TAG_LEN: usize = 16;
+
+/// Bytes `seal` adds to the plaintext: salt, nonce and Poly1305 tag
+pub(crate) const SEAL_OVERHEAD: usize = SALT_LEN + NONCE_LEN + AEAD_TAG_LEN;
+
+const KDF_ROUNDS: u32 = 10_000;
+
+/// Stretch the passphrase with salted, iterated SHA-256 (as `TraversalKey` does)
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut digest: [u8; 32] = Sha256::new()
+        .chain_update(b"phase1-steg/encryption")
+        .chain_update(salt)
+        .chain_update(passphrase.as_bytes())
+        .finalize()
+        .into();
+    for _ in 0..KDF_ROUNDS {
+        digest = Sha256::new()
+            .chain_update(digest)
+            .chain_update(passphrase.as_bytes())
+            .finalize()
+            .into();
+    }
+    digest.into()
+}
+
+/// Encrypt with ChaCha20-Poly1305 under a passphrase-derived key:
+/// salt(16) + nonce(12) + ciphertext + tag(16), salt and nonce from the OS CSPRNG
+pub(crate) fn seal(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt_nonce = [0u8; SALT_LEN + NONCE_LEN];
+    getrandom::getrandom(&mut salt_nonce).map_err(|e| StegoError::Io(e.into()))?;
+    let (salt, nonce) = salt_nonce.split_at(SALT_LEN);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(nonce), plaintext)
+        .map_err(|_| StegoError::InvalidCover("Payload too large to encrypt".to_string()))?;
+
+    let mut sealed = Vec::with_capacity(SEAL_OVERHEAD + plaintext.len());
+    sealed.extend_from_slice(&salt_nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt a `seal`ed payload; a wrong passphrase fails authentication
+pub(crate) fn open(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if sealed.len() < SEAL_OVERHEAD {
+        return Err(StegoError::ExtractionFailed(
+            "Payload too short to be encrypted".to_string(),
+        ));
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt));
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| {
+        StegoError::AuthenticationFailed("decryption failed: wrong passphrase?".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip_and_wrong_passphrase() {
+        let sealed = seal(b"attack at dawn", "correct horse").unwrap();
+        assert_eq!(sealed.len(), SEAL_OVERHEAD + 14);
+        assert!(!sealed.windows(6).any(|w| w == b"attack"));
+        assert_eq!(open(&sealed, "correct horse").unwrap(), b"attack at dawn");
+
+        assert!(matches!(
+            open(&sealed, "battery staple"),
+            Err(StegoError::AuthenticationFailed(_))
+        ));
+
+        // Fresh salt and nonce every time
+        assert_ne!(sealed, seal(b"attack at dawn", "correct horse").unwrap());
+    }
+}
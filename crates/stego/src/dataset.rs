@@ -0,0 +1,123 @@
+use crate::noise::{generate_natural_cover, SeededRng};
+use crate::utils::CoverPattern;
+use image::{DynamicImage, ImageBuffer, Rgb};
+
+/// Size range and seed of a synthetic dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatasetParams {
+    /// Number of images (indices `0..count`)
+    pub count: usize,
+    pub min_width: u32,
+    pub max_width: u32,
+    pub min_height: u32,
+    pub max_height: u32,
+    /// Base seed; the same seed yields the same dataset on every node
+    pub seed: u64,
+}
+
+/// Dimensions and content seed of one dataset image, derived only from the
+/// dataset params and the index so every node generates identical images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatasetSpec {
+    pub index: usize,
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+}
+
+/// Spec of dataset image `index`, or `None` if it is outside `0..count`
+pub fn dataset_spec(config: &DatasetParams, index: usize) -> Option<DatasetSpec> {
+    if index >= config.count {
+        return None;
+    }
+
+    let mut rng = SeededRng::new(config.seed ^ (index as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93));
+    let width = pick(&mut rng, config.min_width, config.max_width);
+    let height = pick(&mut rng, config.min_height, config.max_height);
+
+    Some(DatasetSpec {
+        index,
+        width,
+        height,
+        seed: rng.next_u64(),
+    })
+}
+
+/// Generate the image described by `spec`
+pub fn generate_dataset_image(spec: &DatasetSpec, pattern: CoverPattern) -> DynamicImage {
+    match pattern {
+        CoverPattern::Natural => generate_natural_cover(spec.width, spec.height, spec.seed),
+        CoverPattern::Gradient => {
+            // Seeded interference stripes: cheap to generate, distinct per image
+            let seed = spec.seed % 997 + 1;
+            let img = ImageBuffer::from_fn(spec.width, spec.height, |x, y| {
+                let (x, y) = (u64::from(x), u64::from(y));
+                let r = ((x * seed) % 256) as u8;
+                let g = ((y * seed * 3) % 256) as u8;
+                let b = (((x + y) * seed * 7) % 256) as u8;
+                Rgb([r, g, b])
+            });
+            DynamicImage::ImageRgb8(img)
+        }
+    }
+}
+
+/// Uniform value in `min..=max` (`min` if the range is empty)
+fn pick(rng: &mut SeededRng, min: u32, max: u32) -> u32 {
+    let span = max.saturating_sub(min) as u64 + 1;
+    min + (rng.next_u64() % span) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    fn small_config(seed: u64) -> DatasetParams {
+        DatasetParams {
+            count: 10,
+            min_width: 32,
+            max_width: 64,
+            min_height: 16,
+            max_height: 48,
+            seed,
+        }
+    }
+
+    #[test]
+    fn test_dataset_is_deterministic_per_seed() {
+        let config = small_config(7);
+
+        for index in 0..config.count {
+            let spec = dataset_spec(&config, index).unwrap();
+            assert_eq!(Some(spec), dataset_spec(&config, index));
+            assert!((32..=64).contains(&spec.width));
+            assert!((16..=48).contains(&spec.height));
+
+            let a = generate_dataset_image(&spec, CoverPattern::Natural);
+            let b = generate_dataset_image(&spec, CoverPattern::Natural);
+            assert_eq!(a.dimensions(), (spec.width, spec.height));
+            assert_eq!(a.to_rgb8().as_raw(), b.to_rgb8().as_raw());
+        }
+
+        let other: Vec<_> = (0..10)
+            .map(|i| dataset_spec(&small_config(8), i).unwrap())
+            .collect();
+        let this: Vec<_> = (0..10).map(|i| dataset_spec(&config, i).unwrap()).collect();
+        assert_ne!(this, other);
+    }
+
+    #[test]
+    fn test_dataset_bounds() {
+        let config = small_config(0);
+        assert!(dataset_spec(&config, 9).is_some());
+        assert!(dataset_spec(&config, 10).is_none());
+
+        let fixed = DatasetParams {
+            min_width: 100,
+            max_width: 100,
+            ..small_config(0)
+        };
+        assert_eq!(dataset_spec(&fixed, 3).unwrap().width, 100);
+    }
+}
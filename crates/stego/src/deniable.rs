@@ -0,0 +1,151 @@
+use crate::error::{Result, StegoError};
+use crate::frame::build_frame;
+use crate::lsb::{capacity_bytes, write_keyed, CoverInfo, KeyedPath, TraversalKey};
+use image::{DynamicImage, GenericImageView};
+
+/// A secret and the passphrase that unlocks it
+#[derive(Debug, Clone, Copy)]
+pub struct KeyedPayload<'a> {
+    pub secret: &'a [u8],
+    pub passphrase: &'a str,
+}
+
+/// Embed up to two payloads under different passphrases for plausible deniability.
+///
+/// The cover's LSB slots are split into two interleaved halves, each filled
+/// completely along its own passphrase-keyed path. A half without a payload
+/// is filled with random bits, so an image holding only `decoy` is
+/// indistinguishable from one that also holds `hidden`: revealing the decoy
+/// passphrase proves nothing about the other half. Extract either payload
+/// with `extract_keyed`.
+///
+/// Both halves are written end to end, so every LSB of the cover is
+/// replaced. The result hides *how many* payloads there are, not *that* the
+/// image carries one: its LSB plane is uniformly random and is flagged by
+/// chi-square or RS steganalysis where a sparse `embed_keyed` would not be.
+/// Use it only on covers whose LSBs are already noise (sensor-noisy photos,
+/// `CoverPattern::Natural`).
+pub fn embed_dual(
+    cover: &DynamicImage,
+    decoy: KeyedPayload<'_>,
+    hidden: Option<KeyedPayload<'_>>,
+    compress: bool,
+) -> Result<(DynamicImage, CoverInfo)> {
+    if hidden.is_some_and(|hidden| hidden.passphrase == decoy.passphrase) {
+        return Err(StegoError::InvalidCover(
+            "Dual payloads require two different passphrases".to_string(),
+        ));
+    }
+
+    let (width, height) = cover.dimensions();
+    let mut rgb_img = cover.to_rgb8();
+
+    // The decoy takes the half its passphrase prefers, the hidden payload the other
+    let decoy_key = TraversalKey::derive(decoy.passphrase);
+    let decoy_half = decoy_key.preferred_half();
+    let hidden_key = match hidden {
+        Some(hidden) => TraversalKey::derive(hidden.passphrase),
+        None => TraversalKey::random()?,
+    };
+    let decoy_path = KeyedPath::half(&rgb_img, &decoy_key, decoy_half);
+    let hidden_path = KeyedPath::half(&rgb_img, &hidden_key, 1 - decoy_half);
+
+    let cover_info = CoverInfo {
+        width,
+        height,
+        channels: 3,
+        lsb_per_channel: 1,
+        capacity_bytes: capacity_bytes(width, height, 1),
+        partition_capacity_bytes: KeyedPath::halves(&rgb_img, &decoy_key)
+            .iter()
+            .map(|half| half.len() as u64 / 8)
+            .collect(),
+    };
+
+    fill_half(&mut rgb_img, &decoy_path, decoy.secret, &decoy_key, compress)?;
+
+    match hidden {
+        Some(hidden) => fill_half(&mut rgb_img, &hidden_path, hidden.secret, &hidden_key, compress)?,
+        // Keystream bits only, indistinguishable from a payload
+        None => write_keyed(&mut rgb_img, &hidden_path, hidden_path.len(), &[], &hidden_key),
+    }
+
+    Ok((DynamicImage::ImageRgb8(rgb_img), cover_info))
+}
+
+/// Write one payload into a half, padding the rest with keystream bits
+fn fill_half(
+    rgb_img: &mut image::RgbImage,
+    path: &KeyedPath,
+    secret: &[u8],
+    key: &TraversalKey,
+    compress: bool,
+) -> Result<()> {
+    let frame = build_frame(secret, compress, None)?;
+    let available = path.len() as u64 / 8;
+    if frame.len() as u64 > available {
+        return Err(StegoError::CapacityExceeded {
+            needed: frame.len() as u64,
+            available,
+        });
+    }
+
+    write_keyed(rgb_img, path, path.len(), &frame, key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsb::extract_keyed;
+    use image::{ImageBuffer, Rgb};
+
+    fn create_test_cover(width: u32, height: u32) -> DynamicImage {
+        let img = ImageBuffer::from_fn(width, height, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        });
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn test_dual_round_trip() {
+        let cover = create_test_cover(80, 80);
+        let decoy = KeyedPayload { secret: b"shopping list", passphrase: "decoy pass" };
+        let hidden = KeyedPayload { secret: b"the real secret", passphrase: "hidden pass" };
+
+        let (stego, info) = embed_dual(&cover, decoy, Some(hidden), true).unwrap();
+        assert_eq!(info.partition_capacity_bytes, vec![1200, 1200]);
+
+        assert_eq!(extract_keyed(&stego, "decoy pass", true).unwrap(), b"shopping list");
+        assert_eq!(extract_keyed(&stego, "hidden pass", true).unwrap(), b"the real secret");
+        assert!(extract_keyed(&stego, "guess", true).is_err());
+    }
+
+    #[test]
+    fn test_dual_without_hidden_payload() {
+        let cover = create_test_cover(60, 60);
+        let decoy = KeyedPayload { secret: b"only the decoy", passphrase: "decoy pass" };
+
+        let (stego, _info) = embed_dual(&cover, decoy, None, false).unwrap();
+        assert_eq!(extract_keyed(&stego, "decoy pass", false).unwrap(), b"only the decoy");
+        assert!(extract_keyed(&stego, "hidden pass", false).is_err());
+    }
+
+    #[test]
+    fn test_dual_rejects_same_passphrase_and_oversized_half() {
+        let cover = create_test_cover(40, 40);
+        let a = KeyedPayload { secret: b"a", passphrase: "same" };
+        assert!(matches!(
+            embed_dual(&cover, a, Some(a), false),
+            Err(StegoError::InvalidCover(_))
+        ));
+
+        // Each half holds 300 bytes; the whole image would hold 600
+        let big = vec![7u8; 400];
+        let decoy = KeyedPayload { secret: &big, passphrase: "p" };
+        assert!(matches!(
+            embed_dual(&cover, decoy, None, false),
+            Err(StegoError::CapacityExceeded { .. })
+        ));
+    }
+}
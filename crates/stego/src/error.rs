@@ -11,17 +11,32 @@ pub enum StegoError {
     #[error("CRC mismatch: expected {expected:#x}, got {actual:#x}")]
     CrcMismatch { expected: u32, actual: u32 },
 
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+
     #[error("Image error: {0}")]
     Image(#[from] image::ImageError),
 
     #[error("Compression error: {0}")]
     Compression(std::io::Error),
 
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("Invalid cover image: {0}")]
     InvalidCover(String),
 
     #[error("Extraction failed: {0}")]
     ExtractionFailed(String),
+
+    #[error("Invalid mask: {0}")]
+    InvalidMask(String),
+
+    #[error("Invalid watermark: {0}")]
+    InvalidWatermark(String),
+
+    #[error("Unsupported options: {0}")]
+    UnsupportedOptions(String),
 }
 
 pub type Result<T> = std::result::Result<T, StegoError>;
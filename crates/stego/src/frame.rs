@@ -0,0 +1,400 @@
+use crate::error::{Result, StegoError};
+use crc32fast::Hasher;
+use flate2::write::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+
+pub(crate) const MAGIC: u32 = 0x53544547; // "STEG"
+pub(crate) const MAGIC_AUTH: u32 = 0x53544741; // "STGA": header followed by an HMAC tag
+/// Frame header: magic, payload length, CRC
+pub const HEADER_LEN: usize = 12;
+/// HMAC-SHA256 tag following the header of authenticated frames
+pub const TAG_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Fixed-size frame header, decoded without touching the payload
+pub(crate) struct FrameHeader {
+    pub(crate) authenticated: bool,
+    pub(crate) payload_len: usize,
+    pub(crate) crc: u32,
+}
+
+impl FrameHeader {
+    /// Decode a header, returning `None` if the magic is not a known version
+    pub(crate) fn parse(header: &[u8]) -> Option<Self> {
+        let magic = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let authenticated = match magic {
+            MAGIC => false,
+            MAGIC_AUTH => true,
+            _ => return None,
+        };
+
+        Some(Self {
+            authenticated,
+            payload_len: u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize,
+            crc: u32::from_be_bytes([header[8], header[9], header[10], header[11]]),
+        })
+    }
+
+    /// Length of the HMAC tag between header and payload
+    pub(crate) fn tag_len(&self) -> usize {
+        if self.authenticated {
+            TAG_LEN
+        } else {
+            0
+        }
+    }
+}
+
+/// Build the embedded frame: magic(4) + len(4) + crc(4), an optional
+/// HMAC-SHA256 tag(32) over header and payload, then the payload
+pub(crate) fn build_frame(
+    secret_bytes: &[u8],
+    compress: bool,
+    mac_key: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let payload = if compress {
+        compress_data(secret_bytes)?
+    } else {
+        secret_bytes.to_vec()
+    };
+
+    let magic = if mac_key.is_some() { MAGIC_AUTH } else { MAGIC };
+    let payload_len = payload.len() as u32;
+    let crc = compute_crc(&payload);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(&magic.to_be_bytes());
+    header.extend_from_slice(&payload_len.to_be_bytes());
+    header.extend_from_slice(&crc.to_be_bytes());
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + TAG_LEN + payload.len());
+    frame.extend_from_slice(&header);
+    if let Some(key) = mac_key {
+        frame.extend_from_slice(&compute_mac(key, &header, &payload));
+    }
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Read and verify a frame through `read(offset, len)`, which returns `len`
+/// frame bytes starting at byte `offset` of a carrier holding `available` bytes
+pub(crate) fn read_frame(
+    mut read: impl FnMut(usize, usize) -> Vec<u8>,
+    available: usize,
+    compress: bool,
+    mac_key: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    if available < HEADER_LEN {
+        return Err(StegoError::ExtractionFailed(
+            "Carrier too small for header".to_string(),
+        ));
+    }
+
+    let header = read(0, HEADER_LEN);
+    let parsed = FrameHeader::parse(&header).ok_or_else(|| {
+        StegoError::InvalidMagic(u32::from_be_bytes([header[0], header[1], header[2], header[3]]))
+    })?;
+    let tag_len = parsed.tag_len();
+    let payload_len = parsed.payload_len;
+    let expected_crc = parsed.crc;
+
+    let body_available = available - HEADER_LEN;
+    if tag_len + payload_len > body_available {
+        return Err(StegoError::ExtractionFailed(format!(
+            "Not enough data: expected {} bytes, got {}",
+            tag_len + payload_len,
+            body_available
+        )));
+    }
+
+    let tag = read(HEADER_LEN, tag_len);
+    let payload = read(HEADER_LEN + tag_len, payload_len);
+
+    let actual_crc = compute_crc(&payload);
+    if actual_crc != expected_crc {
+        return Err(StegoError::CrcMismatch {
+            expected: expected_crc,
+            actual: actual_crc,
+        });
+    }
+
+    if let Some(key) = required_mac_key(parsed.authenticated, mac_key)? {
+        verify_mac(key, &header, &payload, &tag)?;
+    }
+
+    if compress {
+        decompress_data(&payload)
+    } else {
+        Ok(payload)
+    }
+}
+
+/// Authenticated frames need a key and a key needs an authenticated frame;
+/// returns the key to verify with, if any
+fn required_mac_key(authenticated: bool, mac_key: Option<&[u8]>) -> Result<Option<&[u8]>> {
+    match (authenticated, mac_key) {
+        (true, Some(key)) => Ok(Some(key)),
+        (true, None) => Err(StegoError::AuthenticationFailed(
+            "payload is authenticated; a MAC key is required".to_string(),
+        )),
+        (false, Some(_)) => Err(StegoError::AuthenticationFailed(
+            "payload carries no MAC".to_string(),
+        )),
+        (false, None) => Ok(None),
+    }
+}
+
+fn compute_mac(key: &[u8], header: &[u8], payload: &[u8]) -> [u8; TAG_LEN] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(header);
+    mac.update(payload);
+    mac.finalize().into_bytes().into()
+}
+
+fn verify_mac(key: &[u8], header: &[u8], payload: &[u8], tag: &[u8]) -> Result<()> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(header);
+    mac.update(payload);
+    mac.verify_slice(tag)
+        .map_err(|_| StegoError::AuthenticationFailed("MAC mismatch".to_string()))
+}
+
+pub(crate) fn compress_data(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).map_err(StegoError::Compression)?;
+    encoder.finish().map_err(StegoError::Compression)
+}
+
+pub(crate) fn decompress_data(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = DeflateDecoder::new(Vec::new());
+    decoder.write_all(data).map_err(StegoError::Compression)?;
+    decoder.finish().map_err(StegoError::Compression)
+}
+
+pub(crate) fn compute_crc(data: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Chunk size used when streaming frame bytes in and out of a carrier
+const STREAM_CHUNK: usize = 64 * 1024;
+
+/// Byte-addressable view of a carrier's embedding slots
+pub(crate) trait FrameSource {
+    /// Number of frame bytes the carrier can hold
+    fn capacity(&self) -> usize;
+    fn read_at(&self, offset: usize, len: usize) -> Vec<u8>;
+}
+
+impl FrameSource for &[u8] {
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    fn read_at(&self, offset: usize, len: usize) -> Vec<u8> {
+        self[offset..offset + len].to_vec()
+    }
+}
+
+pub(crate) trait FrameSink: FrameSource {
+    fn write_at(&mut self, offset: usize, bytes: &[u8]);
+}
+
+/// Stream `secret` into `sink` as a frame, compressing and CRC-ing incrementally.
+///
+/// The payload is written first and the header (length, CRC, optional tag)
+/// last, so the secret is never held in memory. Returns the frame length.
+pub(crate) fn write_frame_from(
+    sink: &mut impl FrameSink,
+    mut secret: impl Read,
+    compress: bool,
+    mac_key: Option<&[u8]>,
+) -> Result<usize> {
+    let body_start = HEADER_LEN + if mac_key.is_some() { TAG_LEN } else { 0 };
+    let mut writer = SinkWriter {
+        sink: &mut *sink,
+        offset: body_start,
+        crc: Hasher::new(),
+    };
+
+    if compress {
+        let mut encoder = DeflateEncoder::new(&mut writer, Compression::best());
+        io::copy(&mut secret, &mut encoder)?;
+        encoder.finish().map_err(StegoError::Compression)?;
+    } else {
+        io::copy(&mut secret, &mut writer)?;
+    }
+
+    let payload_len = writer.offset - body_start;
+    let crc = writer.crc.finalize();
+
+    let capacity = sink.capacity();
+    if body_start + payload_len > capacity {
+        return Err(StegoError::CapacityExceeded {
+            needed: (body_start + payload_len) as u64,
+            available: capacity as u64,
+        });
+    }
+    let payload_len_u32 = u32::try_from(payload_len).map_err(|_| {
+        StegoError::InvalidCover(format!("Payload of {} bytes is too large", payload_len))
+    })?;
+
+    let magic = if mac_key.is_some() { MAGIC_AUTH } else { MAGIC };
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(&magic.to_be_bytes());
+    header.extend_from_slice(&payload_len_u32.to_be_bytes());
+    header.extend_from_slice(&crc.to_be_bytes());
+    sink.write_at(0, &header);
+
+    if let Some(key) = mac_key {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(&header);
+        for_each_chunk(&*sink, body_start, payload_len, |chunk| {
+            mac.update(chunk);
+            Ok(())
+        })?;
+        let tag: [u8; TAG_LEN] = mac.finalize().into_bytes().into();
+        sink.write_at(HEADER_LEN, &tag);
+    }
+
+    Ok(body_start + payload_len)
+}
+
+/// Verify a frame in `source`, then stream its (decompressed) payload to `out`.
+///
+/// CRC and MAC are checked in a first pass, so nothing is written for a
+/// corrupt or forged payload. Returns the number of bytes written.
+pub(crate) fn read_frame_into(
+    source: &impl FrameSource,
+    compress: bool,
+    mac_key: Option<&[u8]>,
+    out: impl Write,
+) -> Result<u64> {
+    let available = source.capacity();
+    if available < HEADER_LEN {
+        return Err(StegoError::ExtractionFailed(
+            "Carrier too small for header".to_string(),
+        ));
+    }
+
+    let header = source.read_at(0, HEADER_LEN);
+    let parsed = FrameHeader::parse(&header).ok_or_else(|| {
+        StegoError::InvalidMagic(u32::from_be_bytes([header[0], header[1], header[2], header[3]]))
+    })?;
+    let body_start = HEADER_LEN + parsed.tag_len();
+
+    if body_start + parsed.payload_len > available {
+        return Err(StegoError::ExtractionFailed(format!(
+            "Not enough data: expected {} bytes, got {}",
+            parsed.tag_len() + parsed.payload_len,
+            available - HEADER_LEN
+        )));
+    }
+
+    let mut crc = Hasher::new();
+    let mut mac = required_mac_key(parsed.authenticated, mac_key)?.map(|key| {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(&header);
+        mac
+    });
+    for_each_chunk(source, body_start, parsed.payload_len, |chunk| {
+        crc.update(chunk);
+        if let Some(mac) = mac.as_mut() {
+            mac.update(chunk);
+        }
+        Ok(())
+    })?;
+
+    let actual_crc = crc.finalize();
+    if actual_crc != parsed.crc {
+        return Err(StegoError::CrcMismatch {
+            expected: parsed.crc,
+            actual: actual_crc,
+        });
+    }
+    if let Some(mac) = mac {
+        mac.verify_slice(&source.read_at(HEADER_LEN, TAG_LEN))
+            .map_err(|_| StegoError::AuthenticationFailed("MAC mismatch".to_string()))?;
+    }
+
+    let mut out = CountingWriter {
+        inner: out,
+        written: 0,
+    };
+    if compress {
+        let mut decoder = DeflateDecoder::new(&mut out);
+        for_each_chunk(source, body_start, parsed.payload_len, |chunk| {
+            decoder.write_all(chunk).map_err(StegoError::Compression)
+        })?;
+        decoder.finish().map_err(StegoError::Compression)?;
+    } else {
+        for_each_chunk(source, body_start, parsed.payload_len, |chunk| {
+            Ok(out.write_all(chunk)?)
+        })?;
+    }
+    out.flush()?;
+
+    Ok(out.written)
+}
+
+fn for_each_chunk(
+    source: &impl FrameSource,
+    start: usize,
+    len: usize,
+    mut f: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let end = start + len;
+    let mut offset = start;
+    while offset < end {
+        let chunk_len = STREAM_CHUNK.min(end - offset);
+        f(&source.read_at(offset, chunk_len))?;
+        offset += chunk_len;
+    }
+    Ok(())
+}
+
+/// Writes payload bytes into a sink, past its capacity only counting them
+struct SinkWriter<'a, S: FrameSink> {
+    sink: &'a mut S,
+    offset: usize,
+    crc: Hasher,
+}
+
+impl<S: FrameSink> Write for SinkWriter<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.sink.capacity().saturating_sub(self.offset);
+        let fits = buf.len().min(room);
+        if fits > 0 {
+            self.sink.write_at(self.offset, &buf[..fits]);
+        }
+        self.crc.update(buf);
+        self.offset += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -1,7 +1,37 @@
+pub mod audio;
+pub mod container;
+mod crypto;
+pub mod dataset;
+pub mod deniable;
 pub mod error;
+mod frame;
 pub mod lsb;
+pub mod mask;
+pub mod noise;
+pub mod options;
+pub mod probe;
+pub mod sniff;
 pub mod utils;
+pub mod watermark;
 
+pub use audio::{embed_audio, extract_audio, AudioInfo};
+pub use container::{
+    container_carrier, embed_pdf_update, embed_png_chunk, extract_container, extract_pdf_update,
+    extract_png_chunk, Carrier, CONTAINER_CAPACITY_BYTES,
+};
+pub use dataset::{dataset_spec, generate_dataset_image, DatasetParams, DatasetSpec};
+pub use deniable::{embed_dual, KeyedPayload};
 pub use error::{Result, StegoError};
-pub use lsb::{embed, extract, CoverInfo};
-pub use utils::{generate_cover_image, get_mime_type};
+pub use frame::{HEADER_LEN, TAG_LEN};
+pub use lsb::{
+    capacity_bytes, embed, embed_authenticated, embed_keyed, embed_masked, embed_stream, embed_with,
+    extract, extract_keyed, extract_stream, extract_verified, extract_with, masked_capacity,
+    required_capacity, CoverInfo,
+};
+pub use mask::{ChannelMask, EmbedMask, Rect, MASK_GRID};
+pub use noise::generate_natural_cover;
+pub use options::{Compression, Ecc, EmbedOptions, ExtractOptions, FrameStats};
+pub use probe::{probe, probe_file, ChannelOrder, PayloadCandidate, ProbeReport};
+pub use sniff::{sniff, ContentType};
+pub use utils::{generate_cover, generate_cover_image, get_mime_type, texture_score, CoverPattern};
+pub use watermark::{embed_watermark, extract_watermark, WatermarkResult};
@@ -1,11 +1,11 @@
 use crate::error::{Result, StegoError};
-use crc32fast::Hasher;
-use flate2::write::{DeflateEncoder, DeflateDecoder};
-use flate2::Compression;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb};
-use std::io::Write;
-
-const MAGIC: u32 = 0x53544547; // "STEG"
+use crate::frame::{build_frame, read_frame_into, write_frame_from, FrameSink, FrameSource};
+use crate::mask::{EmbedMask, MaskGrid, ANCHOR_PIXELS};
+use crate::options::{EmbedOptions, ExtractOptions, FrameStats};
+use crate::noise::SeededRng;
+use image::{DynamicImage, GenericImageView, RgbImage};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
 
 #[derive(Debug, Clone)]
 pub struct CoverInfo {
@@ -14,6 +14,19 @@ pub struct CoverInfo {
     pub channels: u8,
     pub lsb_per_channel: u8,
     pub capacity_bytes: u64,
+    /// Capacity of each independently keyed region (one entry unless split for dual payloads)
+    pub partition_capacity_bytes: Vec<u64>,
+}
+
+/// Number of payload bytes (header included) an RGB cover can hold
+pub fn capacity_bytes(width: u32, height: u32, lsb_per_channel: u8) -> u64 {
+    let total_pixels = width as u64 * height as u64;
+    total_pixels * 3 * lsb_per_channel as u64 / 8
+}
+
+/// Number of bytes `embed` will write for this secret (header + possibly compressed payload)
+pub fn required_capacity(secret_bytes: &[u8], compress: bool) -> Result<u64> {
+    Ok(build_frame(secret_bytes, compress, None)?.len() as u64)
 }
 
 /// Embed secret bytes into a cover image using LSB steganography
@@ -22,14 +35,73 @@ pub fn embed(
     secret_bytes: &[u8],
     lsb_per_channel: u8,
     compress: bool,
+) -> Result<(DynamicImage, CoverInfo)> {
+    let options = EmbedOptions::new()
+        .lsb_per_channel(lsb_per_channel)
+        .compression(compress.into());
+    embed_with(cover, secret_bytes, &options).map(|(img, info, _)| (img, info))
+}
+
+/// Embed like `embed`, adding an HMAC-SHA256 tag over header and payload so
+/// `extract_verified` can prove the content came from a holder of `mac_key`
+pub fn embed_authenticated(
+    cover: &DynamicImage,
+    secret_bytes: &[u8],
+    lsb_per_channel: u8,
+    compress: bool,
+    mac_key: &[u8],
+) -> Result<(DynamicImage, CoverInfo)> {
+    let options = EmbedOptions::new()
+        .lsb_per_channel(lsb_per_channel)
+        .compression(compress.into())
+        .mac_key(mac_key);
+    embed_with(cover, secret_bytes, &options).map(|(img, info, _)| (img, info))
+}
+
+/// Embed secret bytes with every knob taken from `options`.
+///
+/// A traversal key selects the keyed path of `embed_keyed`, a mask the
+/// protected layout of `embed_masked`; the two cannot be combined.
+/// Encryption and ECC apply on every path.
+pub fn embed_with(
+    cover: &DynamicImage,
+    secret_bytes: &[u8],
+    options: &EmbedOptions,
+) -> Result<(DynamicImage, CoverInfo, FrameStats)> {
+    check_lsb_per_channel(options.lsb_per_channel)?;
+
+    if options.lsb_per_channel != 1 && (options.traversal_key.is_some() || options.mask.is_some()) {
+        return Err(StegoError::UnsupportedOptions(
+            "Keyed and masked embedding use 1 LSB per channel".to_string(),
+        ));
+    }
+
+    if options.traversal_key.is_some() && options.mask.is_some() {
+        return Err(StegoError::InvalidMask(
+            "masks cannot be combined with a traversal key".to_string(),
+        ));
+    }
+
+    let (frame, stats) = options.encode_frame(secret_bytes)?;
+    let (img, info) = match (&options.traversal_key, &options.mask) {
+        (Some(passphrase), _) => embed_keyed_frame(cover, &frame, passphrase)?,
+        (None, Some(mask)) => embed_masked_frame(cover, &frame, mask)?,
+        (None, None) => embed_frame(cover, &frame, options.lsb_per_channel)?,
+    };
+    Ok((img, info, stats))
+}
+
+fn embed_frame(
+    cover: &DynamicImage,
+    frame: &[u8],
+    lsb_per_channel: u8,
 ) -> Result<(DynamicImage, CoverInfo)> {
     let (width, height) = cover.dimensions();
     let channels = 3u8; // RGB only
     
     // Calculate capacity
-    let total_pixels = width as u64 * height as u64;
-    let bits_available = total_pixels * channels as u64 * lsb_per_channel as u64;
-    let capacity_bytes = bits_available / 8;
+    let capacity_bytes = capacity_bytes(width, height, lsb_per_channel);
+    let bits_available = capacity_bytes * 8;
 
     let cover_info = CoverInfo {
         width,
@@ -37,26 +109,10 @@ pub fn embed(
         channels,
         lsb_per_channel,
         capacity_bytes,
+        partition_capacity_bytes: vec![capacity_bytes],
     };
 
-    // Prepare payload
-    let payload = if compress {
-        compress_data(secret_bytes)?
-    } else {
-        secret_bytes.to_vec()
-    };
-
-    let payload_len = payload.len() as u32;
-    let crc = compute_crc(&payload);
-
-    // Build header: magic(4) + len(4) + crc(4) = 12 bytes
-    let mut header = Vec::with_capacity(12 + payload.len());
-    header.extend_from_slice(&MAGIC.to_be_bytes());
-    header.extend_from_slice(&payload_len.to_be_bytes());
-    header.extend_from_slice(&crc.to_be_bytes());
-    header.extend_from_slice(&payload);
-
-    let required_bits = header.len() as u64 * 8;
+    let required_bits = frame.len() as u64 * 8;
     if required_bits > bits_available {
         return Err(StegoError::CapacityExceeded {
             needed: required_bits.div_ceil(8),
@@ -64,167 +120,550 @@ pub fn embed(
         });
     }
 
-    // Convert cover to RGB8
-    let rgb_img = cover.to_rgb8();
-    let mut stego = ImageBuffer::new(width, height);
+    let mut rgb_img = cover.to_rgb8();
+    LsbPlane::full(&mut *rgb_img, lsb_per_channel).write_at(0, frame);
 
-    let mut bit_index = 0usize;
-    let total_bits = header.len() * 8;
+    Ok((DynamicImage::ImageRgb8(rgb_img), cover_info))
+}
 
-    for y in 0..height {
-        for x in 0..width {
-            if bit_index >= total_bits {
-                // Copy remaining pixels unchanged
-                let pixel = rgb_img.get_pixel(x, y);
-                stego.put_pixel(x, y, *pixel);
-                continue;
-            }
+/// Extract secret bytes from a stego image
+pub fn extract(
+    stego: &DynamicImage,
+    lsb_per_channel: u8,
+    compress: bool,
+) -> Result<Vec<u8>> {
+    let options = ExtractOptions::new()
+        .lsb_per_channel(lsb_per_channel)
+        .compression(compress.into());
+    extract_with(stego, &options)
+}
 
-            let pixel = rgb_img.get_pixel(x, y);
-            let mut new_pixel = [pixel[0], pixel[1], pixel[2]];
+/// Extract secret bytes, verifying the HMAC tag with `mac_key`.
+///
+/// With a key, frames without a valid tag fail with `AuthenticationFailed`;
+/// without one, authenticated frames fail the same way since they cannot be
+/// verified.
+pub fn extract_verified(
+    stego: &DynamicImage,
+    lsb_per_channel: u8,
+    compress: bool,
+    mac_key: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let mut options = ExtractOptions::new()
+        .lsb_per_channel(lsb_per_channel)
+        .compression(compress.into());
+    if let Some(key) = mac_key {
+        options = options.mac_key(key);
+    }
+    extract_with(stego, &options)
+}
 
-            for _ in 0..3 {
-                if bit_index >= total_bits {
-                    break;
-                }
+/// Extract secret bytes embedded with matching `EmbedOptions`.
+///
+/// Masked layouts are detected from their descriptor; a traversal key reads
+/// the keyed path (whole image or either dual half) instead.
+pub fn extract_with(stego: &DynamicImage, options: &ExtractOptions) -> Result<Vec<u8>> {
+    if let Some(passphrase) = &options.traversal_key {
+        return extract_keyed_with_options(stego, passphrase, options);
+    }
 
-                let byte_idx = bit_index / 8;
-                let bit_offset = 7 - (bit_index % 8); // MSB first
-                let bit = (header[byte_idx] >> bit_offset) & 1;
+    check_lsb_per_channel(options.lsb_per_channel)?;
+    let rgb_img = stego.to_rgb8();
+    let plane = LsbPlane::detect(&rgb_img, options.lsb_per_channel);
 
-                // Clear LSB and set new bit
-                new_pixel[bit_index % 3] = (new_pixel[bit_index % 3] & !1) | bit;
-                bit_index += 1;
-            }
+    options.read_payload(|offset, len| plane.read_at(offset, len), plane.capacity())
+}
 
-            stego.put_pixel(x, y, Rgb(new_pixel));
-        }
+/// Embed like `embed_authenticated` (or `embed` without a key), leaving the
+/// regions and channels protected by `mask` untouched.
+///
+/// The quantised mask is stored in a small descriptor at the start (or, if
+/// that area is protected, the end) of the image; `extract` detects it and
+/// walks the same positions. `CoverInfo` reports the capacity left by the mask.
+pub fn embed_masked(
+    cover: &DynamicImage,
+    secret_bytes: &[u8],
+    compress: bool,
+    mask: &EmbedMask,
+    mac_key: Option<&[u8]>,
+) -> Result<(DynamicImage, CoverInfo)> {
+    let mut options = EmbedOptions::new()
+        .compression(compress.into())
+        .mask(mask.clone());
+    if let Some(key) = mac_key {
+        options = options.mac_key(key);
+    }
+    embed_with(cover, secret_bytes, &options).map(|(img, info, _)| (img, info))
+}
+
+fn embed_masked_frame(
+    cover: &DynamicImage,
+    frame: &[u8],
+    mask: &EmbedMask,
+) -> Result<(DynamicImage, CoverInfo)> {
+    let (width, height) = cover.dimensions();
+    let grid = mask.rasterize(width, height)?;
+    let anchor = grid.anchor(width, height).ok_or_else(|| {
+        StegoError::InvalidMask(format!(
+            "mask must leave the first or last {} pixels free for its descriptor",
+            ANCHOR_PIXELS
+        ))
+    })?;
+
+    let slots = grid.slots(width, height, &anchor);
+    let capacity_bytes = (slots.len() / 8) as u64;
+    let cover_info = CoverInfo {
+        width,
+        height,
+        channels: grid.channel_count(),
+        lsb_per_channel: 1,
+        capacity_bytes,
+        partition_capacity_bytes: vec![capacity_bytes],
+    };
+
+    if frame.len() as u64 > capacity_bytes {
+        return Err(StegoError::CapacityExceeded {
+            needed: frame.len() as u64,
+            available: capacity_bytes,
+        });
     }
 
-    Ok((DynamicImage::ImageRgb8(stego), cover_info))
+    let mut rgb_img = cover.to_rgb8();
+    grid.write_descriptor(&mut rgb_img, &anchor);
+    LsbPlane::masked(&mut *rgb_img, slots).write_at(0, frame);
+
+    Ok((DynamicImage::ImageRgb8(rgb_img), cover_info))
 }
 
-/// Extract secret bytes from a stego image
-pub fn extract(
+/// Payload capacity of a `width` x `height` cover under `mask` (header included)
+pub fn masked_capacity(width: u32, height: u32, mask: &EmbedMask) -> Result<u64> {
+    let grid = mask.rasterize(width, height)?;
+    Ok(grid
+        .anchor(width, height)
+        .map_or(0, |anchor| (grid.slots(width, height, &anchor).len() / 8) as u64))
+}
+
+/// Streaming `embed`: reads the secret from `secret`, compressing and CRC-ing
+/// it straight into the cover's LSBs instead of buffering it in memory.
+///
+/// Produces the same frame as `embed` (or `embed_authenticated` with a key).
+pub fn embed_stream(
+    cover: &DynamicImage,
+    secret: impl Read,
+    lsb_per_channel: u8,
+    compress: bool,
+    mac_key: Option<&[u8]>,
+) -> Result<(DynamicImage, CoverInfo)> {
+    let (width, height) = cover.dimensions();
+    let capacity_bytes = capacity_bytes(width, height, lsb_per_channel);
+    let cover_info = CoverInfo {
+        width,
+        height,
+        channels: 3,
+        lsb_per_channel,
+        capacity_bytes,
+        partition_capacity_bytes: vec![capacity_bytes],
+    };
+
+    check_lsb_per_channel(lsb_per_channel)?;
+    let mut rgb_img = cover.to_rgb8();
+    write_frame_from(
+        &mut LsbPlane::full(&mut *rgb_img, lsb_per_channel),
+        secret,
+        compress,
+        mac_key,
+    )?;
+
+    Ok((DynamicImage::ImageRgb8(rgb_img), cover_info))
+}
+
+/// Streaming `extract_verified`: verifies the frame, then writes the recovered
+/// (decompressed) secret to `out`. Returns the number of bytes written.
+pub fn extract_stream(
     stego: &DynamicImage,
-    _lsb_per_channel: u8,
+    lsb_per_channel: u8,
     compress: bool,
+    mac_key: Option<&[u8]>,
+    out: impl Write,
+) -> Result<u64> {
+    check_lsb_per_channel(lsb_per_channel)?;
+    let rgb_img = stego.to_rgb8();
+    read_frame_into(&LsbPlane::detect(&rgb_img, lsb_per_channel), compress, mac_key, out)
+}
+
+fn check_lsb_per_channel(lsb_per_channel: u8) -> Result<()> {
+    if !(1..=8).contains(&lsb_per_channel) {
+        return Err(StegoError::UnsupportedOptions(format!(
+            "lsb_per_channel must be between 1 and 8, got {}",
+            lsb_per_channel
+        )));
+    }
+    Ok(())
+}
+
+/// Embed secret bytes along a passphrase-keyed pseudo-random path through the cover.
+///
+/// The frame (header included) is XORed with a passphrase-derived keystream, so
+/// without the passphrase neither the positions nor the header are recognisable.
+pub fn embed_keyed(
+    cover: &DynamicImage,
+    secret_bytes: &[u8],
+    passphrase: &str,
+    compress: bool,
+) -> Result<(DynamicImage, CoverInfo)> {
+    let options = EmbedOptions::new()
+        .compression(compress.into())
+        .traversal_key(passphrase);
+    embed_with(cover, secret_bytes, &options).map(|(img, info, _)| (img, info))
+}
+
+fn embed_keyed_frame(
+    cover: &DynamicImage,
+    frame: &[u8],
+    passphrase: &str,
+) -> Result<(DynamicImage, CoverInfo)> {
+    let (width, height) = cover.dimensions();
+    let mut rgb_img = cover.to_rgb8();
+
+    let capacity_bytes = capacity_bytes(width, height, 1);
+    let cover_info = CoverInfo {
+        width,
+        height,
+        channels: 3,
+        lsb_per_channel: 1,
+        capacity_bytes,
+        partition_capacity_bytes: vec![capacity_bytes],
+    };
+
+    if frame.len() as u64 > capacity_bytes {
+        return Err(StegoError::CapacityExceeded {
+            needed: frame.len() as u64,
+            available: capacity_bytes,
+        });
+    }
+
+    let key = TraversalKey::derive(passphrase);
+    let path = KeyedPath::whole(&rgb_img, &key);
+    write_keyed(&mut rgb_img, &path, frame.len() * 8, frame, &key);
+
+    Ok((DynamicImage::ImageRgb8(rgb_img), cover_info))
+}
+
+/// Extract secret bytes embedded with `embed_keyed` or `embed_dual`
+pub fn extract_keyed(stego: &DynamicImage, passphrase: &str, compress: bool) -> Result<Vec<u8>> {
+    let options = ExtractOptions::new()
+        .compression(compress.into())
+        .traversal_key(passphrase);
+    extract_with(stego, &options)
+}
+
+fn extract_keyed_with_options(
+    stego: &DynamicImage,
+    passphrase: &str,
+    options: &ExtractOptions,
 ) -> Result<Vec<u8>> {
-    let (width, height) = stego.dimensions();
     let rgb_img = stego.to_rgb8();
+    let key = TraversalKey::derive(passphrase);
+
+    // A keyed payload may use the whole image or either half of a dual split
+    let whole = KeyedPath::whole(&rgb_img, &key);
+    let halves = KeyedPath::halves(&rgb_img, &key);
+    for path in std::iter::once(whole).chain(halves) {
+        if let Ok(secret) = read_keyed(&rgb_img, &path, &key, options) {
+            return Ok(secret);
+        }
+    }
 
-    // Extract header (12 bytes = 96 bits)
-    let mut header_bits = Vec::new();
-    let mut bit_count = 0usize;
-    let header_bits_needed = 96;
+    Err(StegoError::ExtractionFailed(
+        "No payload found for this passphrase".to_string(),
+    ))
+}
 
-    'outer: for y in 0..height {
-        for x in 0..width {
-            if bit_count >= header_bits_needed {
-                break 'outer;
-            }
+/// Secrets derived from a passphrase: traversal seed and keystream key
+pub(crate) struct TraversalKey {
+    seed: u64,
+    stream_key: [u8; 32],
+}
 
-            let pixel = rgb_img.get_pixel(x, y);
-            for ch in 0..3 {
-                if bit_count >= header_bits_needed {
-                    break 'outer;
-                }
-                let bit = pixel[ch] & 1;
-                header_bits.push(bit);
-                bit_count += 1;
-            }
+impl TraversalKey {
+    const KDF_ROUNDS: u32 = 10_000;
+
+    /// Stretch the passphrase with iterated SHA-256 and split it into
+    /// independent traversal and keystream keys
+    pub(crate) fn derive(passphrase: &str) -> Self {
+        let mut digest: [u8; 32] = Sha256::new()
+            .chain_update(b"phase1-steg/traversal")
+            .chain_update(passphrase.as_bytes())
+            .finalize()
+            .into();
+        for _ in 0..Self::KDF_ROUNDS {
+            digest = Sha256::new()
+                .chain_update(digest)
+                .chain_update(passphrase.as_bytes())
+                .finalize()
+                .into();
+        }
+
+        let seed_bytes: [u8; 32] = Sha256::new().chain_update(b"seed").chain_update(digest).finalize().into();
+        let stream_key: [u8; 32] = Sha256::new().chain_update(b"stream").chain_update(digest).finalize().into();
+
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&seed_bytes[..8]);
+        Self {
+            seed: u64::from_be_bytes(seed),
+            stream_key,
         }
     }
 
-    if header_bits.len() < header_bits_needed {
-        return Err(StegoError::ExtractionFailed(
-            "Image too small for header".to_string(),
-        ));
+    /// Key from the OS CSPRNG, used to fill regions that hold no payload
+    pub(crate) fn random() -> Result<Self> {
+        let mut stream_key = [0u8; 32];
+        getrandom::getrandom(&mut stream_key).map_err(|e| StegoError::Io(e.into()))?;
+
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&stream_key[..8]);
+        Ok(Self {
+            seed: u64::from_be_bytes(seed),
+            stream_key,
+        })
     }
 
-    // Reconstruct header bytes
-    let header_bytes = bits_to_bytes(&header_bits[..96]);
-    
-    let magic = u32::from_be_bytes([header_bytes[0], header_bytes[1], header_bytes[2], header_bytes[3]]);
-    if magic != MAGIC {
-        return Err(StegoError::InvalidMagic(magic));
-    }
-
-    let payload_len = u32::from_be_bytes([header_bytes[4], header_bytes[5], header_bytes[6], header_bytes[7]]) as usize;
-    let expected_crc = u32::from_be_bytes([header_bytes[8], header_bytes[9], header_bytes[10], header_bytes[11]]);
-
-    // Extract payload
-    let payload_bits_needed = payload_len * 8;
-    let mut payload_bits = Vec::new();
-    bit_count = 0;
-
-    'outer2: for y in 0..height {
-        for x in 0..width {
-            let pixel = rgb_img.get_pixel(x, y);
-            for ch in 0..3 {
-                if bit_count < 96 {
-                    // Skip header bits
-                    bit_count += 1;
-                    continue;
-                }
-
-                if payload_bits.len() >= payload_bits_needed {
-                    break 'outer2;
-                }
-
-                let bit = pixel[ch] & 1;
-                payload_bits.push(bit);
-                bit_count += 1;
-            }
+    /// Preferred dual-split half for this passphrase (0 or 1)
+    pub(crate) fn preferred_half(&self) -> usize {
+        (self.stream_key[31] & 1) as usize
+    }
+
+    /// Keystream bytes in SHA-256 counter mode
+    pub(crate) fn keystream(&self, len: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(len + 32);
+        let mut counter = 0u64;
+        while out.len() < len {
+            let block = Sha256::new()
+                .chain_update(self.stream_key)
+                .chain_update(counter.to_be_bytes())
+                .finalize();
+            out.extend_from_slice(&block);
+            counter += 1;
         }
+        out.truncate(len);
+        out
     }
+}
 
-    if payload_bits.len() < payload_bits_needed {
-        return Err(StegoError::ExtractionFailed(
-            format!("Not enough data: expected {} bits, got {}", payload_bits_needed, payload_bits.len()),
-        ));
+/// Keyed pseudo-random permutation of `0..len`, evaluated one index at a time.
+///
+/// A balanced Feistel network over the next even power of two, cycle-walked
+/// back into range, so a path costs no memory and only the positions a frame
+/// uses are ever computed.
+struct KeyedPermutation {
+    len: usize,
+    half_bits: u32,
+    round_keys: [u64; 4],
+}
+
+impl KeyedPermutation {
+    fn new(len: usize, seed: u64) -> Self {
+        let bits = usize::BITS - len.saturating_sub(1).leading_zeros();
+        let mut rng = SeededRng::new(seed);
+        Self {
+            len,
+            half_bits: bits.max(2).div_ceil(2),
+            round_keys: std::array::from_fn(|_| rng.next_u64()),
+        }
     }
 
-    let payload = bits_to_bytes(&payload_bits);
+    fn feistel(&self, x: u64) -> u64 {
+        let mask = (1u64 << self.half_bits) - 1;
+        let (mut left, mut right) = (x >> self.half_bits, x & mask);
+        for &round_key in &self.round_keys {
+            let mut z = right ^ round_key;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            (left, right) = (right, left ^ ((z ^ (z >> 31)) & mask));
+        }
+        (left << self.half_bits) | right
+    }
 
-    // Verify CRC
-    let actual_crc = compute_crc(&payload);
-    if actual_crc != expected_crc {
-        return Err(StegoError::CrcMismatch {
-            expected: expected_crc,
-            actual: actual_crc,
-        });
+    /// Image of `index` (below `len`)
+    fn index(&self, index: usize) -> usize {
+        let mut x = self.feistel(index as u64);
+        while x >= self.len as u64 {
+            x = self.feistel(x);
+        }
+        x as usize
     }
+}
 
-    // Decompress if needed
-    let secret = if compress {
-        decompress_data(&payload)?
-    } else {
-        payload
+/// Passphrase-keyed order over the LSB slots of an RGB image: every sample,
+/// or one of the two interleaved halves used for dual payloads
+pub(crate) struct KeyedPath {
+    first: usize,
+    stride: usize,
+    permutation: KeyedPermutation,
+}
+
+impl KeyedPath {
+    pub(crate) fn whole(rgb_img: &RgbImage, key: &TraversalKey) -> Self {
+        Self {
+            first: 0,
+            stride: 1,
+            permutation: KeyedPermutation::new(rgb_img.as_raw().len(), key.seed),
+        }
+    }
+
+    /// The even (`half` 0) or the odd (`half` 1) samples
+    pub(crate) fn half(rgb_img: &RgbImage, key: &TraversalKey, half: usize) -> Self {
+        let samples = rgb_img.as_raw().len();
+        Self {
+            first: half,
+            stride: 2,
+            permutation: KeyedPermutation::new((samples + 1 - half) / 2, key.seed),
+        }
+    }
+
+    pub(crate) fn halves(rgb_img: &RgbImage, key: &TraversalKey) -> [Self; 2] {
+        [0, 1].map(|half| Self::half(rgb_img, key, half))
+    }
+
+    /// Number of slots on the path
+    pub(crate) fn len(&self) -> usize {
+        self.permutation.len
+    }
+
+    /// Sample holding bit `bit_index` of the path
+    fn slot(&self, bit_index: usize) -> usize {
+        self.first + self.stride * self.permutation.index(bit_index)
+    }
+}
+
+/// Write `data` XOR keystream into the LSBs of the first `bits` slots of
+/// `path` (MSB first), padding past `data` with keystream bits
+pub(crate) fn write_keyed(
+    rgb_img: &mut RgbImage,
+    path: &KeyedPath,
+    bits: usize,
+    data: &[u8],
+    key: &TraversalKey,
+) {
+    let stream = key.keystream(bits.div_ceil(8));
+    let raw: &mut [u8] = rgb_img;
+
+    for bit_index in 0..bits {
+        let byte = data.get(bit_index / 8).copied().unwrap_or(0) ^ stream[bit_index / 8];
+        let bit = (byte >> (7 - (bit_index % 8))) & 1;
+        let slot = path.slot(bit_index);
+        raw[slot] = (raw[slot] & !1) | bit;
+    }
+}
+
+/// Read a whitened frame along `path` and verify its header and CRC
+pub(crate) fn read_keyed(
+    rgb_img: &RgbImage,
+    path: &KeyedPath,
+    key: &TraversalKey,
+    options: &ExtractOptions,
+) -> Result<Vec<u8>> {
+    let raw = rgb_img.as_raw();
+    let read = |offset: usize, len: usize| {
+        let bits: Vec<u8> = (offset * 8..(offset + len) * 8)
+            .map(|bit_index| raw[path.slot(bit_index)] & 1)
+            .collect();
+        let stream = key.keystream(offset + len);
+        bits_to_bytes(&bits)
+            .into_iter()
+            .zip(&stream[offset..])
+            .map(|(byte, k)| byte ^ k)
+            .collect()
     };
 
-    Ok(secret)
+    options.read_payload(read, path.len() / 8)
+}
+
+/// Frame bytes stored MSB first in raw sample LSBs: the low `lsb` bits of
+/// every sample in order (highest of them first), or the lowest bit of only
+/// `slots` for masked embeddings
+struct LsbPlane<B> {
+    raw: B,
+    lsb: usize,
+    slots: Option<Vec<usize>>,
 }
 
-fn compress_data(data: &[u8]) -> Result<Vec<u8>> {
-    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
-    encoder.write_all(data).map_err(StegoError::Compression)?;
-    encoder.finish().map_err(StegoError::Compression)
+impl<B> LsbPlane<B> {
+    fn full(raw: B, lsb_per_channel: u8) -> Self {
+        Self {
+            raw,
+            lsb: lsb_per_channel as usize,
+            slots: None,
+        }
+    }
+
+    fn masked(raw: B, slots: Vec<usize>) -> Self {
+        Self {
+            raw,
+            lsb: 1,
+            slots: Some(slots),
+        }
+    }
+
+    /// Sample index and bit shift holding frame bit `bit_index`
+    fn position(&self, bit_index: usize) -> (usize, usize) {
+        match &self.slots {
+            Some(slots) => (slots[bit_index], 0),
+            None => (bit_index / self.lsb, self.lsb - 1 - bit_index % self.lsb),
+        }
+    }
+}
+
+impl<'a> LsbPlane<&'a [u8]> {
+    /// Plane written by `embed` or, if a mask descriptor is present, `embed_masked`
+    fn detect(rgb_img: &'a RgbImage, lsb_per_channel: u8) -> Self {
+        let (width, height) = rgb_img.dimensions();
+        match MaskGrid::find(rgb_img) {
+            Some((grid, anchor)) => Self::masked(rgb_img.as_raw(), grid.slots(width, height, &anchor)),
+            None => Self::full(rgb_img.as_raw(), lsb_per_channel),
+        }
+    }
 }
 
-fn decompress_data(data: &[u8]) -> Result<Vec<u8>> {
-    let mut decoder = DeflateDecoder::new(Vec::new());
-    decoder.write_all(data).map_err(StegoError::Compression)?;
-    decoder.finish().map_err(StegoError::Compression)
+/// Plane of an `embed_masked` image, if it carries a mask descriptor
+pub(crate) fn masked_plane(rgb_img: &RgbImage) -> Option<impl FrameSource + '_> {
+    let (width, height) = rgb_img.dimensions();
+    MaskGrid::find(rgb_img)
+        .map(|(grid, anchor)| LsbPlane::masked(rgb_img.as_raw(), grid.slots(width, height, &anchor)))
 }
 
-fn compute_crc(data: &[u8]) -> u32 {
-    let mut hasher = Hasher::new();
-    hasher.update(data);
-    hasher.finalize()
+impl<B: AsRef<[u8]>> FrameSource for LsbPlane<B> {
+    fn capacity(&self) -> usize {
+        match &self.slots {
+            Some(slots) => slots.len() / 8,
+            None => self.raw.as_ref().len() * self.lsb / 8,
+        }
+    }
+
+    fn read_at(&self, offset: usize, len: usize) -> Vec<u8> {
+        let raw = self.raw.as_ref();
+        let bits: Vec<u8> = (offset * 8..(offset + len) * 8)
+            .map(|bit_index| {
+                let (slot, shift) = self.position(bit_index);
+                (raw[slot] >> shift) & 1
+            })
+            .collect();
+        bits_to_bytes(&bits)
+    }
 }
 
-fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
+impl<B: AsRef<[u8]> + AsMut<[u8]>> FrameSink for LsbPlane<B> {
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) {
+        for i in 0..bytes.len() * 8 {
+            let bit = (bytes[i / 8] >> (7 - (i % 8))) & 1;
+            let (slot, shift) = self.position(offset * 8 + i);
+            let sample = &mut self.raw.as_mut()[slot];
+            *sample = (*sample & !(1 << shift)) | (bit << shift);
+        }
+    }
+}
+
+pub(crate) fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
     bits.chunks(8)
         .map(|chunk| {
             let mut byte = 0u8;
@@ -239,6 +678,8 @@ fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mask::ChannelMask;
+    use crate::options::Ecc;
     use image::{ImageBuffer, Rgb};
 
     fn create_test_cover(width: u32, height: u32) -> DynamicImage {
@@ -279,6 +720,224 @@ mod tests {
         assert!(matches!(result, Err(StegoError::CapacityExceeded { .. })));
     }
 
+    #[test]
+    fn test_keyed_round_trip() {
+        let cover = create_test_cover(100, 100);
+        let secret = b"Keyed traversal secret";
+
+        let (stego, _info) = embed_keyed(&cover, secret, "correct horse", true).unwrap();
+        let recovered = extract_keyed(&stego, "correct horse", true).unwrap();
+        assert_eq!(secret.as_slice(), recovered.as_slice());
+
+        assert!(extract_keyed(&stego, "wrong horse", true).is_err());
+        assert!(extract(&stego, 1, true).is_err());
+    }
+
+    #[test]
+    fn test_keyed_permutation_is_a_bijection() {
+        for len in [1, 2, 3, 7, 64, 100, 1000, 4099] {
+            let permutation = KeyedPermutation::new(len, 42);
+            let mut seen = vec![false; len];
+            for index in 0..len {
+                let image = permutation.index(index);
+                assert!(!seen[image], "len {} maps two indices to {}", len, image);
+                seen[image] = true;
+            }
+        }
+
+        // Different keys walk different paths
+        let a = KeyedPermutation::new(1000, 1);
+        let b = KeyedPermutation::new(1000, 2);
+        let differing = (0..1000).filter(|&i| a.index(i) != b.index(i)).count();
+        assert!(differing > 900);
+    }
+
+    #[test]
+    fn test_authenticated_round_trip() {
+        let cover = create_test_cover(100, 100);
+        let secret = b"Signed by someone holding the key";
+
+        let (stego, _info) = embed_authenticated(&cover, secret, 1, true, b"shared key").unwrap();
+        let recovered = extract_verified(&stego, 1, true, Some(b"shared key")).unwrap();
+        assert_eq!(secret.as_slice(), recovered.as_slice());
+
+        let wrong_key = extract_verified(&stego, 1, true, Some(b"other key"));
+        assert!(matches!(wrong_key, Err(StegoError::AuthenticationFailed(_))));
+
+        let no_key = extract(&stego, 1, true);
+        assert!(matches!(no_key, Err(StegoError::AuthenticationFailed(_))));
+    }
+
+    #[test]
+    fn test_tampered_payload_with_recomputed_crc_fails_authentication() {
+        let cover = create_test_cover(100, 100);
+        let (stego, _info) = embed_authenticated(&cover, b"pay alice 10", 1, false, b"k").unwrap();
+
+        // Forge a payload of the same length and patch the CRC to match
+        let mut raw = stego.to_rgb8();
+        let forged = b"pay mallory!";
+        let frame_bits: Vec<u8> = raw.as_raw()[..(12 + 32 + forged.len()) * 8]
+            .iter()
+            .map(|v| v & 1)
+            .collect();
+        let mut frame = bits_to_bytes(&frame_bits);
+        frame[8..12].copy_from_slice(&crc32fast::hash(forged).to_be_bytes());
+        frame[44..].copy_from_slice(forged);
+        for (i, sample) in raw.iter_mut().take(frame.len() * 8).enumerate() {
+            *sample = (*sample & !1) | ((frame[i / 8] >> (7 - i % 8)) & 1);
+        }
+
+        let result = extract_verified(&DynamicImage::ImageRgb8(raw), 1, false, Some(b"k"));
+        assert!(matches!(result, Err(StegoError::AuthenticationFailed(_))));
+    }
+
+    #[test]
+    fn test_plain_payload_rejected_when_key_expected() {
+        let cover = create_test_cover(100, 100);
+        let (stego, _info) = embed(&cover, b"unsigned", 1, false).unwrap();
+
+        let result = extract_verified(&stego, 1, false, Some(b"k"));
+        assert!(matches!(result, Err(StegoError::AuthenticationFailed(_))));
+    }
+
+    #[test]
+    fn test_stream_matches_buffered_embed() {
+        let cover = create_test_cover(200, 200);
+        let secret = b"Streamed secret, compressed on the fly. ".repeat(50);
+
+        for mac_key in [None, Some(b"k".as_slice())] {
+            let (streamed, _info) =
+                embed_stream(&cover, secret.as_slice(), 1, true, mac_key).unwrap();
+            let (buffered, _info) = match mac_key {
+                Some(key) => embed_authenticated(&cover, &secret, 1, true, key).unwrap(),
+                None => embed(&cover, &secret, 1, true).unwrap(),
+            };
+            assert_eq!(streamed.to_rgb8().as_raw(), buffered.to_rgb8().as_raw());
+
+            let mut recovered = Vec::new();
+            let written = extract_stream(&streamed, 1, true, mac_key, &mut recovered).unwrap();
+            assert_eq!(written, secret.len() as u64);
+            assert_eq!(recovered, secret);
+        }
+    }
+
+    #[test]
+    fn test_stream_capacity_exceeded() {
+        let cover = create_test_cover(10, 10);
+        let secret = vec![0xA5u8; 1000];
+
+        let result = embed_stream(&cover, secret.as_slice(), 1, false, None);
+        assert!(matches!(
+            result,
+            Err(StegoError::CapacityExceeded { needed: 1012, .. })
+        ));
+    }
+
+    #[test]
+    fn test_stream_writes_nothing_on_crc_mismatch() {
+        let cover = create_test_cover(100, 100);
+        let (stego, _info) = embed_stream(&cover, b"corrupt me".as_slice(), 1, false, None).unwrap();
+
+        let mut raw = stego.to_rgb8();
+        raw.as_mut()[12 * 8] ^= 1; // first payload bit
+
+        let mut recovered = Vec::new();
+        let result = extract_stream(&DynamicImage::ImageRgb8(raw), 1, false, None, &mut recovered);
+        assert!(matches!(result, Err(StegoError::CrcMismatch { .. })));
+        assert!(recovered.is_empty());
+    }
+
+    #[test]
+    fn test_stream_multi_bit_uses_extra_capacity() {
+        let cover = create_test_cover(20, 20);
+        let secret = vec![0x3Cu8; 200]; // over the 1-bit capacity of 150 bytes
+
+        assert!(embed_stream(&cover, secret.as_slice(), 1, false, None).is_err());
+        let (stego, info) = embed_stream(&cover, secret.as_slice(), 2, false, None).unwrap();
+        assert_eq!(info.capacity_bytes, 300);
+
+        let mut recovered = Vec::new();
+        extract_stream(&stego, 2, false, None, &mut recovered).unwrap();
+        assert_eq!(recovered, secret);
+        assert!(extract_stream(&stego, 1, false, None, &mut Vec::new()).is_err());
+
+        assert!(matches!(
+            embed_stream(&cover, secret.as_slice(), 0, false, None),
+            Err(StegoError::UnsupportedOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_multi_bit_round_trip_uses_extra_capacity() {
+        let cover = create_test_cover(20, 20);
+        let secret = vec![0x3Cu8; 200]; // over the 1-bit capacity of 150 bytes
+
+        assert!(embed(&cover, &secret, 1, false).is_err());
+        let (stego, info) = embed(&cover, &secret, 2, false).unwrap();
+        assert_eq!(info.capacity_bytes, 300);
+        assert_eq!(extract(&stego, 2, false).unwrap(), secret);
+        assert!(extract(&stego, 1, false).is_err());
+
+        let report = crate::probe(&stego);
+        assert_eq!(report.candidate.unwrap().lsb_per_channel, 2);
+
+        assert!(matches!(embed(&cover, &secret, 0, false), Err(StegoError::UnsupportedOptions(_))));
+        assert!(matches!(extract(&stego, 9, false), Err(StegoError::UnsupportedOptions(_))));
+    }
+
+    #[test]
+    fn test_options_combine_traversal_key_and_mac() {
+        let cover = create_test_cover(100, 100);
+        let secret = b"Keyed and signed";
+
+        let options = EmbedOptions::new().traversal_key("pass").mac_key(b"k".as_slice());
+        let (stego, _info, _) = embed_with(&cover, secret, &options).unwrap();
+
+        let keyed = ExtractOptions::new().traversal_key("pass");
+        assert_eq!(extract_with(&stego, &keyed.clone().mac_key(b"k".as_slice())).unwrap(), secret);
+        assert!(extract_with(&stego, &keyed.mac_key(b"other".as_slice())).is_err());
+
+        let masked = options.mask(EmbedMask::new());
+        assert!(matches!(
+            embed_with(&cover, secret, &masked),
+            Err(StegoError::InvalidMask(_))
+        ));
+    }
+
+    #[test]
+    fn test_embed_with_reports_frame_stats() {
+        let cover = create_test_cover(100, 100);
+        let secret = b"stats ".repeat(100);
+
+        let plain = EmbedOptions::new().compression(crate::options::Compression::None);
+        let (_, _, stats) = embed_with(&cover, &secret, &plain).unwrap();
+        assert_eq!(stats.payload_bytes, secret.len() as u64);
+        assert_eq!(stats.frame_bytes, (crate::frame::HEADER_LEN + secret.len()) as u64);
+
+        // Encryption and ECC grow the frame, not the compressed payload
+        let compressed = EmbedOptions::new().traversal_key("pass");
+        let (_, _, small) = embed_with(&cover, &secret, &compressed).unwrap();
+        let sealed_options = compressed.encryption("key").ecc(Ecc::Repeat3);
+        let (_, _, sealed) = embed_with(&cover, &secret, &sealed_options).unwrap();
+        assert!(small.payload_bytes < secret.len() as u64 / 4);
+        assert_eq!(sealed.payload_bytes, small.payload_bytes);
+        assert!(sealed.frame_bytes > 3 * small.frame_bytes);
+    }
+
+    #[test]
+    fn test_options_reject_multi_bit_keyed_and_masked() {
+        let cover = create_test_cover(100, 100);
+
+        let keyed = EmbedOptions::new().lsb_per_channel(2).traversal_key("pass");
+        let masked = EmbedOptions::new().lsb_per_channel(2).mask(EmbedMask::new());
+        for options in [keyed, masked] {
+            assert!(matches!(
+                embed_with(&cover, b"secret", &options),
+                Err(StegoError::UnsupportedOptions(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_invalid_magic() {
         let mut img = ImageBuffer::from_pixel(100, 100, Rgb([128, 128, 128]));
@@ -289,4 +948,52 @@ mod tests {
         let result = extract(&stego, 1, false);
         assert!(matches!(result, Err(StegoError::InvalidMagic(_))));
     }
+
+    #[test]
+    fn test_options_encryption_and_ecc_on_every_path() {
+        let cover = create_test_cover(120, 120);
+        let secret = b"sealed and repeated".repeat(4);
+
+        let base = EmbedOptions::new().encryption("sealing pass").ecc(Ecc::Repeat3);
+        let paths = [
+            (base.clone(), ExtractOptions::new()),
+            (
+                base.clone().traversal_key("path pass"),
+                ExtractOptions::new().traversal_key("path pass"),
+            ),
+            (
+                base.clone().mask(EmbedMask::new().channels(ChannelMask::GREEN)),
+                ExtractOptions::new(),
+            ),
+        ];
+
+        for (embed_options, extract_options) in paths {
+            let extract_options = extract_options.encryption("sealing pass").ecc(Ecc::Repeat3);
+            let (stego, _info, _) = embed_with(&cover, &secret, &embed_options).unwrap();
+            assert_eq!(extract_with(&stego, &extract_options).unwrap(), secret);
+
+            let wrong = extract_options.clone().encryption("other pass");
+            assert!(extract_with(&stego, &wrong).is_err());
+        }
+    }
+
+    #[test]
+    fn test_options_ecc_repairs_flipped_lsbs() {
+        let cover = create_test_cover(100, 100);
+        let secret = b"survives a few flipped bits";
+        let embed_options = EmbedOptions::new().ecc(Ecc::Repeat3);
+        let (stego, _info, _) = embed_with(&cover, secret, &embed_options).unwrap();
+
+        // Flip one LSB in the first copy of every frame byte, header included
+        let mut raw = stego.to_rgb8();
+        let frame_len = embed_options.required_capacity(secret).unwrap() as usize / 3;
+        for byte in 0..frame_len {
+            raw.as_mut()[byte * 24 + byte % 8] ^= 1;
+        }
+        let damaged = DynamicImage::ImageRgb8(raw);
+
+        assert!(extract(&damaged, 1, true).is_err());
+        let extract_options = ExtractOptions::new().ecc(Ecc::Repeat3);
+        assert_eq!(extract_with(&damaged, &extract_options).unwrap(), secret);
+    }
 }
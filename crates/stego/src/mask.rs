@@ -0,0 +1,438 @@
+use crate::error::{Result, StegoError};
+use image::{GrayImage, RgbImage};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Cells per side of the grid region masks are quantised to
+pub const MASK_GRID: u32 = 16;
+
+pub(crate) const MASK_MAGIC: u32 = 0x5354474D; // "STGM"
+
+const GRID_BYTES: usize = (MASK_GRID * MASK_GRID / 8) as usize;
+
+/// Serialized mask: magic(4) + channels(1) + excluded-cell bitmap(32)
+const DESCRIPTOR_LEN: usize = 4 + 1 + GRID_BYTES;
+
+/// Pixels at each end of the image that may hold the descriptor, one bit per pixel
+pub(crate) const ANCHOR_PIXELS: usize = DESCRIPTOR_LEN * 8;
+
+/// Colour channels allowed to carry payload bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMask(u8);
+
+impl ChannelMask {
+    pub const ALL: Self = Self(0b111);
+    pub const RED: Self = Self(0b001);
+    pub const GREEN: Self = Self(0b010);
+    pub const BLUE: Self = Self(0b100);
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Whether channel `index` (0 = R, 1 = G, 2 = B) is selected
+    pub fn contains(self, index: usize) -> bool {
+        self.0 & (1 << index) != 0
+    }
+
+    pub fn count(self) -> u8 {
+        self.0.count_ones() as u8
+    }
+
+    fn first(self) -> usize {
+        self.0.trailing_zeros() as usize
+    }
+}
+
+impl FromStr for ChannelMask {
+    type Err = StegoError;
+
+    /// Accepts `all`, letters (`b`, `rg`) or names (`blue`, `red,green`)
+    fn from_str(s: &str) -> Result<Self> {
+        let mut mask = 0u8;
+        for token in s.split(',').map(|t| t.trim().to_ascii_lowercase()) {
+            match token.as_str() {
+                "all" => mask |= Self::ALL.0,
+                "red" => mask |= Self::RED.0,
+                "green" => mask |= Self::GREEN.0,
+                "blue" => mask |= Self::BLUE.0,
+                letters if !letters.is_empty() => {
+                    for c in letters.chars() {
+                        mask |= match c {
+                            'r' => Self::RED.0,
+                            'g' => Self::GREEN.0,
+                            'b' => Self::BLUE.0,
+                            _ => {
+                                return Err(StegoError::InvalidMask(format!(
+                                    "unknown channel selection {:?}",
+                                    s
+                                )))
+                            }
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if mask == 0 {
+            return Err(StegoError::InvalidMask(
+                "channel selection is empty".to_string(),
+            ));
+        }
+        Ok(Self(mask))
+    }
+}
+
+/// Pixel rectangle to leave untouched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Rect {
+    type Err = StegoError;
+
+    /// Parses `x,y,width,height`
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<u32> = s
+            .split(',')
+            .map(|p| p.trim().parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| StegoError::InvalidMask(format!("invalid rectangle {:?}", s)))?;
+
+        match parts[..] {
+            [x, y, width, height] => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(StegoError::InvalidMask(format!(
+                "rectangle must be x,y,width,height, got {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Regions and channels to keep untouched when embedding.
+///
+/// Regions are quantised to a `MASK_GRID` x `MASK_GRID` cell grid (a cell is
+/// skipped if any of its pixels is protected), so the whole mask fits in a
+/// 37-byte descriptor that `extract` reads back to walk the same positions.
+#[derive(Debug, Clone)]
+pub struct EmbedMask {
+    channels: ChannelMask,
+    regions: Vec<Rect>,
+    mask_image: Option<GrayImage>,
+}
+
+impl Default for EmbedMask {
+    fn default() -> Self {
+        Self {
+            channels: ChannelMask::ALL,
+            regions: Vec::new(),
+            mask_image: None,
+        }
+    }
+}
+
+impl EmbedMask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn channels(mut self, channels: ChannelMask) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    pub fn exclude(mut self, rect: Rect) -> Self {
+        self.regions.push(rect);
+        self
+    }
+
+    /// Protect pixels where `mask` is bright (> 127); the mask is scaled to the cover
+    pub fn exclude_mask_image(mut self, mask: GrayImage) -> Self {
+        self.mask_image = Some(mask);
+        self
+    }
+
+    pub(crate) fn rasterize(&self, width: u32, height: u32) -> Result<MaskGrid> {
+        let mut grid = MaskGrid {
+            channels: self.channels,
+            excluded: [0; GRID_BYTES],
+        };
+
+        for rect in &self.regions {
+            let x_end = rect.x.saturating_add(rect.width).min(width);
+            let y_end = rect.y.saturating_add(rect.height).min(height);
+            if rect.x >= x_end || rect.y >= y_end {
+                return Err(StegoError::InvalidMask(format!(
+                    "rectangle {:?} lies outside the {}x{} cover",
+                    rect, width, height
+                )));
+            }
+
+            let (cx0, cy0) = MaskGrid::cell_xy(rect.x, rect.y, width, height);
+            let (cx1, cy1) = MaskGrid::cell_xy(x_end - 1, y_end - 1, width, height);
+            for cy in cy0..=cy1 {
+                for cx in cx0..=cx1 {
+                    grid.exclude_cell(cy * MASK_GRID + cx);
+                }
+            }
+        }
+
+        if let Some(mask) = &self.mask_image {
+            let (mw, mh) = mask.dimensions();
+            if mw == 0 || mh == 0 {
+                return Err(StegoError::InvalidMask("mask image is empty".to_string()));
+            }
+            for y in 0..height {
+                let my = (y as u64 * mh as u64 / height as u64) as u32;
+                for x in 0..width {
+                    let mx = (x as u64 * mw as u64 / width as u64) as u32;
+                    if mask.get_pixel(mx, my)[0] > 127 {
+                        let (cx, cy) = MaskGrid::cell_xy(x, y, width, height);
+                        grid.exclude_cell(cy * MASK_GRID + cx);
+                    }
+                }
+            }
+        }
+
+        Ok(grid)
+    }
+}
+
+/// Mask quantised to the cell grid, as stored in the descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MaskGrid {
+    channels: ChannelMask,
+    excluded: [u8; GRID_BYTES],
+}
+
+impl MaskGrid {
+    fn cell_xy(x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        (
+            (x as u64 * MASK_GRID as u64 / width as u64) as u32,
+            (y as u64 * MASK_GRID as u64 / height as u64) as u32,
+        )
+    }
+
+    fn exclude_cell(&mut self, cell: u32) {
+        self.excluded[cell as usize / 8] |= 1 << (cell % 8);
+    }
+
+    fn pixel_excluded(&self, pixel: usize, width: u32, height: u32) -> bool {
+        let (x, y) = (
+            (pixel % width as usize) as u32,
+            (pixel / width as usize) as u32,
+        );
+        let (cx, cy) = Self::cell_xy(x, y, width, height);
+        let cell = cy * MASK_GRID + cx;
+        self.excluded[cell as usize / 8] & (1 << (cell % 8)) != 0
+    }
+
+    pub(crate) fn channel_count(&self) -> u8 {
+        self.channels.count()
+    }
+
+    /// First anchor (start, then end of the image) whose pixels are all unmasked
+    pub(crate) fn anchor(&self, width: u32, height: u32) -> Option<Range<usize>> {
+        anchors(width, height)
+            .into_iter()
+            .find(|range| !range.clone().any(|p| self.pixel_excluded(p, width, height)))
+    }
+
+    /// Raw sample indices that carry the frame, in raster order
+    pub(crate) fn slots(&self, width: u32, height: u32, anchor: &Range<usize>) -> Vec<usize> {
+        let total = width as usize * height as usize;
+        (0..total)
+            .filter(|p| !anchor.contains(p) && !self.pixel_excluded(*p, width, height))
+            .flat_map(|p| (0..3).map(move |c| (p, c)))
+            .filter(|&(_, c)| self.channels.contains(c))
+            .map(|(p, c)| p * 3 + c)
+            .collect()
+    }
+
+    /// Store the descriptor in the LSBs of the first selected channel of `anchor`
+    pub(crate) fn write_descriptor(&self, rgb_img: &mut RgbImage, anchor: &Range<usize>) {
+        let mut bytes = Vec::with_capacity(DESCRIPTOR_LEN);
+        bytes.extend_from_slice(&MASK_MAGIC.to_be_bytes());
+        bytes.push(self.channels.0);
+        bytes.extend_from_slice(&self.excluded);
+
+        let channel = self.channels.first();
+        let raw: &mut [u8] = rgb_img;
+        for (i, pixel) in anchor.clone().enumerate() {
+            let bit = (bytes[i / 8] >> (7 - i % 8)) & 1;
+            let slot = pixel * 3 + channel;
+            raw[slot] = (raw[slot] & !1) | bit;
+        }
+    }
+
+    /// Find a descriptor written by `write_descriptor`, returning it with its anchor
+    pub(crate) fn find(rgb_img: &RgbImage) -> Option<(Self, Range<usize>)> {
+        let (width, height) = rgb_img.dimensions();
+        let raw = rgb_img.as_raw();
+
+        for anchor in anchors(width, height) {
+            for channel in 0..3 {
+                let read = |len: usize| -> Vec<u8> {
+                    let bits: Vec<u8> = anchor
+                        .clone()
+                        .take(len * 8)
+                        .map(|pixel| raw[pixel * 3 + channel] & 1)
+                        .collect();
+                    crate::lsb::bits_to_bytes(&bits)
+                };
+
+                if read(4) != MASK_MAGIC.to_be_bytes() {
+                    continue;
+                }
+                let bytes = read(DESCRIPTOR_LEN);
+                let channels = ChannelMask(bytes[4]);
+                if channels.0 == 0 || channels.0 & !ChannelMask::ALL.0 != 0 {
+                    continue;
+                }
+                if channels.first() != channel {
+                    continue;
+                }
+
+                let mut excluded = [0u8; GRID_BYTES];
+                excluded.copy_from_slice(&bytes[5..]);
+                return Some((Self { channels, excluded }, anchor));
+            }
+        }
+        None
+    }
+}
+
+/// Candidate descriptor locations: the first and the last `ANCHOR_PIXELS` pixels
+fn anchors(width: u32, height: u32) -> Vec<Range<usize>> {
+    let total = width as usize * height as usize;
+    if total < ANCHOR_PIXELS * 2 {
+        return Vec::new();
+    }
+    vec![0..ANCHOR_PIXELS, total - ANCHOR_PIXELS..total]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsb::{embed_masked, extract, extract_verified, masked_capacity};
+    use image::{DynamicImage, ImageBuffer, Luma, Rgb};
+
+    fn create_test_cover(width: u32, height: u32) -> DynamicImage {
+        let img = ImageBuffer::from_fn(width, height, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        });
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn test_parse_channels_and_rects() {
+        assert_eq!("b".parse::<ChannelMask>().unwrap(), ChannelMask::BLUE);
+        assert_eq!(
+            "red, green".parse::<ChannelMask>().unwrap(),
+            ChannelMask::RED.union(ChannelMask::GREEN)
+        );
+        assert_eq!("all".parse::<ChannelMask>().unwrap(), ChannelMask::ALL);
+        assert!("x".parse::<ChannelMask>().is_err());
+        assert!("".parse::<ChannelMask>().is_err());
+
+        let rect: Rect = "10, 20, 30, 40".parse().unwrap();
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (10, 20, 30, 40));
+        assert!("1,2,3".parse::<Rect>().is_err());
+    }
+
+    #[test]
+    fn test_masked_round_trip_leaves_protected_pixels() {
+        let cover = create_test_cover(160, 160);
+        let face = Rect {
+            x: 40,
+            y: 40,
+            width: 50,
+            height: 50,
+        };
+        let mask = EmbedMask::new().channels(ChannelMask::BLUE).exclude(face);
+        let secret = b"Only the blue channel outside the face".repeat(8);
+
+        let (stego, info) = embed_masked(&cover, &secret, false, &mask, None).unwrap();
+        assert_eq!(info.channels, 1);
+        assert_eq!(
+            info.capacity_bytes,
+            masked_capacity(160, 160, &mask).unwrap()
+        );
+        assert!(info.capacity_bytes < crate::lsb::capacity_bytes(160, 160, 1) / 3);
+
+        let before = cover.to_rgb8();
+        let after = stego.to_rgb8();
+        for (x, y, pixel) in after.enumerate_pixels() {
+            let original = before.get_pixel(x, y);
+            assert_eq!(pixel[0], original[0]);
+            assert_eq!(pixel[1], original[1]);
+            if (40..90).contains(&x) && (40..90).contains(&y) {
+                assert_eq!(pixel, original);
+            }
+        }
+
+        assert_eq!(extract(&stego, 1, false).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_descriptor_moves_to_end_when_start_is_protected() {
+        let cover = create_test_cover(128, 128);
+        let mask = EmbedMask::new().exclude(Rect {
+            x: 0,
+            y: 0,
+            width: 128,
+            height: 4,
+        });
+
+        let (stego, _info) =
+            embed_masked(&cover, b"bottom anchored", true, &mask, Some(b"k")).unwrap();
+        assert_eq!(
+            &stego.to_rgb8().as_raw()[..128 * 4 * 3],
+            &cover.to_rgb8().as_raw()[..128 * 4 * 3]
+        );
+        assert_eq!(
+            extract_verified(&stego, 1, true, Some(b"k")).unwrap(),
+            b"bottom anchored"
+        );
+    }
+
+    #[test]
+    fn test_mask_image_and_invalid_masks() {
+        // Protect the whole top half through a half-resolution mask image
+        let mask_image = ImageBuffer::from_fn(32, 32, |_, y| Luma([if y < 16 { 255 } else { 0 }]));
+        let mask = EmbedMask::new().exclude_mask_image(mask_image);
+        let capacity = masked_capacity(64, 64, &mask).unwrap();
+        let full = crate::lsb::capacity_bytes(64, 64, 1);
+        assert!(capacity < full / 2 + 1 && capacity > full / 3);
+
+        let outside = EmbedMask::new().exclude(Rect {
+            x: 100,
+            y: 0,
+            width: 5,
+            height: 5,
+        });
+        assert!(matches!(
+            masked_capacity(64, 64, &outside),
+            Err(StegoError::InvalidMask(_))
+        ));
+
+        let everything = EmbedMask::new().exclude(Rect {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64,
+        });
+        let result = embed_masked(&create_test_cover(64, 64), b"x", false, &everything, None);
+        assert!(matches!(result, Err(StegoError::InvalidMask(_))));
+    }
+}
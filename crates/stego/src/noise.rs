@@ -0,0 +1,233 @@
+use image::{DynamicImage, ImageBuffer, Rgb};
+
+/// Small deterministic PRNG (SplitMix64) so covers are reproducible from a seed
+pub(crate) struct SeededRng(u64);
+
+impl SeededRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Standard normal sample (Box-Muller)
+    pub(crate) fn next_gaussian(&mut self) -> f32 {
+        let u1 = self.next_f32().max(f32::MIN_POSITIVE);
+        let u2 = self.next_f32();
+        (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+    }
+}
+
+/// Classic 2D Perlin gradient noise with a seeded permutation table
+struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    fn new(rng: &mut SeededRng) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        for i in (1..table.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        let mut perm = [0u8; 512];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = table[i & 255];
+        }
+        Self { perm }
+    }
+
+    /// Noise value in roughly [-1, 1]
+    fn noise(&self, x: f32, y: f32) -> f32 {
+        let xi = x.floor() as i32 & 255;
+        let yi = y.floor() as i32 & 255;
+        let xf = x - x.floor();
+        let yf = y - y.floor();
+
+        let u = fade(xf);
+        let v = fade(yf);
+
+        let p = &self.perm;
+        let aa = p[p[xi as usize] as usize + yi as usize];
+        let ab = p[p[xi as usize] as usize + yi as usize + 1];
+        let ba = p[p[xi as usize + 1] as usize + yi as usize];
+        let bb = p[p[xi as usize + 1] as usize + yi as usize + 1];
+
+        let x1 = lerp(grad(aa, xf, yf), grad(ba, xf - 1.0, yf), u);
+        let x2 = lerp(grad(ab, xf, yf - 1.0), grad(bb, xf - 1.0, yf - 1.0), u);
+        lerp(x1, x2, v)
+    }
+
+    /// Fractal Brownian motion: summed octaves of noise, normalised to [-1, 1]
+    fn fbm(&self, x: f32, y: f32, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut norm = 0.0;
+
+        for _ in 0..octaves {
+            sum += self.noise(x * frequency, y * frequency) * amplitude;
+            norm += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+
+        sum / norm
+    }
+
+    /// Ridged multifractal noise in [0, 1], good for rock/bark-like texture
+    fn ridged(&self, x: f32, y: f32, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 0.5;
+        let mut frequency = 1.0;
+
+        for _ in 0..octaves {
+            let n = 1.0 - self.noise(x * frequency, y * frequency).abs();
+            sum += n * n * amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+
+        sum
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+/// Generate a photographic-looking cover from seeded fractal noise.
+///
+/// Layers a domain-warped low-frequency "scene", a seeded colour palette,
+/// ridged high-frequency texture, a soft vignette and simulated sensor
+/// noise (read noise plus luminance-dependent shot noise), so that LSB
+/// changes are masked by the natural variation of every pixel.
+pub fn generate_natural_cover(width: u32, height: u32, seed: u64) -> DynamicImage {
+    let mut rng = SeededRng::new(seed);
+    let scene = Perlin::new(&mut rng);
+    let warp = Perlin::new(&mut rng);
+    let detail = Perlin::new(&mut rng);
+
+    // Palette: three seeded anchor colours (shadows, midtones, highlights)
+    let mut palette = [[0.0f32; 3]; 3];
+    for (level, colour) in palette.iter_mut().enumerate() {
+        let base = 0.15 + 0.35 * level as f32;
+        for c in colour.iter_mut() {
+            *c = (base + (rng.next_f32() - 0.5) * 0.4).clamp(0.0, 1.0);
+        }
+    }
+
+    let scale = 3.0 / width.max(height).max(1) as f32;
+    let offset_x = rng.next_f32() * 256.0;
+    let offset_y = rng.next_f32() * 256.0;
+    let read_noise = 1.5 + rng.next_f32();
+
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let max_dist = (cx * cx + cy * cy).sqrt().max(1.0);
+
+    let img = ImageBuffer::from_fn(width, height, |x, y| {
+        let nx = x as f32 * scale + offset_x;
+        let ny = y as f32 * scale + offset_y;
+
+        // Domain warping gives organic, non-grid-aligned shapes
+        let wx = warp.fbm(nx, ny, 4);
+        let wy = warp.fbm(nx + 5.2, ny + 1.3, 4);
+        let base = scene.fbm(nx + 1.5 * wx, ny + 1.5 * wy, 6) * 0.5 + 0.5;
+
+        // Fine texture and a separate field for local hue shifts
+        let texture = detail.ridged(nx * 24.0, ny * 24.0, 4);
+        let tint = detail.fbm(nx * 0.7 + 11.0, ny * 0.7 + 7.0, 3);
+
+        let dx = x as f32 - cx;
+        let dy = y as f32 - cy;
+        let vignette = 1.0 - 0.35 * ((dx * dx + dy * dy).sqrt() / max_dist).powi(2);
+
+        let level = (base * 2.0).clamp(0.0, 2.0);
+        let (lo, hi, t) = if level < 1.0 {
+            (palette[0], palette[1], level)
+        } else {
+            (palette[1], palette[2], level - 1.0)
+        };
+
+        let mut pixel = [0u8; 3];
+        for c in 0..3 {
+            let shift = if c == 1 { 0.0 } else { tint * 0.08 * if c == 0 { 1.0 } else { -1.0 } };
+            let value = (lerp(lo[c], hi[c], t) + shift) * (0.8 + 0.35 * texture) * vignette;
+
+            let value = value.clamp(0.0, 1.0) * 255.0;
+            let shot_noise = value.sqrt() * 0.25;
+            let noisy = value + rng.next_gaussian() * (read_noise + shot_noise);
+            pixel[c] = noisy.round().clamp(0.0, 255.0) as u8;
+        }
+
+        Rgb(pixel)
+    });
+
+    DynamicImage::ImageRgb8(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    #[test]
+    fn test_natural_cover_is_deterministic() {
+        let a = generate_natural_cover(64, 48, 42);
+        let b = generate_natural_cover(64, 48, 42);
+        let c = generate_natural_cover(64, 48, 43);
+
+        assert_eq!(a.dimensions(), (64, 48));
+        assert_eq!(a.to_rgb8().as_raw(), b.to_rgb8().as_raw());
+        assert_ne!(a.to_rgb8().as_raw(), c.to_rgb8().as_raw());
+    }
+
+    #[test]
+    fn test_natural_cover_has_noisy_lsb_plane() {
+        let img = generate_natural_cover(128, 128, 7).to_rgb8();
+        let raw = img.as_raw();
+
+        // LSBs of a textured, noisy cover should be close to a fair coin
+        let ones = raw.iter().filter(|&&v| v & 1 == 1).count() as f64;
+        let ratio = ones / raw.len() as f64;
+        assert!((0.45..0.55).contains(&ratio), "LSB ratio {}", ratio);
+
+        // Horizontally adjacent samples should rarely be identical
+        let equal_neighbours = raw
+            .chunks(3)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .filter(|w| w[0] == w[1])
+            .count();
+        assert!(equal_neighbours < raw.len() / 3 / 20);
+    }
+}
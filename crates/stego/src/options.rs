@@ -0,0 +1,315 @@
+use crate::crypto::{open, seal};
+use crate::error::Result;
+use crate::frame::{build_frame, compress_data, decompress_data, read_frame};
+use crate::mask::EmbedMask;
+
+/// Codec applied to the secret before it is framed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Raw DEFLATE (flate2, default level)
+    #[default]
+    Deflate,
+}
+
+impl Compression {
+    pub(crate) fn enabled(self) -> bool {
+        self == Compression::Deflate
+    }
+}
+
+impl From<bool> for Compression {
+    fn from(compress: bool) -> Self {
+        if compress {
+            Compression::Deflate
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Forward error correction over the whole frame, header included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Ecc {
+    #[default]
+    None,
+    /// Every frame byte is written three times in a row and read back by a
+    /// bitwise majority vote, correcting any bit flipped in one of the three
+    /// copies at three times the capacity. Isolated flips (re-quantised or
+    /// lightly edited pixels) are repaired; damage spanning neighbouring
+    /// samples is not.
+    Repeat3,
+}
+
+impl Ecc {
+    /// Carrier bytes used per frame byte
+    pub(crate) fn factor(self) -> usize {
+        match self {
+            Ecc::None => 1,
+            Ecc::Repeat3 => 3,
+        }
+    }
+
+    pub(crate) fn encode(self, frame: Vec<u8>) -> Vec<u8> {
+        match self {
+            Ecc::None => frame,
+            Ecc::Repeat3 => frame.iter().flat_map(|&byte| [byte; 3]).collect(),
+        }
+    }
+
+    pub(crate) fn decode(self, encoded: Vec<u8>) -> Vec<u8> {
+        match self {
+            Ecc::None => encoded,
+            Ecc::Repeat3 => encoded
+                .chunks_exact(3)
+                .map(|c| (c[0] & c[1]) | (c[0] & c[2]) | (c[1] & c[2]))
+                .collect(),
+        }
+    }
+}
+
+/// How `embed_with` lays a secret into a cover.
+///
+/// Defaults to 1 LSB per channel, DEFLATE, no encryption, sequential
+/// traversal over every channel and a plain CRC frame without ECC; `common`
+/// converts the server configuration with `From<&StegoConfig>`.
+#[derive(Debug, Clone)]
+pub struct EmbedOptions {
+    pub lsb_per_channel: u8,
+    pub compression: Compression,
+    pub traversal_key: Option<String>,
+    /// Passphrase the (compressed) payload is encrypted under
+    pub encryption_key: Option<String>,
+    pub mac_key: Option<Vec<u8>>,
+    pub mask: Option<EmbedMask>,
+    pub ecc: Ecc,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        Self {
+            lsb_per_channel: 1,
+            compression: Compression::default(),
+            traversal_key: None,
+            encryption_key: None,
+            mac_key: None,
+            mask: None,
+            ecc: Ecc::default(),
+        }
+    }
+}
+
+impl EmbedOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lsb_per_channel(mut self, lsb_per_channel: u8) -> Self {
+        self.lsb_per_channel = lsb_per_channel;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Scatter the frame along a passphrase-keyed path, whitened with a
+    /// passphrase-derived keystream (see `embed_keyed`)
+    pub fn traversal_key(mut self, passphrase: impl Into<String>) -> Self {
+        self.traversal_key = Some(passphrase.into());
+        self
+    }
+
+    /// Encrypt the compressed payload with ChaCha20-Poly1305 under a key
+    /// stretched from `passphrase`; the frame header stays readable
+    pub fn encryption(mut self, passphrase: impl Into<String>) -> Self {
+        self.encryption_key = Some(passphrase.into());
+        self
+    }
+
+    /// Append an HMAC-SHA256 tag keyed with `key` (see `embed_authenticated`)
+    pub fn mac_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.mac_key = Some(key.into());
+        self
+    }
+
+    /// Leave the regions and channels protected by `mask` untouched (see `embed_masked`)
+    pub fn mask(mut self, mask: EmbedMask) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn ecc(mut self, ecc: Ecc) -> Self {
+        self.ecc = ecc;
+        self
+    }
+
+    /// Number of carrier bytes the frame for `secret_bytes` will occupy
+    /// (header, HMAC tag if any, possibly compressed and encrypted payload,
+    /// times the ECC expansion)
+    pub fn required_capacity(&self, secret_bytes: &[u8]) -> Result<u64> {
+        Ok(self.encode_frame(secret_bytes)?.1.frame_bytes)
+    }
+
+    /// Carrier bytes for `secret_bytes`: compressed, encrypted, framed, then ECC-encoded
+    pub(crate) fn encode_frame(&self, secret_bytes: &[u8]) -> Result<(Vec<u8>, FrameStats)> {
+        let payload = if self.compression.enabled() {
+            compress_data(secret_bytes)?
+        } else {
+            secret_bytes.to_vec()
+        };
+        let mac_key = self.mac_key.as_deref();
+
+        let frame = match &self.encryption_key {
+            Some(passphrase) => build_frame(&seal(&payload, passphrase)?, false, mac_key)?,
+            None => build_frame(&payload, false, mac_key)?,
+        };
+        let frame = self.ecc.encode(frame);
+        let stats = FrameStats {
+            payload_bytes: payload.len() as u64,
+            frame_bytes: frame.len() as u64,
+        };
+        Ok((frame, stats))
+    }
+}
+
+/// Sizes of the frame an embed wrote, returned by `embed_with` and the
+/// container embedders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The secret after compression; encryption, header, tag and ECC excluded
+    pub payload_bytes: u64,
+    /// Carrier bytes the whole frame occupies
+    pub frame_bytes: u64,
+}
+
+/// How `extract_with` recovers a secret; must match the `EmbedOptions` used.
+///
+/// Region/channel masks need no option: their descriptor is detected in the image.
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub lsb_per_channel: u8,
+    pub compression: Compression,
+    pub traversal_key: Option<String>,
+    pub encryption_key: Option<String>,
+    pub mac_key: Option<Vec<u8>>,
+    pub ecc: Ecc,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            lsb_per_channel: 1,
+            compression: Compression::default(),
+            traversal_key: None,
+            encryption_key: None,
+            mac_key: None,
+            ecc: Ecc::default(),
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lsb_per_channel(mut self, lsb_per_channel: u8) -> Self {
+        self.lsb_per_channel = lsb_per_channel;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn traversal_key(mut self, passphrase: impl Into<String>) -> Self {
+        self.traversal_key = Some(passphrase.into());
+        self
+    }
+
+    pub fn encryption(mut self, passphrase: impl Into<String>) -> Self {
+        self.encryption_key = Some(passphrase.into());
+        self
+    }
+
+    /// Require a valid HMAC tag for `key`; untagged frames are rejected
+    pub fn mac_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.mac_key = Some(key.into());
+        self
+    }
+
+    pub fn ecc(mut self, ecc: Ecc) -> Self {
+        self.ecc = ecc;
+        self
+    }
+
+    /// Verify the frame stored in `available` carrier bytes through
+    /// `read(offset, len)`, undoing ECC, encryption and compression
+    pub(crate) fn read_payload(
+        &self,
+        mut read: impl FnMut(usize, usize) -> Vec<u8>,
+        available: usize,
+    ) -> Result<Vec<u8>> {
+        let factor = self.ecc.factor();
+        let compress = self.compression.enabled();
+        let payload = read_frame(
+            |offset, len| self.ecc.decode(read(offset * factor, len * factor)),
+            available / factor,
+            compress && self.encryption_key.is_none(),
+            self.mac_key.as_deref(),
+        )?;
+
+        match &self.encryption_key {
+            Some(passphrase) => {
+                let plaintext = open(&payload, passphrase)?;
+                if compress {
+                    decompress_data(&plaintext)
+                } else {
+                    Ok(plaintext)
+                }
+            }
+            None => Ok(payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_capacity_counts_mac_tag() {
+        let plain = EmbedOptions::new().compression(Compression::None);
+        let tagged = plain.clone().mac_key("k");
+
+        assert_eq!(plain.required_capacity(b"abc").unwrap(), 12 + 3);
+        assert_eq!(tagged.required_capacity(b"abc").unwrap(), 12 + 32 + 3);
+
+        let sealed = plain.clone().encryption("pass");
+        assert_eq!(sealed.required_capacity(b"abc").unwrap(), 12 + 44 + 3);
+        let repeated = tagged.ecc(Ecc::Repeat3);
+        assert_eq!(repeated.required_capacity(b"abc").unwrap(), 3 * (12 + 32 + 3));
+    }
+
+    #[test]
+    fn test_repeat3_corrects_one_copy_per_byte() {
+        let encoded = Ecc::Repeat3.encode(b"frame".to_vec());
+        assert_eq!(encoded.len(), 15);
+
+        // Each bit flipped in at most one of its copies
+        let mut damaged = encoded.clone();
+        for (i, byte) in damaged.iter_mut().enumerate() {
+            *byte ^= 1 << (i % 8);
+        }
+        assert_eq!(Ecc::Repeat3.decode(damaged), b"frame");
+
+        // The same bit flipped in two copies outvotes the third
+        let mut damaged = encoded;
+        damaged[0] ^= 0x01;
+        damaged[1] ^= 0x01;
+        assert_ne!(Ecc::Repeat3.decode(damaged), b"frame");
+    }
+}
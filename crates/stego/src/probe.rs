@@ -0,0 +1,456 @@
+use crate::container::{container_frame, Carrier};
+use crate::error::Result;
+use crate::frame::{compute_crc, FrameHeader, FrameSource, HEADER_LEN};
+use crate::lsb::{bits_to_bytes, masked_plane};
+use crate::sniff::{sniff, OCTET_STREAM};
+use flate2::{Decompress, FlushDecompress, Status};
+use image::DynamicImage;
+
+/// Highest LSB depth tried per channel
+const MAX_PROBE_LSB: u8 = 8;
+
+/// Refuse to inflate payloads beyond this size while probing (decompression bombs)
+const MAX_INFLATED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Prefixes of common encrypted container formats
+const ENCRYPTED_SIGNATURES: &[&[u8]] = &[
+    b"Salted__",
+    b"age-encryption.org/",
+    b"-----BEGIN AGE ENCRYPTED FILE-----",
+    b"-----BEGIN PGP MESSAGE-----",
+];
+
+/// Order in which a pixel's channels carry consecutive payload bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOrder {
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ChannelOrder {
+    pub const ALL: [ChannelOrder; 6] = [
+        ChannelOrder::Rgb,
+        ChannelOrder::Bgr,
+        ChannelOrder::Rbg,
+        ChannelOrder::Grb,
+        ChannelOrder::Gbr,
+        ChannelOrder::Brg,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelOrder::Rgb => "RGB",
+            ChannelOrder::Rbg => "RBG",
+            ChannelOrder::Grb => "GRB",
+            ChannelOrder::Gbr => "GBR",
+            ChannelOrder::Brg => "BRG",
+            ChannelOrder::Bgr => "BGR",
+        }
+    }
+
+    /// Index into an RGB pixel of the n-th channel in this order
+    fn indices(self) -> [usize; 3] {
+        match self {
+            ChannelOrder::Rgb => [0, 1, 2],
+            ChannelOrder::Rbg => [0, 2, 1],
+            ChannelOrder::Grb => [1, 0, 2],
+            ChannelOrder::Gbr => [1, 2, 0],
+            ChannelOrder::Brg => [2, 0, 1],
+            ChannelOrder::Bgr => [2, 1, 0],
+        }
+    }
+}
+
+/// A frame header found with one set of extraction parameters
+#[derive(Debug, Clone)]
+pub struct PayloadCandidate {
+    pub carrier: Carrier,
+    /// Bit depth of pixel carriers (0 for PNG chunk and PDF update containers)
+    pub lsb_per_channel: u8,
+    pub channel_order: ChannelOrder,
+    /// Header version: `true` for frames carrying an HMAC tag
+    pub authenticated: bool,
+    /// Frame follows a region/channel mask descriptor (`embed_masked`)
+    pub masked: bool,
+    /// Payload length declared by the header (after compression, if any)
+    pub declared_size_bytes: u64,
+    /// Whether the declared frame fits in the carrier at this bit depth
+    pub fits_carrier: bool,
+    pub crc_valid: bool,
+    /// Payload is a complete deflate stream
+    pub compressed: bool,
+    /// Payload (inflated if compressed) looks like ciphertext
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeReport {
+    /// A frame with a matching CRC was found
+    pub payload_present: bool,
+    /// Best match: CRC-valid first, then headers whose frame fits the carrier
+    pub candidate: Option<PayloadCandidate>,
+    /// Bit depth / channel order combinations tried, each against every header version
+    pub combinations_tried: usize,
+}
+
+/// Look for an embedded frame under every supported bit depth and channel order.
+///
+/// Only plain (`embed`) and authenticated frames can be detected; keyed and
+/// dual payloads are indistinguishable from noise without their passphrase.
+pub fn probe(image: &DynamicImage) -> ProbeReport {
+    let rgb_img = image.to_rgb8();
+    let raw = rgb_img.as_raw();
+
+    let mut best: Option<PayloadCandidate> = None;
+    let mut combinations_tried = 0;
+
+    if let Some(plane) = masked_plane(&rgb_img) {
+        let available = plane.capacity();
+        if available >= HEADER_LEN {
+            if let Some(header) = FrameHeader::parse(&plane.read_at(0, HEADER_LEN)) {
+                let mut candidate = inspect(&plane, (1, ChannelOrder::Rgb), &header, available);
+                candidate.masked = true;
+                if candidate.crc_valid {
+                    return ProbeReport {
+                        payload_present: true,
+                        candidate: Some(candidate),
+                        combinations_tried,
+                    };
+                }
+                best = Some(candidate);
+            }
+        }
+    }
+
+    for lsb in 1..=MAX_PROBE_LSB {
+        for order in ChannelOrder::ALL {
+            combinations_tried += 1;
+
+            let reader = SampleReader { raw, lsb, order };
+            let available = reader.capacity();
+            if available < HEADER_LEN {
+                continue;
+            }
+            let Some(header) = FrameHeader::parse(&reader.read_at(0, HEADER_LEN)) else {
+                continue;
+            };
+
+            let candidate = inspect(&reader, (lsb, order), &header, available);
+            if candidate.crc_valid {
+                return ProbeReport {
+                    payload_present: true,
+                    candidate: Some(candidate),
+                    combinations_tried,
+                };
+            }
+
+            let better = best
+                .as_ref()
+                .is_none_or(|b| candidate.fits_carrier && !b.fits_carrier);
+            if better {
+                best = Some(candidate);
+            }
+        }
+    }
+
+    ProbeReport {
+        payload_present: false,
+        candidate: best,
+        combinations_tried,
+    }
+}
+
+/// Probe an uploaded file: PNG chunk and PDF update containers first, then
+/// (if the file decodes as an image) its pixels as `probe` does.
+///
+/// Fails only if the file is neither a container with a frame nor an image.
+pub fn probe_file(file_bytes: &[u8]) -> Result<ProbeReport> {
+    let container = container_frame(file_bytes).and_then(|(carrier, frame)| {
+        let header = FrameHeader::parse(frame.get(..HEADER_LEN)?)?;
+        let mut candidate = inspect(&frame, (0, ChannelOrder::Rgb), &header, frame.len());
+        candidate.carrier = carrier;
+        Some(candidate)
+    });
+
+    if container.as_ref().is_some_and(|c| c.crc_valid) {
+        return Ok(ProbeReport {
+            payload_present: true,
+            candidate: container,
+            combinations_tried: 0,
+        });
+    }
+
+    match image::load_from_memory(file_bytes) {
+        Ok(img) => {
+            let mut report = probe(&img);
+            if report.candidate.is_none() {
+                report.candidate = container;
+            }
+            Ok(report)
+        }
+        Err(_) if container.is_some() => Ok(ProbeReport {
+            payload_present: false,
+            candidate: container,
+            combinations_tried: 0,
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn inspect(
+    reader: &impl FrameSource,
+    (lsb, order): (u8, ChannelOrder),
+    header: &FrameHeader,
+    available: usize,
+) -> PayloadCandidate {
+    let body_start = HEADER_LEN + header.tag_len();
+    let fits_carrier = body_start + header.payload_len <= available;
+
+    let mut candidate = PayloadCandidate {
+        carrier: Carrier::Pixels,
+        lsb_per_channel: lsb,
+        channel_order: order,
+        authenticated: header.authenticated,
+        masked: false,
+        declared_size_bytes: header.payload_len as u64,
+        fits_carrier,
+        crc_valid: false,
+        compressed: false,
+        encrypted: false,
+    };
+
+    if !fits_carrier {
+        return candidate;
+    }
+
+    let payload = reader.read_at(body_start, header.payload_len);
+    candidate.crc_valid = compute_crc(&payload) == header.crc;
+    if !candidate.crc_valid {
+        return candidate;
+    }
+
+    match inflate(&payload) {
+        Some(inflated) => {
+            candidate.compressed = true;
+            candidate.encrypted = looks_encrypted(&inflated);
+        }
+        None => candidate.encrypted = looks_encrypted(&payload),
+    }
+    candidate
+}
+
+/// Reads frame bytes from the top `lsb` bits of each sample, channels in `order`
+struct SampleReader<'a> {
+    raw: &'a [u8],
+    lsb: u8,
+    order: ChannelOrder,
+}
+
+impl FrameSource for SampleReader<'_> {
+    fn capacity(&self) -> usize {
+        self.raw.len() / 3 * 3 * self.lsb as usize / 8
+    }
+
+    fn read_at(&self, offset: usize, len: usize) -> Vec<u8> {
+        let lsb = self.lsb as usize;
+        let indices = self.order.indices();
+
+        let bits: Vec<u8> = (offset * 8..(offset + len) * 8)
+            .map(|bit_index| {
+                let sample = bit_index / lsb;
+                let slot = sample / 3 * 3 + indices[sample % 3];
+                let shift = lsb - 1 - (bit_index % lsb);
+                (self.raw[slot] >> shift) & 1
+            })
+            .collect();
+        bits_to_bytes(&bits)
+    }
+}
+
+/// Inflate a raw deflate stream, or `None` if it is not one complete stream
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut inflater = Decompress::new(false);
+    let mut out = Vec::with_capacity(data.len() * 2 + 64);
+
+    loop {
+        let consumed = inflater.total_in() as usize;
+        let produced = out.len();
+        let status = inflater
+            .decompress_vec(&data[consumed..], &mut out, FlushDecompress::Finish)
+            .ok()?;
+
+        match status {
+            Status::StreamEnd => return Some(out),
+            _ if out.len() == out.capacity() && out.len() < MAX_INFLATED_BYTES => {
+                out.reserve(out.len());
+            }
+            _ if inflater.total_in() as usize == consumed && out.len() == produced => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Ciphertext signature, or near-maximal byte entropy in an unrecognised format
+fn looks_encrypted(data: &[u8]) -> bool {
+    if ENCRYPTED_SIGNATURES.iter().any(|sig| data.starts_with(sig)) {
+        return true;
+    }
+
+    // Images and other compressed formats are high-entropy too
+    if data.len() < 32 || sniff(data) != OCTET_STREAM {
+        return false;
+    }
+
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+
+    // Normalise by the maximum entropy reachable with this many bytes
+    entropy / len.min(256.0).log2() > 0.9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::embed_png_chunk;
+    use crate::frame::build_frame;
+    use crate::lsb::{embed, embed_authenticated, embed_masked};
+    use crate::mask::{ChannelMask, EmbedMask};
+    use crate::options::EmbedOptions;
+    use image::{ImageBuffer, Rgb, RgbImage};
+
+    fn create_test_cover(width: u32, height: u32) -> DynamicImage {
+        let img = ImageBuffer::from_fn(width, height, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        });
+        DynamicImage::ImageRgb8(img)
+    }
+
+    /// Embed a frame the way other tools might: `lsb` bits per sample, custom channel order
+    fn embed_with(cover: &DynamicImage, frame: &[u8], lsb: u8, order: ChannelOrder) -> RgbImage {
+        let mut rgb_img = cover.to_rgb8();
+        let raw: &mut [u8] = &mut rgb_img;
+        let lsb = lsb as usize;
+        let indices = order.indices();
+
+        for bit_index in 0..frame.len() * 8 {
+            let bit = (frame[bit_index / 8] >> (7 - bit_index % 8)) & 1;
+            let sample = bit_index / lsb;
+            let slot = sample / 3 * 3 + indices[sample % 3];
+            let shift = lsb - 1 - (bit_index % lsb);
+            raw[slot] = (raw[slot] & !(1 << shift)) | (bit << shift);
+        }
+        rgb_img
+    }
+
+    #[test]
+    fn test_probe_finds_default_embedding() {
+        let cover = create_test_cover(100, 100);
+        let secret = b"Probe me if you can".repeat(5);
+
+        let (stego, _info) = embed(&cover, &secret, 1, true).unwrap();
+        let report = probe(&stego);
+
+        assert!(report.payload_present);
+        let candidate = report.candidate.unwrap();
+        assert_eq!(candidate.lsb_per_channel, 1);
+        assert_eq!(candidate.channel_order, ChannelOrder::Rgb);
+        assert!(candidate.compressed);
+        assert!(!candidate.authenticated);
+        assert!(!candidate.masked);
+        assert!(!candidate.encrypted);
+    }
+
+    #[test]
+    fn test_probe_finds_masked_embedding() {
+        let cover = create_test_cover(100, 100);
+        let mask = EmbedMask::new().channels(ChannelMask::BLUE);
+        let (stego, _info) = embed_masked(&cover, b"masked payload", false, &mask, None).unwrap();
+
+        let report = probe(&stego);
+        assert!(report.payload_present);
+        let candidate = report.candidate.unwrap();
+        assert!(candidate.masked);
+        assert_eq!(candidate.declared_size_bytes, 14);
+    }
+
+    #[test]
+    fn test_probe_other_depths_and_orders() {
+        let cover = create_test_cover(100, 100);
+        let secret: Vec<u8> = (0..200u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let frame = build_frame(&secret, false, Some(b"key")).unwrap();
+
+        let stego = DynamicImage::ImageRgb8(embed_with(&cover, &frame, 2, ChannelOrder::Bgr));
+        let report = probe(&stego);
+
+        assert!(report.payload_present);
+        let candidate = report.candidate.unwrap();
+        assert_eq!(candidate.lsb_per_channel, 2);
+        assert_eq!(candidate.channel_order, ChannelOrder::Bgr);
+        assert!(candidate.authenticated);
+        assert_eq!(candidate.declared_size_bytes, secret.len() as u64);
+        assert!(candidate.encrypted);
+    }
+
+    #[test]
+    fn test_probe_reports_damaged_payload() {
+        let cover = create_test_cover(100, 100);
+        let (stego, _info) = embed_authenticated(&cover, b"fragile", 1, false, b"k").unwrap();
+
+        // Flip a payload bit past the header and tag
+        let mut rgb_img = stego.to_rgb8();
+        let raw: &mut [u8] = &mut rgb_img;
+        raw[(HEADER_LEN + 32) * 8] ^= 1;
+
+        let report = probe(&DynamicImage::ImageRgb8(rgb_img));
+        assert!(!report.payload_present);
+        let candidate = report.candidate.unwrap();
+        assert!(candidate.fits_carrier);
+        assert!(!candidate.crc_valid);
+    }
+
+    #[test]
+    fn test_probe_clean_cover() {
+        let report = probe(&create_test_cover(64, 64));
+
+        assert!(!report.payload_present);
+        assert!(report.candidate.is_none());
+        assert_eq!(report.combinations_tried, 8 * 6);
+    }
+
+    #[test]
+    fn test_probe_file_finds_png_chunk() {
+        let mut png = Vec::new();
+        create_test_cover(32, 32)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let (stego, _) = embed_png_chunk(&png, b"in a chunk", &EmbedOptions::new()).unwrap();
+
+        let report = probe_file(&stego).unwrap();
+        assert!(report.payload_present);
+        let candidate = report.candidate.unwrap();
+        assert_eq!(candidate.carrier, Carrier::PngChunk);
+        assert!(candidate.compressed);
+        assert_eq!(report.combinations_tried, 0);
+
+        // Without the chunk the pixels are probed instead
+        let report = probe_file(&png).unwrap();
+        assert!(!report.payload_present);
+        assert_eq!(report.combinations_tried, 8 * 6);
+        assert!(probe_file(b"neither image nor container").is_err());
+    }
+}
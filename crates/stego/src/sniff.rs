@@ -0,0 +1,365 @@
+/// Detected content type of a recovered payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentType {
+    pub mime: &'static str,
+    /// Suggested file extension, without the dot
+    pub extension: &'static str,
+}
+
+impl ContentType {
+    const fn new(mime: &'static str, extension: &'static str) -> Self {
+        Self { mime, extension }
+    }
+}
+
+pub const OCTET_STREAM: ContentType = ContentType::new("application/octet-stream", "bin");
+
+/// Fixed magic bytes at an offset, most specific first
+const SIGNATURES: &[(usize, &[u8], ContentType)] = &[
+    // Images
+    (0, b"\x89PNG\r\n\x1a\n", ContentType::new("image/png", "png")),
+    (0, b"\xFF\xD8\xFF", ContentType::new("image/jpeg", "jpg")),
+    (0, b"GIF87a", ContentType::new("image/gif", "gif")),
+    (0, b"GIF89a", ContentType::new("image/gif", "gif")),
+    (0, b"II*\x00", ContentType::new("image/tiff", "tif")),
+    (0, b"MM\x00*", ContentType::new("image/tiff", "tif")),
+    (0, b"\x00\x00\x01\x00", ContentType::new("image/x-icon", "ico")),
+    (0, b"8BPS", ContentType::new("image/vnd.adobe.photoshop", "psd")),
+    (0, b"\x00\x00\x00\x0CjP  \r\n\x87\n", ContentType::new("image/jp2", "jp2")),
+    (0, b"BM", ContentType::new("image/bmp", "bmp")),
+    // Audio
+    (0, b"ID3", ContentType::new("audio/mpeg", "mp3")),
+    (0, b"fLaC", ContentType::new("audio/flac", "flac")),
+    (0, b"OggS", ContentType::new("audio/ogg", "ogg")),
+    (0, b"MThd", ContentType::new("audio/midi", "mid")),
+    (0, b"#!AMR", ContentType::new("audio/amr", "amr")),
+    // Video
+    (0, b"FLV\x01", ContentType::new("video/x-flv", "flv")),
+    // Documents
+    (0, b"%PDF-", ContentType::new("application/pdf", "pdf")),
+    (0, b"{\\rtf", ContentType::new("application/rtf", "rtf")),
+    (0, b"%!PS", ContentType::new("application/postscript", "ps")),
+    (0, b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1", ContentType::new("application/x-ole-storage", "doc")),
+    // Archives and compression
+    (0, b"\x1F\x8B", ContentType::new("application/gzip", "gz")),
+    (0, b"BZh", ContentType::new("application/x-bzip2", "bz2")),
+    (0, b"\xFD7zXZ\x00", ContentType::new("application/x-xz", "xz")),
+    (0, b"\x28\xB5\x2F\xFD", ContentType::new("application/zstd", "zst")),
+    (0, b"7z\xBC\xAF\x27\x1C", ContentType::new("application/x-7z-compressed", "7z")),
+    (0, b"Rar!\x1A\x07", ContentType::new("application/vnd.rar", "rar")),
+    (0, b"\x04\x22\x4D\x18", ContentType::new("application/x-lz4", "lz4")),
+    (257, b"ustar", ContentType::new("application/x-tar", "tar")),
+    (0, b"MSCF", ContentType::new("application/vnd.ms-cab-compressed", "cab")),
+    // Executables and binaries
+    (0, b"\x7FELF", ContentType::new("application/x-elf", "elf")),
+    (0, b"MZ", ContentType::new("application/vnd.microsoft.portable-executable", "exe")),
+    (0, b"\xCF\xFA\xED\xFE", ContentType::new("application/x-mach-binary", "macho")),
+    (0, b"\xFE\xED\xFA\xCF", ContentType::new("application/x-mach-binary", "macho")),
+    (0, b"\xCA\xFE\xBA\xBE", ContentType::new("application/java-vm", "class")),
+    (0, b"\x00asm", ContentType::new("application/wasm", "wasm")),
+    (0, b"SQLite format 3\x00", ContentType::new("application/vnd.sqlite3", "sqlite")),
+    // Fonts
+    (0, b"wOFF", ContentType::new("font/woff", "woff")),
+    (0, b"wOF2", ContentType::new("font/woff2", "woff2")),
+    (0, b"OTTO", ContentType::new("font/otf", "otf")),
+    (0, b"\x00\x01\x00\x00\x00", ContentType::new("font/ttf", "ttf")),
+    // Encrypted containers
+    (0, b"-----BEGIN PGP MESSAGE-----", ContentType::new("application/pgp-encrypted", "asc")),
+    (0, b"age-encryption.org/", ContentType::new("application/x-age-encrypted", "age")),
+];
+
+/// Identify a payload from its leading bytes, falling back to a UTF-8 text
+/// heuristic and finally `application/octet-stream`
+pub fn sniff(data: &[u8]) -> ContentType {
+    if let Some(content_type) = sniff_container(data) {
+        return content_type;
+    }
+
+    for &(offset, magic, content_type) in SIGNATURES {
+        if data.len() >= offset + magic.len() && &data[offset..offset + magic.len()] == magic {
+            return content_type;
+        }
+    }
+
+    if is_mp3_frame(data) {
+        return ContentType::new("audio/mpeg", "mp3");
+    }
+
+    sniff_text(data).unwrap_or(OCTET_STREAM)
+}
+
+/// Formats that share a generic envelope (RIFF, ISO BMFF, ZIP, EBML, IFF)
+fn sniff_container(data: &[u8]) -> Option<ContentType> {
+    if data.len() >= 12 && &data[0..4] == b"RIFF" {
+        return match &data[8..12] {
+            b"WEBP" => Some(ContentType::new("image/webp", "webp")),
+            b"WAVE" => Some(ContentType::new("audio/wav", "wav")),
+            b"AVI " => Some(ContentType::new("video/x-msvideo", "avi")),
+            _ => None,
+        };
+    }
+
+    if data.len() >= 12 && &data[0..4] == b"FORM" {
+        return match &data[8..12] {
+            b"AIFF" | b"AIFC" => Some(ContentType::new("audio/aiff", "aiff")),
+            _ => None,
+        };
+    }
+
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return Some(match &data[8..12] {
+            b"heic" | b"heix" | b"mif1" | b"msf1" => ContentType::new("image/heic", "heic"),
+            b"avif" | b"avis" => ContentType::new("image/avif", "avif"),
+            b"M4A " | b"M4B " => ContentType::new("audio/mp4", "m4a"),
+            b"qt  " => ContentType::new("video/quicktime", "mov"),
+            b"3gp4" | b"3gp5" | b"3g2a" => ContentType::new("video/3gpp", "3gp"),
+            _ => ContentType::new("video/mp4", "mp4"),
+        });
+    }
+
+    if data.starts_with(b"\x1A\x45\xDF\xA3") {
+        let head = &data[..data.len().min(64)];
+        return Some(if contains(head, b"webm") {
+            ContentType::new("video/webm", "webm")
+        } else {
+            ContentType::new("video/x-matroska", "mkv")
+        });
+    }
+
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        return Some(sniff_zip(data));
+    }
+
+    None
+}
+
+/// ZIP-based formats, told apart by their well-known entries
+fn sniff_zip(data: &[u8]) -> ContentType {
+    // OpenDocument and EPUB store an uncompressed `mimetype` entry first
+    if data.len() > 38 && &data[30..38] == b"mimetype" {
+        let rest = &data[38..data.len().min(128)];
+        if rest.starts_with(b"application/epub+zip") {
+            return ContentType::new("application/epub+zip", "epub");
+        }
+        if rest.starts_with(b"application/vnd.oasis.opendocument.text") {
+            return ContentType::new("application/vnd.oasis.opendocument.text", "odt");
+        }
+        if rest.starts_with(b"application/vnd.oasis.opendocument.spreadsheet") {
+            return ContentType::new("application/vnd.oasis.opendocument.spreadsheet", "ods");
+        }
+        if rest.starts_with(b"application/vnd.oasis.opendocument.presentation") {
+            return ContentType::new("application/vnd.oasis.opendocument.presentation", "odp");
+        }
+    }
+
+    // Only entry names from local headers count: file contents may contain anything
+    let names = zip_entry_names(data);
+    let has_entry = |name: &[u8]| names.contains(&name);
+    let has_dir = |dir: &[u8]| names.iter().any(|entry| entry.starts_with(dir));
+
+    // Office Open XML: a [Content_Types].xml entry plus the part directory
+    if has_entry(b"[Content_Types].xml") {
+        if has_dir(b"word/") {
+            return ContentType::new(
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                "docx",
+            );
+        }
+        if has_dir(b"xl/") {
+            return ContentType::new(
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "xlsx",
+            );
+        }
+        if has_dir(b"ppt/") {
+            return ContentType::new(
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+                "pptx",
+            );
+        }
+    }
+    if has_entry(b"AndroidManifest.xml") {
+        return ContentType::new("application/vnd.android.package-archive", "apk");
+    }
+    if has_entry(b"META-INF/MANIFEST.MF") {
+        return ContentType::new("application/java-archive", "jar");
+    }
+
+    ContentType::new("application/zip", "zip")
+}
+
+/// Most local headers walked when sniffing a ZIP
+const MAX_ZIP_ENTRIES: usize = 64;
+
+/// File names of the leading ZIP local headers, up to the first entry whose
+/// size is deferred to a data descriptor (its end cannot be found)
+fn zip_entry_names(data: &[u8]) -> Vec<&[u8]> {
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| {
+        u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize
+    };
+
+    let mut names = Vec::new();
+    let mut pos = 0;
+    while names.len() < MAX_ZIP_ENTRIES
+        && data.len() >= pos + 30
+        && data[pos..pos + 4] == *b"PK\x03\x04"
+    {
+        let flags = u16_at(pos + 6);
+        let compressed_len = u32_at(pos + 18);
+        let name_len = u16_at(pos + 26);
+        let extra_len = u16_at(pos + 28);
+
+        let name_start = pos + 30;
+        let Some(name) = data.get(name_start..name_start + name_len) else {
+            break;
+        };
+        names.push(name);
+
+        if flags & 0x0008 != 0 {
+            break;
+        }
+        pos = (name_start + name_len + extra_len).saturating_add(compressed_len);
+    }
+    names
+}
+
+/// MPEG audio frame sync without an ID3 tag (layer III, any version)
+fn is_mp3_frame(data: &[u8]) -> bool {
+    data.len() >= 4 && data[0] == 0xFF && matches!(data[1], 0xFB | 0xF3 | 0xF2 | 0xE3)
+}
+
+/// Valid UTF-8 (or BOM-marked UTF-16) without binary control characters
+fn sniff_text(data: &[u8]) -> Option<ContentType> {
+    if data.starts_with(b"\xFF\xFE") || data.starts_with(b"\xFE\xFF") {
+        return Some(ContentType::new("text/plain", "txt"));
+    }
+
+    let text = std::str::from_utf8(data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data)).ok()?;
+    if text.is_empty()
+        || text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0C'))
+    {
+        return None;
+    }
+
+    let trimmed = text.trim();
+    let lower: String = trimmed.chars().take(256).collect::<String>().to_ascii_lowercase();
+
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        return Some(ContentType::new("text/html", "html"));
+    }
+    if lower.starts_with("<svg") || (lower.starts_with("<?xml") && lower.contains("<svg")) {
+        return Some(ContentType::new("image/svg+xml", "svg"));
+    }
+    if lower.starts_with("<?xml") {
+        return Some(ContentType::new("application/xml", "xml"));
+    }
+    if (trimmed.starts_with('{') && trimmed.ends_with('}'))
+        || (trimmed.starts_with('[') && trimmed.ends_with(']'))
+    {
+        return Some(ContentType::new("application/json", "json"));
+    }
+    if lower.starts_with("-----begin ") {
+        return Some(ContentType::new("application/x-pem-file", "pem"));
+    }
+    if trimmed.starts_with("#!") {
+        return Some(ContentType::new("text/x-shellscript", "sh"));
+    }
+
+    Some(ContentType::new("text/plain", "txt"))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_binary_signatures() {
+        let cases: &[(&[u8], &str)] = &[
+            (b"\x89PNG\r\n\x1a\n\x00\x00", "png"),
+            (b"%PDF-1.7\n%\xE2\xE3", "pdf"),
+            (b"\x1F\x8B\x08\x00\x00\x00", "gz"),
+            (b"ID3\x04\x00\x00", "mp3"),
+            (b"\x00\x00\x00\x18ftypmp42\x00\x00", "mp4"),
+            (b"RIFF\x24\x00\x00\x00WAVEfmt ", "wav"),
+            (b"SQLite format 3\x00\x10\x00", "sqlite"),
+            (b"BM\x36\x00\x0C\x00\x00\x00\x00\x00", "bmp"),
+        ];
+
+        for (data, extension) in cases {
+            assert_eq!(sniff(data).extension, *extension);
+        }
+    }
+
+    /// Stored ZIP local header and body
+    fn zip_entry(name: &str, body: &[u8]) -> Vec<u8> {
+        let mut entry = b"PK\x03\x04".to_vec();
+        entry.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        entry.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        entry.extend_from_slice(&0u16.to_le_bytes());
+        entry.extend_from_slice(name.as_bytes());
+        entry.extend_from_slice(body);
+        entry
+    }
+
+    #[test]
+    fn test_sniff_tar_and_zip_variants() {
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar).mime, "application/x-tar");
+
+        let docx = [
+            zip_entry("[Content_Types].xml", b"<Types/>"),
+            zip_entry("word/document.xml", b"<w:document/>"),
+        ]
+        .concat();
+        assert_eq!(sniff(&docx).extension, "docx");
+
+        let xlsx = [
+            zip_entry("[Content_Types].xml", b"<Types/>"),
+            zip_entry("_rels/.rels", b"<Relationships/>"),
+            zip_entry("xl/workbook.xml", b"<workbook/>"),
+        ]
+        .concat();
+        assert_eq!(sniff(&xlsx).extension, "xlsx");
+
+        // Part names inside file contents, or without [Content_Types].xml, are not OOXML
+        let notes = zip_entry("notes.txt", b"see word/document.xml and [Content_Types].xml");
+        assert_eq!(sniff(&notes).extension, "zip");
+        let loose = zip_entry("word/document.xml", b"<w:document/>");
+        assert_eq!(sniff(&loose).extension, "zip");
+
+        let jar = zip_entry("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0");
+        assert_eq!(sniff(&jar).extension, "jar");
+
+        let mut epub = b"PK\x03\x04".to_vec();
+        epub.extend_from_slice(&[0u8; 26]);
+        epub.extend_from_slice(b"mimetypeapplication/epub+zip");
+        assert_eq!(sniff(&epub).extension, "epub");
+
+        assert_eq!(sniff(b"PK\x03\x04plain archive").mime, "application/zip");
+    }
+
+    #[test]
+    fn test_sniff_text_heuristic() {
+        assert_eq!(sniff("héllo wörld\n".as_bytes()).mime, "text/plain");
+        assert_eq!(sniff(b"  {\"a\": [1, 2]}\n").mime, "application/json");
+        assert_eq!(sniff(b"<!DOCTYPE html><html></html>").extension, "html");
+        assert_eq!(sniff(b"\xEF\xBB\xBF<?xml version=\"1.0\"?><svg/>").extension, "svg");
+        assert_eq!(sniff(b"hi"), ContentType::new("text/plain", "txt"));
+    }
+
+    #[test]
+    fn test_sniff_unknown_binary() {
+        assert_eq!(sniff(b"\x00\x01\x02\x03\xFF\xFE\x80"), OCTET_STREAM);
+        assert_eq!(sniff(b""), OCTET_STREAM);
+    }
+}
@@ -1,4 +1,26 @@
+use crate::noise::generate_natural_cover;
+use crate::sniff::sniff;
 use image::{DynamicImage, ImageBuffer, Rgb};
+use serde::{Deserialize, Serialize};
+
+/// Procedural pattern used when generating cover and dataset images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverPattern {
+    /// Smooth linear gradient (legacy, trivially detectable)
+    #[default]
+    Gradient,
+    /// Seeded fractal noise with texture and simulated sensor noise
+    Natural,
+}
+
+/// Generate a cover image using the given procedural pattern
+pub fn generate_cover(pattern: CoverPattern, width: u32, height: u32, seed: u64) -> DynamicImage {
+    match pattern {
+        CoverPattern::Gradient => generate_cover_image(width, height),
+        CoverPattern::Natural => generate_natural_cover(width, height, seed),
+    }
+}
 
 /// Generate a default cover image (gradient pattern)
 pub fn generate_cover_image(width: u32, height: u32) -> DynamicImage {
@@ -11,51 +33,46 @@ pub fn generate_cover_image(width: u32, height: u32) -> DynamicImage {
     DynamicImage::ImageRgb8(img)
 }
 
-/// Detect MIME type from magic bytes
-pub fn get_mime_type(data: &[u8]) -> &'static str {
-    if data.len() < 4 {
-        return "application/octet-stream";
+/// Texture score of an image in [0, 1]: mean absolute luminance difference
+/// between horizontally and vertically adjacent pixels. Busy, noisy covers
+/// score higher and hide LSB changes better than smooth ones.
+pub fn texture_score(image: &DynamicImage) -> f64 {
+    let luma = image.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width < 2 || height < 2 {
+        return 0.0;
     }
 
-    // PNG
-    if data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
-        return "image/png";
+    let mut total = 0u64;
+    let mut count = 0u64;
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            let p = luma.get_pixel(x, y)[0] as i32;
+            let right = luma.get_pixel(x + 1, y)[0] as i32;
+            let below = luma.get_pixel(x, y + 1)[0] as i32;
+            total += ((p - right).abs() + (p - below).abs()) as u64;
+            count += 2;
+        }
     }
 
-    // JPEG
-    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
-        return "image/jpeg";
-    }
-
-    // GIF
-    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
-        return "image/gif";
-    }
-
-    // WebP
-    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
-        return "image/webp";
-    }
-
-    // BMP
-    if data.starts_with(b"BM") {
-        return "image/bmp";
-    }
+    total as f64 / count as f64 / 255.0
+}
 
-    "application/octet-stream"
+/// Detect MIME type from magic bytes (see `sniff` for the suggested extension)
+pub fn get_mime_type(data: &[u8]) -> &'static str {
+    sniff(data).mime
 }
 
-/// Generate a synthetic dataset image (for stress testing)
-pub fn generate_dataset_image(index: usize, base_width: u32, base_height: u32) -> DynamicImage {
-    let seed = index as u32;
-    let width = base_width + (seed % 200);
-    let height = base_height + ((seed * 7) % 200);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let img = ImageBuffer::from_fn(width, height, |x, y| {
-        let r = ((x * seed) % 256) as u8;
-        let g = ((y * seed * 3) % 256) as u8;
-        let b = (((x + y) * seed * 7) % 256) as u8;
-        Rgb([r, g, b])
-    });
-    DynamicImage::ImageRgb8(img)
+    #[test]
+    fn test_texture_score_prefers_natural_covers() {
+        let smooth = texture_score(&generate_cover_image(128, 128));
+        let natural = texture_score(&generate_natural_cover(128, 128, 3));
+
+        assert!(smooth < natural, "smooth {} vs natural {}", smooth, natural);
+        assert!((0.0..=1.0).contains(&natural));
+    }
 }
@@ -0,0 +1,443 @@
+use crate::error::{Result, StegoError};
+use crate::noise::SeededRng;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb};
+use sha2::{Digest, Sha256};
+
+/// Side of the repeating watermark tile, in pixels
+const TILE: usize = 64;
+/// Side of one spreading cell inside the tile, in pixels
+const CELL: usize = 4;
+const CELLS_PER_SIDE: usize = TILE / CELL;
+const CELL_COUNT: usize = CELLS_PER_SIDE * CELLS_PER_SIDE;
+/// Cells carrying a known +1 bit, used to find the tile grid and score confidence
+const SYNC_CELLS: usize = 128;
+/// Radius of the box blur subtracted from luminance before folding
+const HIGHPASS_RADIUS: usize = 3;
+/// Ratio between neighbouring coarse scale candidates
+const SCALE_STEP: f32 = 1.04;
+
+/// Maximum watermark payload size in bytes
+pub const MAX_WATERMARK_BYTES: usize = 8;
+
+/// Default embedding strength in 8-bit luminance levels
+pub const DEFAULT_WATERMARK_STRENGTH: f32 = 3.0;
+
+/// Result of a watermark extraction
+#[derive(Debug, Clone)]
+pub struct WatermarkResult {
+    pub payload: Vec<u8>,
+    /// Normalised sync correlation in [0, 1]; unmarked images typically score below 0.45
+    pub confidence: f32,
+    /// Estimated scale of the image relative to the marked original
+    pub scale: f32,
+    /// Tile grid offset at which the watermark was found
+    pub offset: (u32, u32),
+}
+
+#[derive(Clone, Copy)]
+enum CellRole {
+    Sync,
+    Bit(usize),
+}
+
+/// Keyed assignment of ±1 chips and roles (sync or message bit) to tile cells
+struct Layout {
+    chips: [f32; CELL_COUNT],
+    roles: [CellRole; CELL_COUNT],
+}
+
+impl Layout {
+    fn new(key: &str, num_bits: usize) -> Self {
+        let digest = Sha256::new()
+            .chain_update(b"phase1-steg/watermark")
+            .chain_update(key.as_bytes())
+            .finalize();
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest[..8]);
+        let mut rng = SeededRng::new(u64::from_be_bytes(seed));
+
+        let mut chips = [0.0f32; CELL_COUNT];
+        for chip in chips.iter_mut() {
+            *chip = if rng.next_u64() & 1 == 1 { 1.0 } else { -1.0 };
+        }
+
+        // Shuffle cells so sync and message cells are spread over the tile
+        let mut cells: Vec<usize> = (0..CELL_COUNT).collect();
+        for i in (1..cells.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            cells.swap(i, j);
+        }
+
+        let mut roles = [CellRole::Sync; CELL_COUNT];
+        for (n, &cell) in cells.iter().enumerate().skip(SYNC_CELLS) {
+            roles[cell] = CellRole::Bit((n - SYNC_CELLS) % num_bits);
+        }
+
+        Self { chips, roles }
+    }
+}
+
+/// Embed a small, robust watermark that survives recompression, scaling and cropping.
+///
+/// Unlike `embed`, the payload is spread over a keyed ±1 pattern of 4x4 cells
+/// tiled across the whole luminance plane, so it lives in mid frequencies
+/// instead of LSBs. Strength is in luminance levels and is raised in textured
+/// areas where changes are less visible.
+pub fn embed_watermark(
+    cover: &DynamicImage,
+    payload: &[u8],
+    key: &str,
+    strength: f32,
+) -> Result<DynamicImage> {
+    check_payload_len(payload.len())?;
+
+    let (width, height) = cover.dimensions();
+    let rgb_img = cover.to_rgb8();
+    let luma = luminance(&rgb_img);
+    let layout = Layout::new(key, payload.len() * 8);
+
+    let bit_sign = |bit: usize| {
+        if (payload[bit / 8] >> (7 - bit % 8)) & 1 == 1 {
+            1.0
+        } else {
+            -1.0
+        }
+    };
+
+    let w = width as usize;
+    let h = height as usize;
+    let img = ImageBuffer::from_fn(width, height, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        let cell = ((y % TILE) / CELL) * CELLS_PER_SIDE + (x % TILE) / CELL;
+        let sign = match layout.roles[cell] {
+            CellRole::Sync => 1.0,
+            CellRole::Bit(bit) => bit_sign(bit),
+        };
+
+        // Local activity: stronger where neighbouring luminance varies more
+        let at = |xx: usize, yy: usize| luma[yy.min(h - 1) * w + xx.min(w - 1)];
+        let here = at(x, y);
+        let activity = (here - at(x + 1, y)).abs() + (here - at(x, y + 1)).abs();
+        let mask = 0.75 + (activity / 24.0).min(0.75);
+
+        let delta = strength * mask * layout.chips[cell] * sign;
+        let pixel = rgb_img.get_pixel(x as u32, y as u32);
+        Rgb([0, 1, 2].map(|c| (pixel[c] as f32 + delta).round().clamp(0.0, 255.0) as u8))
+    });
+
+    Ok(DynamicImage::ImageRgb8(img))
+}
+
+/// Recover a watermark of `payload_len` bytes, searching over tile offset and scale
+pub fn extract_watermark(image: &DynamicImage, payload_len: usize, key: &str) -> Result<WatermarkResult> {
+    check_payload_len(payload_len)?;
+
+    let (width, height) = image.dimensions();
+    if (width as usize) < CELL * 4 || (height as usize) < CELL * 4 {
+        return Err(StegoError::ExtractionFailed(
+            "Image too small for watermark".to_string(),
+        ));
+    }
+
+    let residual = highpass(&luminance(&image.to_rgb8()), width as usize, height as usize);
+    let layout = Layout::new(key, payload_len * 8);
+
+    let search = |scale: f32, best: &mut Option<Candidate>| {
+        let folded = fold(&residual, width as usize, height as usize, scale);
+        let sums = CellSums::new(&folded);
+
+        for dy in 0..TILE {
+            for dx in 0..TILE {
+                let score = sync_correlation(&sums, &layout, dx, dy);
+                if best.as_ref().is_none_or(|b| score > b.score) {
+                    *best = Some(Candidate {
+                        score,
+                        scale,
+                        offset: (dx, dy),
+                        cells: sums.cells(dx, dy),
+                    });
+                }
+            }
+        }
+    };
+
+    // Unscaled images are by far the common case; skip the scale search when clearly marked
+    let mut best = None;
+    search(1.0, &mut best);
+    if best.as_ref().is_some_and(|b| b.score < 0.5) {
+        for scale in scale_candidates() {
+            search(scale, &mut best);
+        }
+
+        // Refine around the best coarse scale so cells line up across the whole image
+        let coarse = best.as_ref().map(|b| b.scale).unwrap_or(1.0);
+        for step in -4..=4 {
+            if step != 0 {
+                search(coarse * SCALE_STEP.powf(step as f32 / 8.0), &mut best);
+            }
+        }
+    }
+
+    let Candidate {
+        score,
+        scale,
+        offset: (dx, dy),
+        cells,
+    } = best.expect("unscaled search always yields a candidate");
+
+    let mut estimates = vec![0.0f32; payload_len * 8];
+    for (cell, &value) in cells.iter().enumerate() {
+        if let CellRole::Bit(bit) = layout.roles[cell] {
+            estimates[bit] += value * layout.chips[cell];
+        }
+    }
+
+    let mut payload = vec![0u8; payload_len];
+    for (bit, estimate) in estimates.iter().enumerate() {
+        if *estimate > 0.0 {
+            payload[bit / 8] |= 1 << (7 - bit % 8);
+        }
+    }
+
+    Ok(WatermarkResult {
+        payload,
+        confidence: score.clamp(0.0, 1.0),
+        scale,
+        offset: (dx as u32, dy as u32),
+    })
+}
+
+fn check_payload_len(len: usize) -> Result<()> {
+    if len == 0 {
+        return Err(StegoError::InvalidWatermark(
+            "Watermark payload must not be empty".to_string(),
+        ));
+    }
+    if len > MAX_WATERMARK_BYTES {
+        return Err(StegoError::CapacityExceeded {
+            needed: len as u64,
+            available: MAX_WATERMARK_BYTES as u64,
+        });
+    }
+    Ok(())
+}
+
+fn luminance(rgb_img: &image::RgbImage) -> Vec<f32> {
+    rgb_img
+        .pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect()
+}
+
+/// Luminance minus its local box-blurred mean, removing most of the image content
+fn highpass(luma: &[f32], width: usize, height: usize) -> Vec<f32> {
+    // Summed-area table with a zero border row/column
+    let stride = width + 1;
+    let mut sat = vec![0.0f64; stride * (height + 1)];
+    for y in 0..height {
+        let mut row = 0.0f64;
+        for x in 0..width {
+            row += luma[y * width + x] as f64;
+            sat[(y + 1) * stride + x + 1] = sat[y * stride + x + 1] + row;
+        }
+    }
+
+    let r = HIGHPASS_RADIUS;
+    let mut out = vec![0.0f32; width * height];
+    for y in 0..height {
+        let (y0, y1) = (y.saturating_sub(r), (y + r + 1).min(height));
+        for x in 0..width {
+            let (x0, x1) = (x.saturating_sub(r), (x + r + 1).min(width));
+            let sum = sat[y1 * stride + x1] - sat[y0 * stride + x1] - sat[y1 * stride + x0]
+                + sat[y0 * stride + x0];
+            let mean = sum / ((y1 - y0) * (x1 - x0)) as f64;
+            out[y * width + x] = luma[y * width + x] - mean as f32;
+        }
+    }
+    out
+}
+
+/// Best tile alignment found so far during extraction
+struct Candidate {
+    score: f32,
+    scale: f32,
+    offset: (usize, usize),
+    cells: Vec<f32>,
+}
+
+/// Coarse scale candidates from 0.5x to 2x (1x is searched separately)
+fn scale_candidates() -> Vec<f32> {
+    let mut scales = Vec::new();
+    let mut s = 1.0f32;
+    while s < 2.0 {
+        s *= SCALE_STEP;
+        scales.push(s);
+        scales.push(1.0 / s);
+    }
+    scales
+}
+
+/// Average the residual of every tile-period block into one TILE x TILE tile
+fn fold(residual: &[f32], width: usize, height: usize, scale: f32) -> Vec<f32> {
+    let mut acc = vec![0.0f32; TILE * TILE];
+    let mut count = vec![0u32; TILE * TILE];
+
+    for y in 0..height {
+        let ty = ((y as f32 + 0.5) / scale) as usize % TILE;
+        for x in 0..width {
+            let tx = ((x as f32 + 0.5) / scale) as usize % TILE;
+            acc[ty * TILE + tx] += residual[y * width + x];
+            count[ty * TILE + tx] += 1;
+        }
+    }
+
+    for (value, n) in acc.iter_mut().zip(count) {
+        if n > 0 {
+            *value /= n as f32;
+        }
+    }
+    acc
+}
+
+/// Cyclic summed-area table over a folded tile, for O(1) cell sums at any offset
+struct CellSums {
+    sat: Vec<f32>,
+}
+
+impl CellSums {
+    const SIDE: usize = 2 * TILE + 1;
+
+    fn new(folded: &[f32]) -> Self {
+        let side = Self::SIDE;
+        let mut sat = vec![0.0f32; side * side];
+        for y in 0..2 * TILE {
+            let mut row = 0.0;
+            for x in 0..2 * TILE {
+                row += folded[(y % TILE) * TILE + x % TILE];
+                sat[(y + 1) * side + x + 1] = sat[y * side + x + 1] + row;
+            }
+        }
+        Self { sat }
+    }
+
+    fn cell(&self, dx: usize, dy: usize, cell: usize) -> f32 {
+        let side = Self::SIDE;
+        let x0 = dx + (cell % CELLS_PER_SIDE) * CELL;
+        let y0 = dy + (cell / CELLS_PER_SIDE) * CELL;
+        let (x1, y1) = (x0 + CELL, y0 + CELL);
+        self.sat[y1 * side + x1] - self.sat[y0 * side + x1] - self.sat[y1 * side + x0]
+            + self.sat[y0 * side + x0]
+    }
+
+    fn cells(&self, dx: usize, dy: usize) -> Vec<f32> {
+        (0..CELL_COUNT).map(|cell| self.cell(dx, dy, cell)).collect()
+    }
+}
+
+/// Normalised correlation between sync cells and their expected chips
+fn sync_correlation(sums: &CellSums, layout: &Layout, dx: usize, dy: usize) -> f32 {
+    let mut dot = 0.0f32;
+    let mut energy = 0.0f32;
+    let mut n = 0usize;
+
+    for cell in 0..CELL_COUNT {
+        if let CellRole::Sync = layout.roles[cell] {
+            let value = sums.cell(dx, dy, cell);
+            dot += value * layout.chips[cell];
+            energy += value * value;
+            n += 1;
+        }
+    }
+
+    if energy <= f32::EPSILON {
+        return 0.0;
+    }
+    dot / (energy * n as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::generate_natural_cover;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::imageops::FilterType;
+    use std::io::Cursor;
+
+    const PAYLOAD: &[u8] = b"WM-2024";
+    const KEY: &str = "watermark key";
+
+    fn marked_cover() -> DynamicImage {
+        let cover = generate_natural_cover(320, 256, 11);
+        embed_watermark(&cover, PAYLOAD, KEY, DEFAULT_WATERMARK_STRENGTH).unwrap()
+    }
+
+    fn jpeg_recompress(img: &DynamicImage, quality: u8) -> DynamicImage {
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut Cursor::new(&mut bytes), quality)
+            .encode_image(&img.to_rgb8())
+            .unwrap();
+        image::load_from_memory(&bytes).unwrap()
+    }
+
+    fn assert_recovered(img: &DynamicImage) -> WatermarkResult {
+        let result = extract_watermark(img, PAYLOAD.len(), KEY).unwrap();
+        assert_eq!(result.payload, PAYLOAD, "confidence {}", result.confidence);
+        assert!(result.confidence > 0.5, "confidence {}", result.confidence);
+        result
+    }
+
+    #[test]
+    fn test_watermark_round_trip() {
+        let result = assert_recovered(&marked_cover());
+        assert_eq!(result.offset, (0, 0));
+        assert_eq!(result.scale, 1.0);
+    }
+
+    #[test]
+    fn test_watermark_survives_jpeg() {
+        assert_recovered(&jpeg_recompress(&marked_cover(), 50));
+    }
+
+    #[test]
+    fn test_watermark_survives_scaling() {
+        let marked = marked_cover();
+
+        let smaller = marked.resize_exact(240, 192, FilterType::Triangle);
+        let result = assert_recovered(&smaller);
+        assert!((result.scale - 0.75).abs() < 0.05, "scale {}", result.scale);
+
+        let larger = marked.resize_exact(480, 384, FilterType::CatmullRom);
+        assert_recovered(&larger);
+    }
+
+    #[test]
+    fn test_watermark_survives_cropping_and_jpeg() {
+        let cropped = marked_cover().crop_imm(37, 21, 200, 180);
+        assert_recovered(&jpeg_recompress(&cropped, 75));
+    }
+
+    #[test]
+    fn test_unmarked_image_has_low_confidence() {
+        let cover = generate_natural_cover(320, 256, 11);
+        let marked = extract_watermark(&marked_cover(), PAYLOAD.len(), KEY).unwrap();
+        let unmarked = extract_watermark(&cover, PAYLOAD.len(), KEY).unwrap();
+        let wrong_key = extract_watermark(&marked_cover(), PAYLOAD.len(), "other key").unwrap();
+
+        assert!(unmarked.confidence < 0.5, "confidence {}", unmarked.confidence);
+        assert!(wrong_key.confidence < 0.5, "confidence {}", wrong_key.confidence);
+        assert!(marked.confidence > unmarked.confidence + 0.3);
+    }
+
+    #[test]
+    fn test_watermark_payload_limits() {
+        let cover = generate_natural_cover(64, 64, 1);
+        assert!(matches!(
+            embed_watermark(&cover, &[0u8; MAX_WATERMARK_BYTES + 1], KEY, 4.0),
+            Err(StegoError::CapacityExceeded { .. })
+        ));
+        assert!(matches!(
+            embed_watermark(&cover, &[], KEY, 4.0),
+            Err(StegoError::InvalidWatermark(_))
+        ));
+    }
+}
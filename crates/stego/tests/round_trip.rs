@@ -0,0 +1,384 @@
+//! Property-based checks of every embedding mode against the public API:
+//! round-trip at every bit depth, capacity boundary, cropped carriers,
+//! header corruption and the PNG chunk / PDF update containers.
+
+use image::{DynamicImage, GenericImageView, ImageBuffer};
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::io::Cursor;
+use stego::{
+    Carrier, ChannelMask, EmbedMask, EmbedOptions, ExtractOptions, Rect, StegoError, HEADER_LEN,
+    TAG_LEN,
+};
+
+/// Deepest bit plane exercised by the sequential modes
+const MAX_LSB: u8 = 8;
+
+#[derive(Debug, Clone, Copy)]
+enum ColorType {
+    Luma8,
+    LumaA8,
+    Rgb8,
+    Rgba8,
+    Rgb16,
+}
+
+#[derive(Debug, Clone)]
+enum Mode {
+    Buffered,
+    Stream,
+    Masked(EmbedMask),
+    Keyed(String),
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    /// Bits per channel; masked and keyed modes always use 1
+    lsb: u8,
+    compress: bool,
+    mac_key: Option<Vec<u8>>,
+    mode: Mode,
+}
+
+impl Options {
+    fn mac_key(&self) -> Option<&[u8]> {
+        self.mac_key.as_deref()
+    }
+
+    /// Whether frame bits sit in the first samples of the LSB plane, in order
+    fn sequential(&self) -> bool {
+        matches!(self.mode, Mode::Buffered | Mode::Stream)
+    }
+}
+
+fn cover(width: u32, height: u32, color: ColorType, seed: u64) -> DynamicImage {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut sample = move |_, _| rng.next_u32();
+
+    match color {
+        ColorType::Luma8 => {
+            DynamicImage::ImageLuma8(ImageBuffer::from_fn(width, height, |x, y| {
+                image::Luma([sample(x, y) as u8])
+            }))
+        }
+        ColorType::LumaA8 => {
+            DynamicImage::ImageLumaA8(ImageBuffer::from_fn(width, height, |x, y| {
+                let v = sample(x, y);
+                image::LumaA([v as u8, (v >> 8) as u8])
+            }))
+        }
+        ColorType::Rgb8 => {
+            DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+                let v = sample(x, y);
+                image::Rgb([v as u8, (v >> 8) as u8, (v >> 16) as u8])
+            }))
+        }
+        ColorType::Rgba8 => {
+            DynamicImage::ImageRgba8(ImageBuffer::from_fn(width, height, |x, y| {
+                let v = sample(x, y);
+                image::Rgba([v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8])
+            }))
+        }
+        ColorType::Rgb16 => {
+            DynamicImage::ImageRgb16(ImageBuffer::from_fn(width, height, |x, y| {
+                let v = sample(x, y);
+                image::Rgb([v as u16, (v >> 16) as u16, v.rotate_left(8) as u16])
+            }))
+        }
+    }
+}
+
+fn color_type() -> impl Strategy<Value = ColorType> {
+    prop_oneof![
+        Just(ColorType::Luma8),
+        Just(ColorType::LumaA8),
+        Just(ColorType::Rgb8),
+        Just(ColorType::Rgba8),
+        Just(ColorType::Rgb16),
+    ]
+}
+
+fn embed_mask(width: u32, height: u32) -> impl Strategy<Value = EmbedMask> {
+    let channels = (1u8..8).prop_map(|bits| {
+        [ChannelMask::RED, ChannelMask::GREEN, ChannelMask::BLUE]
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| bits & (1 << i) != 0)
+            .map(|(_, c)| c)
+            .reduce(ChannelMask::union)
+            .unwrap()
+    });
+    let rect = (0..width, 0..height, 1..=width, 1..=height).prop_map(|(x, y, w, h)| Rect {
+        x,
+        y,
+        width: w,
+        height: h,
+    });
+
+    (channels, proptest::collection::vec(rect, 0..3)).prop_map(|(channels, rects)| {
+        rects
+            .into_iter()
+            .fold(EmbedMask::new().channels(channels), EmbedMask::exclude)
+    })
+}
+
+fn options(width: u32, height: u32) -> impl Strategy<Value = Options> {
+    let mode = prop_oneof![
+        Just(Mode::Buffered),
+        Just(Mode::Stream),
+        embed_mask(width, height).prop_map(Mode::Masked),
+        "[a-z]{1,12}".prop_map(Mode::Keyed),
+    ];
+    let mac_key = proptest::option::of(proptest::collection::vec(any::<u8>(), 1..40));
+
+    (1..=MAX_LSB, any::<bool>(), mac_key, mode).prop_map(|(lsb, compress, mac_key, mode)| Options {
+        lsb: if matches!(mode, Mode::Masked(_) | Mode::Keyed(_)) {
+            1
+        } else {
+            lsb
+        },
+        compress,
+        // Keyed traversal frames are never authenticated
+        mac_key: if matches!(mode, Mode::Keyed(_)) {
+            None
+        } else {
+            mac_key
+        },
+        mode,
+    })
+}
+
+/// Cover dimensions, colour type, pixel seed, options and secret
+fn case() -> impl Strategy<Value = (u32, u32, ColorType, u64, Options, Vec<u8>)> {
+    (20u32..72, 20u32..72).prop_flat_map(|(width, height)| {
+        (
+            Just(width),
+            Just(height),
+            color_type(),
+            any::<u64>(),
+            options(width, height),
+            proptest::collection::vec(any::<u8>(), 0..600),
+        )
+    })
+}
+
+fn embed(
+    cover: &DynamicImage,
+    secret: &[u8],
+    options: &Options,
+) -> stego::Result<(DynamicImage, stego::CoverInfo)> {
+    let (lsb, compress) = (options.lsb, options.compress);
+    match &options.mode {
+        Mode::Buffered => match options.mac_key() {
+            Some(key) => stego::embed_authenticated(cover, secret, lsb, compress, key),
+            None => stego::embed(cover, secret, lsb, compress),
+        },
+        Mode::Stream => stego::embed_stream(cover, secret, lsb, compress, options.mac_key()),
+        Mode::Masked(mask) => {
+            stego::embed_masked(cover, secret, compress, mask, options.mac_key())
+        }
+        Mode::Keyed(passphrase) => stego::embed_keyed(cover, secret, passphrase, compress),
+    }
+}
+
+fn extract(stego: &DynamicImage, options: &Options) -> stego::Result<Vec<u8>> {
+    let (lsb, compress) = (options.lsb, options.compress);
+    match &options.mode {
+        Mode::Buffered | Mode::Masked(_) => {
+            stego::extract_verified(stego, lsb, compress, options.mac_key())
+        }
+        Mode::Stream => {
+            let mut out = Vec::new();
+            stego::extract_stream(stego, lsb, compress, options.mac_key(), &mut out)?;
+            Ok(out)
+        }
+        Mode::Keyed(passphrase) => stego::extract_keyed(stego, passphrase, compress),
+    }
+}
+
+/// Bytes the frame for `secret` occupies in the carrier
+fn frame_len(secret: &[u8], options: &Options) -> u64 {
+    let tag_len = if options.mac_key.is_some() {
+        TAG_LEN
+    } else {
+        0
+    };
+    stego::required_capacity(secret, options.compress).unwrap() + tag_len as u64
+}
+
+/// Carrier capacity the embedding mode offers on a `width` x `height` cover
+fn mode_capacity(width: u32, height: u32, options: &Options) -> u64 {
+    match &options.mode {
+        Mode::Masked(mask) => stego::masked_capacity(width, height, mask).unwrap(),
+        _ => stego::capacity_bytes(width, height, options.lsb),
+    }
+}
+
+fn encode_png(image: &DynamicImage) -> Vec<u8> {
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+    png
+}
+
+/// Single-page-tree PDF with a classic xref table
+fn create_pdf() -> Vec<u8> {
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for object in [
+        "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+        "2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n",
+    ] {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(object.as_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(b"xref\n0 3\n0000000000 65535 f\r\n");
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n\r\n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!("trailer\n<< /Size 3 /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", xref).as_bytes(),
+    );
+    pdf
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(96))]
+
+    #[test]
+    fn embed_then_extract_round_trips(
+        (width, height, color, seed, options, secret) in case()
+    ) {
+        let cover = cover(width, height, color, seed);
+        let fits = frame_len(&secret, &options) <= mode_capacity(width, height, &options);
+
+        match embed(&cover, &secret, &options) {
+            Ok((stego, info)) => {
+                prop_assert!(fits);
+                prop_assert_eq!(stego.dimensions(), (width, height));
+                prop_assert_eq!(info.capacity_bytes, mode_capacity(width, height, &options));
+                prop_assert_eq!(extract(&stego, &options)?, secret);
+            }
+            Err(StegoError::CapacityExceeded { needed, available }) => {
+                prop_assert!(!fits);
+                prop_assert!(needed > available);
+            }
+            Err(StegoError::InvalidMask(_)) => {
+                // Regions covering both descriptor anchors leave no room at all
+                prop_assert!(matches!(options.mode, Mode::Masked(_)));
+                prop_assert_eq!(mode_capacity(width, height, &options), 0);
+            }
+            Err(e) => prop_assert!(false, "unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn capacity_boundary_is_exact(
+        width in 1u32..64,
+        height in 1u32..64,
+        lsb in 1..=MAX_LSB,
+        mac in any::<bool>(),
+    ) {
+        let capacity = stego::capacity_bytes(width, height, lsb) as usize;
+        let overhead = HEADER_LEN + if mac { TAG_LEN } else { 0 };
+        prop_assume!(capacity >= overhead);
+
+        let cover = cover(width, height, ColorType::Rgb8, 0);
+        let embed = |secret: &[u8]| match mac {
+            true => stego::embed_authenticated(&cover, secret, lsb, false, b"k"),
+            false => stego::embed(&cover, secret, lsb, false),
+        };
+
+        let exact = vec![0x5Au8; capacity - overhead];
+        let (stego, _info) = embed(&exact)?;
+        let key = mac.then_some(b"k".as_slice());
+        prop_assert_eq!(stego::extract_verified(&stego, lsb, false, key)?, exact);
+
+        let over = vec![0x5Au8; capacity - overhead + 1];
+        let result = embed(&over);
+        prop_assert!(
+            matches!(
+                result,
+                Err(StegoError::CapacityExceeded { needed, available })
+                    if needed == capacity as u64 + 1 && available == capacity as u64
+            ),
+            "expected CapacityExceeded"
+        );
+    }
+
+    #[test]
+    fn cropped_carrier_never_yields_wrong_secret(
+        (width, height, color, seed, options, secret) in case(),
+        keep_rows in 1u32..72,
+    ) {
+        let cover = cover(width, height, color, seed);
+        let Ok((stego, _info)) = embed(&cover, &secret, &options) else {
+            return Ok(());
+        };
+
+        let keep_rows = keep_rows.min(height);
+        let cropped = stego.crop_imm(0, 0, width, keep_rows);
+
+        if let Ok(recovered) = extract(&cropped, &options) {
+            prop_assert_eq!(&recovered, &secret);
+        }
+        if options.sequential()
+            && frame_len(&secret, &options) > stego::capacity_bytes(width, keep_rows, options.lsb)
+        {
+            prop_assert!(extract(&cropped, &options).is_err());
+        }
+    }
+
+    #[test]
+    fn header_corruption_is_rejected(
+        (width, height, color, seed, options, secret) in case(),
+        bit in 0usize..HEADER_LEN * 8,
+    ) {
+        prop_assume!(options.sequential());
+        let cover = cover(width, height, color, seed);
+        let Ok((stego, _info)) = embed(&cover, &secret, &options) else {
+            return Ok(());
+        };
+
+        // Header bits fill the low `lsb` bits of the first samples, MSB first
+        let lsb = options.lsb as usize;
+        let mut raw = stego.to_rgb8();
+        raw.as_mut()[bit / lsb] ^= 1 << (lsb - 1 - bit % lsb);
+        let corrupted = DynamicImage::ImageRgb8(raw);
+
+        prop_assert!(extract(&corrupted, &options).is_err());
+        prop_assert!(!stego::probe(&corrupted).payload_present);
+    }
+
+    #[test]
+    fn container_carriers_round_trip(
+        secret in proptest::collection::vec(any::<u8>(), 0..4096),
+        compress in any::<bool>(),
+        mac_key in proptest::option::of(proptest::collection::vec(any::<u8>(), 1..40)),
+        pdf in any::<bool>(),
+        seed in any::<u64>(),
+    ) {
+        let mut embed_options = EmbedOptions::new().compression(compress.into());
+        let mut extract_options = ExtractOptions::new().compression(compress.into());
+        if let Some(key) = &mac_key {
+            embed_options = embed_options.mac_key(key.clone());
+            extract_options = extract_options.mac_key(key.clone());
+        }
+
+        let (stego, carrier) = if pdf {
+            (stego::embed_pdf_update(&create_pdf(), &secret, &embed_options)?.0, Carrier::PdfUpdate)
+        } else {
+            let cover_png = encode_png(&cover(24, 24, ColorType::Rgb8, seed));
+            prop_assert_eq!(stego::container_carrier(&cover_png), None);
+            (stego::embed_png_chunk(&cover_png, &secret, &embed_options)?.0, Carrier::PngChunk)
+        };
+
+        prop_assert_eq!(stego::container_carrier(&stego), Some(carrier));
+        prop_assert_eq!(stego::extract_container(&stego, &extract_options)?, secret);
+        if mac_key.is_some() {
+            let unkeyed = ExtractOptions::new().compression(compress.into());
+            prop_assert!(stego::extract_container(&stego, &unkeyed).is_err());
+        }
+    }
+}
@@ -4,6 +4,7 @@ let statusPollInterval = 1000;
 let currentStressTest = null;
 let stegoImageData = null;
 let recoveredImageData = null;
+let recoveredExtension = 'bin';
 
 // Charts
 let throughputChart = null;
@@ -13,6 +14,7 @@ let distributionChart = null;
 // Initialize
 document.addEventListener('DOMContentLoaded', () => {
     initTabs();
+    initApiKey();
     initEmbedHandler();
     initExtractHandler();
     initClusterStatus();
@@ -20,6 +22,24 @@ document.addEventListener('DOMContentLoaded', () => {
     initCharts();
 });
 
+// ============================================================================
+// API Key
+// ============================================================================
+
+function initApiKey() {
+    const input = document.getElementById('api-key');
+    input.value = localStorage.getItem('apiKey') || '';
+    input.addEventListener('change', () => {
+        localStorage.setItem('apiKey', input.value.trim());
+    });
+}
+
+// Authorization header for /api and /admin requests, merged into `headers`
+function authHeaders(headers = {}) {
+    const key = localStorage.getItem('apiKey');
+    return key ? { ...headers, 'Authorization': `Bearer ${key}` } : headers;
+}
+
 // ============================================================================
 // Tab Management
 // ============================================================================
@@ -97,6 +117,7 @@ async function embedSecret(nodeUrl, file) {
     
     const response = await fetch(`${nodeUrl}/api/embed`, {
         method: 'POST',
+        headers: authHeaders(),
         body: formData
     });
     
@@ -154,7 +175,7 @@ function initExtractHandler() {
     
     document.getElementById('download-recovered').addEventListener('click', () => {
         if (recoveredImageData) {
-            downloadFile(recoveredImageData, 'recovered.png', 'image/png');
+            downloadFile(recoveredImageData, `recovered.${recoveredExtension}`);
         }
     });
 }
@@ -165,6 +186,7 @@ async function extractSecret(nodeUrl, file) {
     
     const response = await fetch(`${nodeUrl}/api/extract`, {
         method: 'POST',
+        headers: authHeaders(),
         body: formData
     });
     
@@ -180,6 +202,7 @@ function displayExtractResult(result) {
     document.getElementById('extract-result').classList.remove('hidden');
     
     recoveredImageData = `data:${result.recovered_mime};base64,${result.recovered_b64}`;
+    recoveredExtension = result.recovered_extension || 'bin';
     
     if (result.recovered_mime.startsWith('image/')) {
         document.getElementById('recovered-preview').src = recoveredImageData;
@@ -292,7 +315,7 @@ async function failNode(nodeId, ip, port) {
     try {
         const response = await fetch(`http://${ip}:${port}/admin/fail`, {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' },
+            headers: authHeaders({ 'Content-Type': 'application/json' }),
             body: JSON.stringify({ action: actionType })
         });
         
@@ -313,7 +336,7 @@ async function restoreNode(nodeId, ip, port) {
     try {
         const response = await fetch(`http://${ip}:${port}/admin/restore`, {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' }
+            headers: authHeaders({ 'Content-Type': 'application/json' })
         });
         
         if (response.ok) {
@@ -502,6 +525,18 @@ async function startStressTest() {
         clusterInfo = 'Cluster info unavailable';
     }
     
+    // Dataset size comes from the cluster config (identical on every node)
+    let datasetCount = 50;
+    try {
+        const node = await selectBestNode();
+        const listing = await fetch(`${node}/api/dataset`, { headers: authHeaders() });
+        if (listing.ok) {
+            datasetCount = (await listing.json()).count || datasetCount;
+        }
+    } catch (error) {
+        console.warn('Could not get dataset listing:', error);
+    }
+    
     currentStressTest = {
         running: true,
         totalRequests: numClients * reqsPerClient,
@@ -514,7 +549,8 @@ async function startStressTest() {
         reservoirSize: 5000, // Keep 5000 samples for accurate percentiles even at 500k requests
         latencyCount: 0,
         // NEW: Track which nodes received requests (for load balancing verification)
-        nodeDistribution: {}
+        nodeDistribution: {},
+        datasetCount
     };
     
     // Reset charts
@@ -586,11 +622,12 @@ async function stressWorker(numRequests, operation) {
         try {
             // Get the node being used (routed through Raft leader)
             selectedNode = await selectBestNode();
-            const datasetIdx = Math.floor(Math.random() * 50);
+            const datasetIdx = Math.floor(Math.random() * currentStressTest.datasetCount);
             
             // Fetch dataset image with failover
             const imgBlob = await requestWithFailover(selectedNode, async (node) => {
                 const imgResponse = await fetch(`${node}/api/dataset/${datasetIdx}`, {
+                    headers: authHeaders(),
                     timeout: 5000
                 });
                 if (!imgResponse.ok) {